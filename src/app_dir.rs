@@ -0,0 +1,193 @@
+use crate::error::{IpaError, Result};
+use crate::icon_extractor::{self, IconWriter};
+use crate::plist_parser::{self, PlistInfo};
+use crate::{asset_catalog, check_bundle_id, finish_icon, icon_stem, info_from_plist, prepare_icon, save_images, IpaInfo, ParseOptions};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Parses an unpacked `.app` bundle on disk, e.g. straight out of a local build
+/// Info.plist, the localized names and the icon (the largest matching loose PNG,
+/// else the image set in Assets.car) are read from the directory and go through
+/// the same icon options as `parse_ipa`. There is no archive, so `AppSize` is unset,
+/// `InstalledSize` totals the files under `app_dir`, `FileName` is the directory's
+/// name and hash-named icons use a digest of Info.plist. Options that inspect the
+/// archive or the executable (Mach-O checks, provisioning profiles, Settings.bundle,
+/// entry scans, icon strips, `include_file_hash`) are ignored
+pub fn parse_app_dir<P: AsRef<Path>>(app_dir: P, options: &ParseOptions) -> Result<IpaInfo> {
+    let app_dir = app_dir.as_ref();
+    let plist_data = match fs::read(app_dir.join("Info.plist")) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(IpaError::InfoPlistNotFound),
+        Err(e) => return Err(e.into()),
+    };
+    let dict = plist_parser::read_plist_dict(&plist_data)?
+        .ok_or_else(|| IpaError::InvalidIpa("Info.plist is not a dictionary".to_string()))?;
+    let mut plist_info = plist_parser::parse_info_dict(&dict)?;
+    check_bundle_id(&plist_info.bundle_identifier, options)?;
+    plist_info.localized_names = localized_names(app_dir)?.into_iter().collect();
+    
+    let mut warnings = Vec::new();
+    let icon = if options.extract_icons {
+        find_icon(app_dir, &plist_info, options, &mut warnings)?
+    } else {
+        None
+    };
+    let (icon_entry, icon) = icon.unzip();
+    let (icon, icon_details) = prepare_icon(icon, options)?;
+    let (icon, icon_checksum) = finish_icon(icon, options)?;
+    
+    let mut info = IpaInfo {
+        installed_size: directory_size(app_dir)?,
+        icon_width: icon_details.dimensions.map(|(width, _)| width),
+        icon_height: icon_details.dimensions.map(|(_, height)| height),
+        icon_phash: icon_details.phash,
+        icon_is_placeholder: icon_details.is_placeholder,
+        icon_has_transparency: icon_details.has_transparency,
+        icon_checksum,
+        file_name: app_dir.file_name().and_then(|n| n.to_str()).map(String::from),
+        warnings,
+        ..info_from_plist(plist_info)
+    };
+    
+    if icon.is_some() {
+        let writer = IconWriter::direct();
+        let stem = icon_stem(&mut info, options, Some(writer.slot(0)), || Ok(options.hash_algorithm.digest(&plist_data)))?;
+        save_images(&mut info, icon, icon_entry, None, &stem, options, writer.slot(0))?;
+    }
+    Ok(info)
+}
+
+/// The largest PNG directly inside the bundle that matches the declared icon
+/// names, by file size (ties go to the first name), else the Assets.car image set
+fn find_icon(
+    app_dir: &Path,
+    plist_info: &PlistInfo,
+    options: &ParseOptions,
+    warnings: &mut Vec<String>,
+) -> Result<Option<(String, Vec<u8>)>> {
+    let mut largest: Option<(u64, String)> = None;
+    for entry in fs::read_dir(app_dir)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if crate::archive_scan::is_macos_junk(&name)
+            || !name.ends_with(".png")
+            || !icon_extractor::is_icon_match(&name, &plist_info.icon_files)
+        {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let size = metadata.len();
+        if largest.as_ref().is_none_or(|(s, n)| size > *s || (size == *s && name < *n)) {
+            largest = Some((size, name));
+        }
+    }
+    
+    if let Some((_, name)) = largest {
+        let icon = crate::png_normalizer::normalize_cgbi_png(&fs::read(app_dir.join(&name))?, options.icon_decode_limits)?;
+        return Ok(Some((name, icon)));
+    }
+    
+    let car_path = app_dir.join("Assets.car");
+    if !car_path.is_file() {
+        return Ok(None);
+    }
+    let set_name = plist_info.icon_asset_name.as_deref().unwrap_or(icon_extractor::DEFAULT_ICON_SET);
+    match asset_catalog::find_icon(&fs::read(&car_path)?, set_name, options.icon_decode_limits) {
+        Ok(icon) => Ok(icon.map(|icon| {
+            if icon.fallback {
+                warnings.push(format!(
+                    "{} has no {} image set; using {}, which may not be the app icon",
+                    car_path.display(),
+                    set_name,
+                    icon.name
+                ));
+            }
+            (icon.name, icon.png)
+        })),
+        Err(e) => {
+            warnings.push(format!("could not read {}: {}", car_path.display(), e));
+            Ok(None)
+        }
+    }
+}
+
+/// CFBundleDisplayName (else CFBundleName) from each `<lang>.lproj/InfoPlist.strings`
+/// Tables that can't be read or parsed are skipped
+fn localized_names(app_dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut names = BTreeMap::new();
+    for entry in fs::read_dir(app_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(language) = file_name.to_str().and_then(|n| n.strip_suffix(".lproj")).filter(|l| !l.is_empty()) else {
+            continue;
+        };
+        let Ok(data) = fs::read(entry.path().join("InfoPlist.strings")) else {
+            continue;
+        };
+        if let Some(name) = plist_parser::localized_name(&data) {
+            names.insert(language.to_string(), name);
+        }
+    }
+    
+    Ok(names)
+}
+
+/// Total size of the regular files under `dir`; symlinks are neither followed nor counted
+fn directory_size(dir: &Path) -> Result<u64> {
+    let mut total = 0u64;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total = total.saturating_add(directory_size(&entry.path())?);
+        } else if file_type.is_file() {
+            total = total.saturating_add(entry.metadata()?.len());
+        }
+    }
+    
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{info_plist, plist_xml, rgba_png, TempDir};
+
+    #[test]
+    fn test_parse_app_dir() {
+        let dir = TempDir::new("app-dir");
+        let app = dir.join("Demo.app");
+        fs::create_dir_all(app.join("fr.lproj")).unwrap();
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["AppIcon".into()]));
+        let plist = plist_xml(plist);
+        fs::write(app.join("Info.plist"), &plist).unwrap();
+        fs::write(app.join("AppIcon.png"), rgba_png(4, 4, [10, 20, 30, 255])).unwrap();
+        let icon = rgba_png(8, 8, [10, 20, 30, 255]);
+        fs::write(app.join("AppIcon@2x.png"), &icon).unwrap();
+        fs::write(app.join("Other.png"), rgba_png(16, 16, [0, 0, 0, 255])).unwrap();
+        fs::write(app.join("fr.lproj/InfoPlist.strings"), "\"CFBundleDisplayName\" = \"Démo\";").unwrap();
+        
+        let options = ParseOptions { icon_output_dir: dir.join("icons"), ..Default::default() };
+        let info = parse_app_dir(&app, &options).unwrap();
+        let saved = fs::read(dir.join("icons").join(info.icon_name.clone().unwrap())).unwrap();
+        let installed_size = directory_size(&app).unwrap();
+        let missing = parse_app_dir(dir.join("Missing.app"), &options);
+        
+        assert_eq!(info.bundle_identifier, "com.example.demo");
+        assert_eq!(info.file_name.as_deref(), Some("Demo.app"));
+        assert_eq!(info.app_size, None);
+        assert_eq!(info.installed_size, installed_size);
+        assert_eq!(info.localized_names["fr"], "Démo");
+        assert_eq!((info.icon_width, info.icon_height), (Some(8), Some(8)));
+        assert_eq!(saved, icon);
+        assert_eq!(info.file_hash, Some(format!("{:x}", md5::compute(&plist))));
+        assert!(matches!(missing, Err(IpaError::InfoPlistNotFound)));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::error::Result;
+use std::collections::{BTreeSet, HashSet};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+/// Signals gathered from a single pass over the archive's entry names
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveScan {
+    /// Entry names that appear more than once (only when requested)
+    pub duplicate_entries: Vec<String>,
+    /// Whether the app bundle contains a `Settings.bundle`
+    pub has_settings_bundle: bool,
+    /// Whether the app bundle contains an `OnDemandResources/` directory
+    pub has_on_demand_resources: bool,
+    /// Whether the archive or app bundle has a `BundleMetadata.plist` App Thinning manifest
+    pub is_thinned: bool,
+    /// Whether the archive contains `__MACOSX/`, `.DS_Store` or AppleDouble (`._*`) entries
+    pub had_macos_junk: bool,
+    /// Watch apps and App Clips embedded in the app bundle, in archive order
+    pub sub_bundles: Vec<SubBundle>,
+    /// Reasons to suspect the IPA was repackaged (see `repackaging_signal`)
+    pub repackaging_signals: Vec<String>,
+    /// Distinct entry modification times, as MS-DOS `(date << 16) | time`
+    pub entry_timestamps: BTreeSet<u32>,
+    /// Names of the `<app_root>Frameworks/*.framework` bundles, without the suffix
+    pub frameworks: BTreeSet<String>,
+}
+
+/// Spread of entry modification times, for build-reproducibility checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryTimestamps {
+    /// Number of distinct timestamps (1 for a reproducible build)
+    #[serde(rename = "Distinct")]
+    pub distinct: usize,
+    /// `YYYY-MM-DD HH:MM:SS`, in the archive's local time (ZIP stores no zone)
+    #[serde(rename = "Earliest")]
+    pub earliest: String,
+    #[serde(rename = "Latest")]
+    pub latest: String,
+}
+
+impl ArchiveScan {
+    /// Summarizes `entry_timestamps`; None for an empty archive
+    pub fn entry_timestamp_summary(&self) -> Option<EntryTimestamps> {
+        let format = |packed: u32| {
+            let t = zip::DateTime::from_msdos((packed >> 16) as u16, packed as u16);
+            format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                t.year(), t.month(), t.day(), t.hour(), t.minute(), t.second()
+            )
+        };
+        
+        Some(EntryTimestamps {
+            distinct: self.entry_timestamps.len(),
+            earliest: format(*self.entry_timestamps.first()?),
+            latest: format(*self.entry_timestamps.last()?),
+        })
+    }
+}
+
+/// An app bundle nested inside the main one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubBundle {
+    /// "watch" or "clip"
+    pub role: &'static str,
+    /// Bundle root with trailing slash, e.g. `Payload/App.app/Watch/Watch.app/`
+    pub root: String,
+}
+
+/// Directories under the app root that hold nested apps, and the role each implies
+const SUB_BUNDLE_DIRS: [(&str, &str); 2] = [("Watch/", "watch"), ("AppClips/", "clip")];
+
+/// Tweak-injection runtimes that sideloading tools copy into `Frameworks/`
+/// Compared case-insensitively against the library name without its extension
+const INJECTED_LIBRARIES: [&str; 12] = [
+    "cydiasubstrate", "substrate", "libsubstrate", "substitute", "libsubstitute", "libhooker",
+    "ellekit", "libellekit", "cephei", "libcephei", "tweakinject", "libkrw",
+];
+
+/// Top-level entries written by the App Store, Xcode or Apple's signing tools
+const EXPECTED_TOP_LEVEL: [&str; 10] = [
+    "Payload", "META-INF", "SwiftSupport", "Symbols", "BCSymbolMaps", "WatchKitSupport",
+    "WatchKitSupport2", "iTunesMetadata.plist", "iTunesArtwork", THINNING_MANIFEST,
+];
+
+/// Written into thinned (device-specific) variants by App Thinning
+const THINNING_MANIFEST: &str = "BundleMetadata.plist";
+
+/// Scans entry names once, relative to the app bundle root (e.g. `Payload/App.app/`)
+/// Duplicate detection keeps every name in memory, so it only runs when asked for
+pub fn scan_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    app_root: &str,
+    detect_duplicates: bool,
+) -> Result<ArchiveScan> {
+    let mut scan = ArchiveScan::default();
+    let mut seen = HashSet::new();
+    let settings_prefix = format!("{}Settings.bundle/", app_root);
+    let on_demand_prefix = format!("{}OnDemandResources/", app_root);
+    
+    for i in 0..archive.len() {
+        // Raw access avoids setting up a decompressor just to read the name
+        let file = archive.by_index_raw(i)?;
+        let name = file.name();
+        let modified = file.last_modified();
+        
+        if is_macos_junk(name) {
+            scan.had_macos_junk = true;
+        } else {
+            // Finder junk carries the zipping machine's clock, not the build's
+            scan
+                .entry_timestamps
+                .insert(u32::from(modified.datepart()) << 16 | u32::from(modified.timepart()));
+        }
+        
+        if name.starts_with(&settings_prefix) {
+            scan.has_settings_bundle = true;
+        }
+        
+        if name.starts_with(&on_demand_prefix) {
+            scan.has_on_demand_resources = true;
+        }
+        
+        if name == THINNING_MANIFEST || name.strip_prefix(app_root) == Some(THINNING_MANIFEST) {
+            scan.is_thinned = true;
+        }
+        
+        if let Some(sub_bundle) = sub_bundle_of(name, app_root) {
+            if !scan.sub_bundles.contains(&sub_bundle) {
+                scan.sub_bundles.push(sub_bundle);
+            }
+        }
+        
+        if let Some(framework) = framework_of(name, app_root) {
+            if !scan.frameworks.contains(framework) {
+                scan.frameworks.insert(framework.to_string());
+            }
+        }
+        
+        if let Some(signal) = repackaging_signal(name, app_root) {
+            if !scan.repackaging_signals.contains(&signal) {
+                scan.repackaging_signals.push(signal);
+            }
+        }
+        
+        if detect_duplicates
+            && !seen.insert(name.to_string())
+            && !scan.duplicate_entries.iter().any(|d| d == name)
+        {
+            scan.duplicate_entries.push(name.to_string());
+        }
+    }
+    
+    Ok(scan)
+}
+
+/// Entries added by the macOS Finder/Archive Utility rather than the app:
+/// anything under `__MACOSX/`, `.DS_Store` files and AppleDouble `._*` files
+pub fn is_macos_junk(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    
+    name.starts_with("__MACOSX/") || file_name == ".DS_Store" || file_name.starts_with("._")
+}
+
+/// Heuristic tamper check for a single entry name
+///
+/// Flags known injection libraries directly under `<app_root>Frameworks/` and
+/// top-level entries that Apple's tooling never writes. It can't see a modified
+/// Info.plist or re-signed binary, and injections under other names go unnoticed,
+/// so a `None` is not proof of an untouched IPA.
+pub fn repackaging_signal(name: &str, app_root: &str) -> Option<String> {
+    if is_macos_junk(name) {
+        return None;
+    }
+    
+    if let Some(rest) = name.strip_prefix(app_root).and_then(|n| n.strip_prefix("Frameworks/")) {
+        let library = rest.split('/').next().unwrap_or(rest);
+        let stem = library
+            .trim_end_matches(".framework")
+            .trim_end_matches(".dylib")
+            .to_ascii_lowercase();
+        return INJECTED_LIBRARIES
+            .contains(&stem.as_str())
+            .then(|| format!("injected library: Frameworks/{}", library));
+    }
+    
+    // A root-level app bundle (`.tipa` layout) is its own top-level entry
+    if !app_root.is_empty() && name.starts_with(app_root) {
+        return None;
+    }
+    
+    let top = name.split('/').next().unwrap_or(name);
+    // iTunesArtwork also appears with a scale suffix
+    let top_base = top.split('@').next().unwrap_or(top);
+    (!top.is_empty() && !EXPECTED_TOP_LEVEL.contains(&top_base))
+        .then(|| format!("unexpected top-level entry: {}", top))
+}
+
+/// Recognizes `<app_root><Watch|AppClips>/<Name>.app/Info.plist` entries
+fn sub_bundle_of(name: &str, app_root: &str) -> Option<SubBundle> {
+    let rest = name.strip_prefix(app_root)?;
+    
+    SUB_BUNDLE_DIRS.iter().find_map(|(dir, role)| {
+        let bundle = rest.strip_prefix(dir)?.strip_suffix("/Info.plist")?;
+        (bundle.ends_with(".app") && !bundle.contains('/')).then(|| SubBundle {
+            role,
+            root: format!("{}{}{}/", app_root, dir, bundle),
+        })
+    })
+}
+
+/// Recognizes `<app_root>Frameworks/<Name>.framework/...` entries, returning `<Name>`
+/// Both the directory entry and the files inside it match, so archives written
+/// without directory entries still list their frameworks
+fn framework_of<'a>(name: &'a str, app_root: &str) -> Option<&'a str> {
+    let (bundle, _) = name.strip_prefix(app_root)?.strip_prefix("Frameworks/")?.split_once('/')?;
+    bundle.strip_suffix(".framework").filter(|stem| !stem.is_empty())
+}
+
+/// Returns entry names that appear more than once in the central directory
+/// Each duplicated name is reported once, in order of its second occurrence
+pub fn find_duplicate_entries<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<String>> {
+    Ok(scan_archive(archive, "", true)?.duplicate_entries)
+}
+
+/// Reads an entry by name, returning None when it doesn't exist
+/// Duplicate names resolve to their first occurrence
+pub fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Option<Vec<u8>>> {
+    for i in 0..archive.len() {
+        if archive.by_index_raw(i)?.name() == name {
+            let file = archive.by_index(i)?;
+            let size = file.size();
+            return read_to_vec(file, size).map(Some);
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Reads an entry to the end, preallocating for its declared size up to a cap
+/// ZIP64 sizes are u64 and can exceed `usize` on 32-bit targets, and a bogus
+/// header shouldn't reserve gigabytes before a single byte is read
+pub(crate) fn read_to_vec<R: Read>(mut reader: R, declared_size: u64) -> Result<Vec<u8>> {
+    const MAX_PREALLOCATION: u64 = 64 * 1024 * 1024;
+    
+    let mut buffer = Vec::with_capacity(declared_size.min(MAX_PREALLOCATION) as usize);
+    reader.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::build_zip;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_find_duplicate_entries() {
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", b"first"),
+            ("Payload/App.app/Icon.png", b"icon"),
+            ("Payload/App.app/Info.plist", b"second"),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        
+        let duplicates = find_duplicate_entries(&mut archive).unwrap();
+        assert_eq!(duplicates, vec!["Payload/App.app/Info.plist"]);
+    }
+
+    #[test]
+    fn test_scan_detects_settings_bundle() {
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/Settings.bundle/Root.plist", b""),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        
+        let scan = scan_archive(&mut archive, "Payload/App.app/", false).unwrap();
+        assert!(scan.has_settings_bundle);
+        assert!(scan.duplicate_entries.is_empty());
+        
+        let scan = scan_archive(&mut archive, "Payload/Other.app/", false).unwrap();
+        assert!(!scan.has_settings_bundle);
+    }
+
+    #[test]
+    fn test_is_macos_junk() {
+        assert!(is_macos_junk("__MACOSX/Payload/App.app/AppIcon60x60@2x.png"));
+        assert!(is_macos_junk("Payload/App.app/.DS_Store"));
+        assert!(is_macos_junk("Payload/App.app/._AppIcon60x60@2x.png"));
+        assert!(!is_macos_junk("Payload/App.app/AppIcon60x60@2x.png"));
+    }
+
+    #[test]
+    fn test_repackaging_signals() {
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/Frameworks/CydiaSubstrate.framework/CydiaSubstrate", b""),
+            ("Payload/App.app/Frameworks/CydiaSubstrate.framework/Info.plist", b""),
+            ("Payload/App.app/Frameworks/Alamofire.framework/Alamofire", b""),
+            ("iTunesArtwork@2x", b""),
+            ("Tweaks/readme.txt", b""),
+            ("__MACOSX/Payload/App.app/._Info.plist", b""),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        
+        let scan = scan_archive(&mut archive, "Payload/App.app/", false).unwrap();
+        assert_eq!(
+            scan.repackaging_signals,
+            vec![
+                "injected library: Frameworks/CydiaSubstrate.framework",
+                "unexpected top-level entry: Tweaks",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_timestamp_summary() {
+        use std::io::Write;
+        use zip::write::{FileOptions, ZipWriter};
+        
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, hour) in [("Payload/App.app/Info.plist", 10), ("Payload/App.app/App", 12)] {
+            let time = zip::DateTime::from_date_and_time(2024, 5, 1, hour, 30, 0).unwrap();
+            writer.start_file(name, FileOptions::default().last_modified_time(time)).unwrap();
+            writer.write_all(b"x").unwrap();
+        }
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        
+        let scan = scan_archive(&mut archive, "Payload/App.app/", false).unwrap();
+        assert_eq!(
+            scan.entry_timestamp_summary(),
+            Some(EntryTimestamps {
+                distinct: 2,
+                earliest: "2024-05-01 10:30:00".to_string(),
+                latest: "2024-05-01 12:30:00".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_scan_finds_sub_bundles() {
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/Watch/Watch.app/Info.plist", b""),
+            ("Payload/App.app/Watch/Watch.app/PlugIns/Ext.appex/Info.plist", b""),
+            ("Payload/App.app/AppClips/Clip.app/Info.plist", b""),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        
+        let scan = scan_archive(&mut archive, "Payload/App.app/", false).unwrap();
+        assert_eq!(
+            scan.sub_bundles,
+            vec![
+                SubBundle { role: "watch", root: "Payload/App.app/Watch/Watch.app/".to_string() },
+                SubBundle { role: "clip", root: "Payload/App.app/AppClips/Clip.app/".to_string() },
+            ]
+        );
+    }
+    
+    #[test]
+    fn test_scan_finds_thinning_markers() {
+        let scan_of = |entries: &[(&str, &[u8])]| {
+            let mut archive = ZipArchive::new(Cursor::new(build_zip(entries))).unwrap();
+            scan_archive(&mut archive, "Payload/App.app/", false).unwrap()
+        };
+        
+        let scan = scan_of(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/OnDemandResources/com.example.level1.assetpack/Info.plist", b""),
+            ("BundleMetadata.plist", b""),
+        ]);
+        assert!(scan.has_on_demand_resources && scan.is_thinned);
+        assert!(scan.repackaging_signals.is_empty());
+        assert!(scan_of(&[("Payload/App.app/BundleMetadata.plist", b"")]).is_thinned);
+        
+        let scan = scan_of(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/Frameworks/OnDemandResources.framework/BundleMetadata.plist", b""),
+        ]);
+        assert!(!scan.has_on_demand_resources && !scan.is_thinned);
+    }
+
+    #[test]
+    fn test_scan_lists_frameworks() {
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", b""),
+            ("Payload/App.app/Frameworks/Sentry.framework/", b""),
+            ("Payload/App.app/Frameworks/Sentry.framework/Sentry", b""),
+            ("Payload/App.app/Frameworks/Alamofire.framework/Info.plist", b""),
+            ("Payload/App.app/Frameworks/libswiftCore.dylib", b""),
+            ("Payload/App.app/Frameworks/Odd.framework", b""),
+            ("Payload/App.app/PlugIns/Ext.appex/Frameworks/Inner.framework/Inner", b""),
+        ]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        
+        let scan = scan_archive(&mut archive, "Payload/App.app/", false).unwrap();
+        assert_eq!(scan.frameworks.into_iter().collect::<Vec<_>>(), ["Alamofire", "Sentry"]);
+        
+        let data = build_zip(&[("Payload/App.app/Info.plist", b"")]);
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        assert!(scan_archive(&mut archive, "Payload/App.app/", false).unwrap().frameworks.is_empty());
+    }
+}
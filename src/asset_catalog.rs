@@ -0,0 +1,505 @@
+use crate::error::{IpaError, Result};
+use crate::png_normalizer::{unpremultiply, DecodeLimits};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+/// `kRenditionAttributeType_Identifier`: ties a rendition key to its FACETKEYS name
+const ATTRIBUTE_IDENTIFIER: u16 = 17;
+/// Rendition compression types we can undo (CoreUI's enum)
+const COMPRESSION_NONE: u32 = 0;
+const COMPRESSION_ZIP: u32 = 2;
+/// Fixed part of a CSI rendition header, up to its TLV list
+const CSI_HEADER_SIZE: usize = 184;
+
+/// An image rendition pulled out of a compiled asset catalog (Assets.car)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarIcon {
+    /// File name actool recorded for the rendition, e.g. "AppIcon60x60@2x.png"
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Standard (non-CgBI) PNG bytes
+    pub png: Vec<u8>,
+    /// Taken from some other "icon" image set because the one asked for isn't
+    /// in the catalog, so it may not be the app icon at all
+    pub fallback: bool,
+}
+
+/// Finds the largest decodable rendition of the image set `name` in an Assets.car
+///
+/// Only when `name` isn't in the catalog at all does it fall back to the largest
+/// rendition of any image set whose name contains "icon", marking the result as
+/// a `fallback`; a set that is there but can't be decoded gives None. Renditions
+/// stored as PNG data, uncompressed ARGB or zip-compressed ARGB are supported;
+/// LZFSE, LZVN, deepmap and other codecs are skipped, so modern catalogs may
+/// yield None.
+pub fn find_icon(car: &[u8], name: &str, limits: DecodeLimits) -> Result<Option<CarIcon>> {
+    let bom = Bom::parse(car)?;
+    
+    // Position of the identifier attribute within each rendition key
+    let key_format = bom.var("KEYFORMAT")?.ok_or_else(|| invalid("KEYFORMAT is missing"))?;
+    let attribute_count = le_u32(key_format, 8)? as usize;
+    let identifier_slot = (0..attribute_count)
+        .map(|i| le_u32(key_format, 12 + i * 4))
+        .collect::<Result<Vec<u32>>>()?
+        .iter()
+        .position(|&attribute| attribute == u32::from(ATTRIBUTE_IDENTIFIER))
+        .ok_or_else(|| invalid("rendition keys have no identifier attribute"))?;
+    
+    let mut facets: HashMap<String, u16> = HashMap::new();
+    for (key, value) in bom.tree_entries("FACETKEYS")? {
+        if let Some(identifier) = facet_identifier(value)? {
+            facets.insert(String::from_utf8_lossy(key).into_owned(), identifier);
+        }
+    }
+    
+    let mut renditions = Vec::new();
+    for (key, csi) in bom.tree_entries("RENDITIONS")? {
+        renditions.push((le_u16(key, identifier_slot * 2)?, csi));
+    }
+    
+    if let Some(&identifier) = facets.get(name) {
+        return largest_rendition(&renditions, &[identifier], limits);
+    }
+    
+    let icon_sets: Vec<u16> = facets
+        .iter()
+        .filter(|(facet, _)| facet.to_ascii_lowercase().contains("icon"))
+        .map(|(_, &identifier)| identifier)
+        .collect();
+    let icon = largest_rendition(&renditions, &icon_sets, limits)?;
+    Ok(icon.map(|icon| CarIcon { fallback: true, ..icon }))
+}
+
+/// Decodes the largest rendition belonging to one of `identifiers`,
+/// skipping ones whose codec isn't supported or that exceed `limits`
+fn largest_rendition(
+    renditions: &[(u16, &[u8])],
+    identifiers: &[u16],
+    limits: DecodeLimits,
+) -> Result<Option<CarIcon>> {
+    let mut candidates = Vec::new();
+    for &(identifier, csi) in renditions {
+        if identifiers.contains(&identifier) {
+            candidates.push((csi_dimensions(csi)?, csi));
+        }
+    }
+    // Largest first; the sort is stable so catalog order breaks ties
+    candidates.sort_by_key(|&((width, height), _)| std::cmp::Reverse(u64::from(width) * u64::from(height)));
+    
+    for ((width, height), csi) in candidates {
+        if width > limits.max_width || height > limits.max_height {
+            continue;
+        }
+        if let Some(icon) = decode_rendition(csi, limits)? {
+            return Ok(Some(icon));
+        }
+    }
+    
+    Ok(None)
+}
+
+fn csi_dimensions(csi: &[u8]) -> Result<(u32, u32)> {
+    if csi.len() < CSI_HEADER_SIZE || le_u32(csi, 0)? != fourcc(b"CTSI") {
+        return Err(invalid("rendition is not a CSI header"));
+    }
+    Ok((le_u32(csi, 12)?, le_u32(csi, 16)?))
+}
+
+/// Turns one CSI rendition into PNG bytes, or None for unsupported formats
+fn decode_rendition(csi: &[u8], limits: DecodeLimits) -> Result<Option<CarIcon>> {
+    let (width, height) = csi_dimensions(csi)?;
+    let pixel_format = le_u32(csi, 24)?;
+    let name_field = &csi[40..168];
+    let name_len = name_field.iter().position(|&b| b == 0).unwrap_or(name_field.len());
+    let name = String::from_utf8_lossy(&name_field[..name_len]).into_owned();
+    
+    let tlv_length = le_u32(csi, 168)? as usize;
+    let payload = CSI_HEADER_SIZE
+        .checked_add(tlv_length)
+        .and_then(|start| csi.get(start..))
+        .ok_or_else(|| invalid("rendition is truncated"))?;
+    
+    let png = if pixel_format == fourcc(b"DATA") {
+        // 'RAWD', version, length, then the file's bytes (PNG for icons)
+        if payload.len() < 12 || le_u32(payload, 0)? != fourcc(b"RAWD") {
+            return Ok(None);
+        }
+        let length = le_u32(payload, 8)? as usize;
+        let data = slice_at(payload, 12, length).ok_or_else(|| invalid("raw data is truncated"))?;
+        if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Ok(None);
+        }
+        data.to_vec()
+    } else if pixel_format == fourcc(b"ARGB") {
+        // 'CELM', version, compression, length, then the pixels
+        if payload.len() < 16 || le_u32(payload, 0)? != fourcc(b"CELM") {
+            return Ok(None);
+        }
+        let compression = le_u32(payload, 8)?;
+        let length = le_u32(payload, 12)? as usize;
+        let data = slice_at(payload, 16, length).ok_or_else(|| invalid("pixel data is truncated"))?;
+        
+        let expected = u64::from(width) * u64::from(height) * 4;
+        if expected > limits.max_alloc {
+            return Ok(None);
+        }
+        let pixels = match compression {
+            COMPRESSION_NONE => data.to_vec(),
+            COMPRESSION_ZIP => {
+                let mut pixels = Vec::new();
+                flate2::read::ZlibDecoder::new(data).take(expected).read_to_end(&mut pixels)?;
+                pixels
+            }
+            _ => return Ok(None),
+        };
+        encode_premultiplied_bgra(&pixels, width, height)?
+    } else {
+        return Ok(None);
+    };
+    
+    Ok(Some(CarIcon { name, width, height, png, fallback: false }))
+}
+
+/// Encodes CoreUI's premultiplied BGRA rows (possibly padded) as an RGBA PNG
+fn encode_premultiplied_bgra(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    let row_length = width as usize * 4;
+    let stride = if height == 0 { 0 } else { pixels.len() / height as usize };
+    if width == 0 || stride < row_length {
+        return Err(invalid("pixel data is smaller than the rendition"));
+    }
+    
+    let mut rgba = Vec::with_capacity(row_length * height as usize);
+    for row in pixels.chunks_exact(stride).take(height as usize) {
+        for pixel in row[..row_length].chunks_exact(4) {
+            let alpha = pixel[3];
+            let channel = |value: u8| unpremultiply(u32::from(value), u32::from(alpha), 255) as u8;
+            rgba.extend_from_slice(&[channel(pixel[2]), channel(pixel[1]), channel(pixel[0]), alpha]);
+        }
+    }
+    
+    let image = image::RgbaImage::from_raw(width, height, rgba).ok_or_else(|| invalid("pixel data is truncated"))?;
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
+/// The identifier attribute of a FACETKEYS value (a rendition key token)
+fn facet_identifier(token: &[u8]) -> Result<Option<u16>> {
+    // cursor hot spot (2 x u16), attribute count, then (name, value) pairs
+    let count = le_u16(token, 4)? as usize;
+    for i in 0..count {
+        if le_u16(token, 6 + i * 4)? == ATTRIBUTE_IDENTIFIER {
+            return Ok(Some(le_u16(token, 8 + i * 4)?));
+        }
+    }
+    Ok(None)
+}
+
+/// The BOM container Assets.car is stored in: numbered blocks, named variables
+/// pointing at blocks, and B+ trees of (key, value) blocks. Big-endian, while
+/// the CoreUI structures inside the blocks are little-endian
+struct Bom<'a> {
+    data: &'a [u8],
+    blocks: Vec<(u32, u32)>,
+    vars: HashMap<String, u32>,
+}
+
+impl<'a> Bom<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if !data.starts_with(b"BOMStore") {
+            return Err(invalid("not a BOM file"));
+        }
+        let index_offset = be_u32(data, 16)? as usize;
+        let vars_offset = be_u32(data, 24)? as usize;
+        
+        let block_count = be_u32(data, index_offset)? as usize;
+        let blocks = (0..block_count)
+            .map(|i| {
+                let entry = index_offset + 4 + i * 8;
+                Ok((be_u32(data, entry)?, be_u32(data, entry + 4)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
+        let mut vars = HashMap::new();
+        let count = be_u32(data, vars_offset)?;
+        // be_u32 just read 4 bytes there, so this can't overflow
+        let mut pos = vars_offset + 4;
+        for _ in 0..count {
+            let block = be_u32(data, pos)?;
+            let name_len = *data.get(pos + 4).ok_or_else(|| invalid("variable table is truncated"))? as usize;
+            let name = data.get(pos + 5..pos + 5 + name_len).ok_or_else(|| invalid("variable table is truncated"))?;
+            vars.insert(String::from_utf8_lossy(name).into_owned(), block);
+            pos += 5 + name_len;
+        }
+        
+        Ok(Bom { data, blocks, vars })
+    }
+
+    fn block(&self, index: u32) -> Result<&'a [u8]> {
+        let &(address, length) = self.blocks.get(index as usize).ok_or_else(|| invalid("block index out of range"))?;
+        slice_at(self.data, address as usize, length as usize).ok_or_else(|| invalid("block lies outside the file"))
+    }
+
+    fn var(&self, name: &str) -> Result<Option<&'a [u8]>> {
+        self.vars.get(name).map(|&index| self.block(index)).transpose()
+    }
+
+    /// Every (key, value) pair of a named tree, walking the leaf chain in order
+    fn tree_entries(&self, name: &str) -> Result<Vec<(&'a [u8], &'a [u8])>> {
+        let Some(tree) = self.var(name)? else {
+            return Ok(Vec::new());
+        };
+        if !tree.starts_with(b"tree") {
+            return Err(invalid("variable is not a tree"));
+        }
+        
+        // Descend along the first child to the leftmost leaf
+        let mut path = self.block(be_u32(tree, 8)?)?;
+        let mut hops = 0;
+        while be_u16(path, 0)? == 0 {
+            path = self.block(be_u32(path, 12)?)?;
+            hops += 1;
+            if hops > self.blocks.len() {
+                return Err(invalid("tree does not end in a leaf"));
+            }
+        }
+        
+        let mut entries = Vec::new();
+        loop {
+            for i in 0..be_u16(path, 2)? as usize {
+                let value = self.block(be_u32(path, 12 + i * 8)?)?;
+                let key = self.block(be_u32(path, 16 + i * 8)?)?;
+                entries.push((key, value));
+            }
+            
+            let forward = be_u32(path, 4)?;
+            hops += 1;
+            if forward == 0 {
+                return Ok(entries);
+            }
+            if hops > self.blocks.len() {
+                return Err(invalid("tree leaves form a cycle"));
+            }
+            path = self.block(forward)?;
+        }
+    }
+}
+
+/// A four-character code as CoreUI stores it in little-endian fields
+#[inline]
+fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+fn bytes<const N: usize>(data: &[u8], pos: usize) -> Result<[u8; N]> {
+    slice_at(data, pos, N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or_else(|| invalid("unexpected end of data"))
+}
+
+/// `data[start..start + len]`, or None when that runs past the end or, with
+/// offsets and lengths read from the file, overflows usize (as it can on 32-bit)
+fn slice_at(data: &[u8], start: usize, len: usize) -> Option<&[u8]> {
+    data.get(start..start.checked_add(len)?)
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Result<u32> {
+    bytes(data, pos).map(u32::from_be_bytes)
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Result<u16> {
+    bytes(data, pos).map(u16::from_be_bytes)
+}
+
+fn le_u32(data: &[u8], pos: usize) -> Result<u32> {
+    bytes(data, pos).map(u32::from_le_bytes)
+}
+
+fn le_u16(data: &[u8], pos: usize) -> Result<u16> {
+    bytes(data, pos).map(u16::from_le_bytes)
+}
+
+fn invalid(msg: &str) -> IpaError {
+    IpaError::AssetCatalog(msg.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A CSI rendition of `pixel_format` wrapping an already-built payload
+    fn csi(name: &str, width: u32, height: u32, pixel_format: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for word in [fourcc(b"CTSI"), 1, 0, width, height, 100, fourcc(pixel_format), 0, 0] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(&[0u8; 4]); // layout, zero
+        let mut name_field = [0u8; 128];
+        name_field[..name.len()].copy_from_slice(name.as_bytes());
+        data.extend_from_slice(&name_field);
+        for word in [0, 0, 0, payload.len() as u32] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// A rendition holding PNG bytes as raw data
+    pub(crate) fn png_rendition(name: &str, png: &[u8], size: u32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for word in [fourcc(b"RAWD"), 1, png.len() as u32] {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+        payload.extend_from_slice(png);
+        csi(name, size, size, b"DATA", &payload)
+    }
+
+    fn argb_rendition(name: &str, bgra: &[u8], width: u32, height: u32, compression: u32) -> Vec<u8> {
+        let mut payload = Vec::new();
+        for word in [fourcc(b"CELM"), 1, compression, bgra.len() as u32] {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+        payload.extend_from_slice(bgra);
+        csi(name, width, height, b"ARGB", &payload)
+    }
+
+    /// An Assets.car with one image set per facet and one identifier-keyed rendition each
+    pub(crate) fn build_car(facets: &[(&str, u16)], renditions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut blocks: Vec<Vec<u8>> = vec![Vec::new()];
+        let mut add = |block: Vec<u8>| {
+            blocks.push(block);
+            blocks.len() as u32 - 1
+        };
+        let tree = |add: &mut dyn FnMut(Vec<u8>) -> u32, entries: Vec<(Vec<u8>, Vec<u8>)>| {
+            let mut leaf = Vec::new();
+            leaf.extend_from_slice(&1u16.to_be_bytes());
+            leaf.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+            leaf.extend_from_slice(&[0u8; 8]);
+            for (key, value) in entries {
+                let (value, key) = (add(value), add(key));
+                leaf.extend_from_slice(&value.to_be_bytes());
+                leaf.extend_from_slice(&key.to_be_bytes());
+            }
+            let leaf = add(leaf);
+            let mut header = b"tree".to_vec();
+            for word in [1, leaf, 4096, 0] {
+                header.extend_from_slice(&word.to_be_bytes());
+            }
+            header.push(0);
+            add(header)
+        };
+        
+        // Rendition keys are (scale, identifier)
+        let mut key_format = Vec::new();
+        for word in [fourcc(b"kfmt"), 0, 2, 12, u32::from(ATTRIBUTE_IDENTIFIER)] {
+            key_format.extend_from_slice(&word.to_le_bytes());
+        }
+        let key_format = add(key_format);
+        let facet_entries = facets
+            .iter()
+            .map(|(name, identifier)| {
+                let mut token = Vec::new();
+                for half in [0, 0, 1, ATTRIBUTE_IDENTIFIER, *identifier] {
+                    token.extend_from_slice(&half.to_le_bytes());
+                }
+                (name.as_bytes().to_vec(), token)
+            })
+            .collect();
+        let facet_tree = tree(&mut add, facet_entries);
+        let rendition_entries = renditions
+            .iter()
+            .map(|(identifier, csi)| {
+                let mut key = 2u16.to_le_bytes().to_vec();
+                key.extend_from_slice(&identifier.to_le_bytes());
+                (key, csi.clone())
+            })
+            .collect();
+        let rendition_tree = tree(&mut add, rendition_entries);
+        
+        let mut data = b"BOMStore".to_vec();
+        data.resize(512, 0);
+        let mut index = (blocks.len() as u32).to_be_bytes().to_vec();
+        for block in &blocks {
+            index.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            index.extend_from_slice(&(block.len() as u32).to_be_bytes());
+            data.extend_from_slice(block);
+        }
+        let index_offset = data.len() as u32;
+        data.extend_from_slice(&index);
+        let vars_offset = data.len() as u32;
+        data.extend_from_slice(&3u32.to_be_bytes());
+        for (name, block) in [("KEYFORMAT", key_format), ("FACETKEYS", facet_tree), ("RENDITIONS", rendition_tree)] {
+            data.extend_from_slice(&block.to_be_bytes());
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+        }
+        data[16..20].copy_from_slice(&index_offset.to_be_bytes());
+        data[24..28].copy_from_slice(&vars_offset.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_find_named_icon() {
+        let small = crate::test_support::rgba_png(2, 2, [1, 2, 3, 255]);
+        let large = crate::test_support::rgba_png(8, 8, [4, 5, 6, 255]);
+        let car = build_car(
+            &[("AppIcon", 7), ("Logo", 8)],
+            &[
+                (7, png_rendition("AppIcon40x40@1x.png", &small, 2)),
+                (8, png_rendition("Logo.png", &small, 16)),
+                (7, png_rendition("AppIcon60x60@2x.png", &large, 8)),
+            ],
+        );
+        
+        let icon = find_icon(&car, "AppIcon", DecodeLimits::default()).unwrap().unwrap();
+        assert_eq!(icon.name, "AppIcon60x60@2x.png");
+        assert_eq!(icon.png, large);
+        assert!(!icon.fallback);
+        
+        // Unknown name: fall back to the largest rendition of an "…Icon" set
+        let icon = find_icon(&car, "Missing", DecodeLimits::default()).unwrap().unwrap();
+        assert_eq!(icon.name, "AppIcon60x60@2x.png");
+        assert!(icon.fallback);
+        
+        // A named set that can't be decoded doesn't fall back to other "…Icon" sets
+        let lzfse = argb_rendition("AppIcon.png", &[0; 16], 2, 2, 4);
+        let car = build_car(
+            &[("AppIcon", 1), ("ShareIcon", 2)],
+            &[(1, lzfse), (2, png_rendition("ShareIcon.png", &large, 8))],
+        );
+        assert_eq!(find_icon(&car, "AppIcon", DecodeLimits::default()).unwrap(), None);
+        
+        assert!(find_icon(b"not a car file", "AppIcon", DecodeLimits::default()).is_err());
+        
+        // Offsets near usize::MAX report truncation rather than wrapping
+        assert_eq!(slice_at(b"abc", usize::MAX, 2), None);
+        assert_eq!(slice_at(b"abc", 1, 2), Some(b"bc".as_slice()));
+        let mut truncated = car.clone();
+        truncated[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(find_icon(&truncated, "AppIcon", DecodeLimits::default()).is_err());
+    }
+
+    #[test]
+    fn test_argb_renditions_are_unpremultiplied() {
+        // Half-transparent pure red, premultiplied: B=0, G=0, R=128, A=128
+        let bgra = [0u8, 0, 128, 128].repeat(4);
+        let mut zipped = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut zipped, &bgra).unwrap();
+        let zipped = zipped.finish().unwrap();
+        
+        for rendition in [
+            argb_rendition("AppIcon.png", &bgra, 2, 2, COMPRESSION_NONE),
+            argb_rendition("AppIcon.png", &zipped, 2, 2, COMPRESSION_ZIP),
+        ] {
+            let car = build_car(&[("AppIcon", 1)], &[(1, rendition)]);
+            let icon = find_icon(&car, "AppIcon", DecodeLimits::default()).unwrap().unwrap();
+            let decoded = image::load_from_memory(&icon.png).unwrap().to_rgba8();
+            assert_eq!(decoded.get_pixel(1, 1).0, [255, 0, 0, 128]);
+        }
+        
+        // LZFSE (4) can't be decoded here, so there's nothing to return
+        let car = build_car(&[("AppIcon", 1)], &[(1, argb_rendition("AppIcon.png", &bgra, 2, 2, 4))]);
+        assert_eq!(find_icon(&car, "AppIcon", DecodeLimits::default()).unwrap(), None);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::error::{IpaError, Result};
+use crate::icon_extractor::IconWriter;
+use crate::{drop_failed_icon_writes, parse_ipa_with_writer, results_to_value, IpaInfo, ParseOptions};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// `parse_ipa` on tokio's blocking pool, so an async caller's executor keeps running
+/// Needs a tokio runtime; the options are moved into the blocking task
+pub async fn parse_ipa_async(ipa_path: impl Into<PathBuf>, options: ParseOptions) -> Result<IpaInfo> {
+    let ipa_path = ipa_path.into();
+    tokio::task::spawn_blocking(move || parse_ipa_with_writer(&ipa_path, &options, IconWriter::direct().slot(0)))
+        .await
+        .map_err(join_error)?
+}
+
+/// `parse_multiple_ipas` with each file parsed on the blocking pool, at most
+/// `max_concurrency` at a time (at least one). Output and failure handling match
+/// the synchronous version: failed files are reported on stderr and left out.
+/// `serialize_icon_writes` queues icon writes to one IO thread here too; the
+/// returned future only resolves once they have all been written
+pub async fn parse_multiple_ipas_async(
+    ipa_paths: Vec<PathBuf>,
+    options: ParseOptions,
+    max_concurrency: usize,
+) -> Result<serde_json::Value> {
+    let semaphore = Arc::new(Semaphore::new(max_concurrency.max(1)));
+    let options = Arc::new(options);
+    // Shared so `content_addressed_icons` still writes each icon once per batch
+    let (writer, io_thread) = if options.serialize_icon_writes {
+        let (writer, io_thread) = IconWriter::queued();
+        (writer, Some(io_thread))
+    } else {
+        (IconWriter::direct(), None)
+    };
+    let writer = Arc::new(writer);
+    
+    let mut tasks = Vec::with_capacity(ipa_paths.len());
+    for (index, path) in ipa_paths.into_iter().enumerate() {
+        // Waiting here, before spawning, keeps the number of queued tasks bounded too
+        let permit = semaphore.clone().acquire_owned().await.map_err(|e| IpaError::Io(std::io::Error::other(e)))?;
+        let (options, writer) = (options.clone(), writer.clone());
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let result = parse_ipa_with_writer(&path, &options, writer.slot(index));
+            drop(permit);
+            (path, result)
+        }));
+    }
+    
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        match task.await.map_err(join_error)? {
+            (path, Ok(info)) => results.push((path, info)),
+            (path, Err(e)) => log::warn!("Failed to parse {}: {}", path.display(), e),
+        }
+    }
+    
+    // Every task has finished with its clone, so this closes the queue
+    drop(writer);
+    if let Some(io_thread) = io_thread {
+        let failed = tokio::task::spawn_blocking(move || io_thread.join())
+            .await
+            .map_err(join_error)?
+            .map_err(|_| IpaError::Io(std::io::Error::other("icon writer thread panicked")))?;
+        for (_, info) in &mut results {
+            drop_failed_icon_writes(info, &failed, &options);
+        }
+    }
+    
+    results_to_value(results, &options)
+}
+
+/// A blocking task that panicked or was cancelled
+fn join_error(e: tokio::task::JoinError) -> IpaError {
+    IpaError::Io(std::io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{build_zip, info_plist, plist_xml, TempDir};
+
+    #[test]
+    fn test_async_parsing_matches_sync() {
+        let dir = TempDir::new("async");
+        let paths: Vec<PathBuf> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.{}", name)));
+                let path = dir.join(format!("{}.ipa", name));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .chain([dir.join("missing.ipa")])
+            .collect();
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let single = runtime.block_on(parse_ipa_async(&paths[0], options.clone())).unwrap();
+        let batch = runtime.block_on(parse_multiple_ipas_async(paths.clone(), options.clone(), 2)).unwrap();
+        let missing = runtime.block_on(parse_ipa_async(&paths[3], options.clone()));
+        
+        assert_eq!(single.bundle_identifier, "com.example.a");
+        assert!(missing.is_err());
+        let ids: Vec<_> = batch.as_array().unwrap().iter().map(|app| app["AppBundleIdentifier"].clone()).collect();
+        assert_eq!(ids, ["com.example.a", "com.example.b", "com.example.c"]);
+    }
+
+    #[test]
+    fn test_async_batch_serializes_icon_writes() {
+        let dir = TempDir::new("async-icons");
+        let paths: Vec<PathBuf> = (0u8..4)
+            .map(|i| {
+                let mut plist = info_plist("Demo", &format!("com.example.demo{}", i));
+                plist.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["AppIcon".into()]));
+                let data = build_zip(&[
+                    ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+                    ("Payload/Demo.app/AppIcon.png", &crate::test_support::rgba_png(4, 4, [i, 0, 0, 255])),
+                ]);
+                let path = dir.join(format!("demo{}.ipa", i));
+                std::fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions { icon_output_dir: dir.join("icons"), serialize_icon_writes: true, ..Default::default() };
+        
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let batch = runtime.block_on(parse_multiple_ipas_async(paths, options.clone(), 2)).unwrap();
+        // Resolved only after the IO thread drained, so every icon is already on disk
+        let saved: Vec<bool> = batch
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|app| options.icon_output_dir.join(app["IconName"].as_str().unwrap()).is_file())
+            .collect();
+        
+        assert_eq!(saved, [true; 4]);
+    }
+}
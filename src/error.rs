@@ -28,6 +28,82 @@ pub enum IpaError {
 
     #[error("Missing required field: {0}")]
     MissingField(String),
+
+    #[error("Hash mismatch: expected {expected}, got {actual}")]
+    HashMismatch { expected: String, actual: String },
+
+    #[error("Invalid manifest: {0}")]
+    Manifest(String),
+
+    #[error("Asset catalog error: {0}")]
+    AssetCatalog(String),
+
+    #[error("Provisioning profile error: {0}")]
+    Provisioning(String),
+
+    #[error("Mach-O error: {0}")]
+    MachO(String),
+
+    #[error("Invalid bundle identifier: {0:?}")]
+    InvalidBundleId(String),
+
+    #[error("Declared icons not found in the bundle: {0}")]
+    DeclaredIconsMissing(String),
+
+    #[error("Unsupported option combination: {0}")]
+    Unsupported(String),
+}
+
+/// Broad failure classes, one per CLI exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A bug, a serialization failure or an unsupported option combination
+    Internal,
+    /// Not a readable ZIP/IPA archive
+    NotAnIpa,
+    /// Info.plist is missing, unreadable or lacks a required field
+    MissingPlist,
+    /// Reading or writing a file failed
+    Io,
+    /// Archive entries are encrypted
+    Encrypted,
+    /// The icon couldn't be decoded or normalized, or declared icons are missing
+    Icon,
+    /// The archive's digest doesn't match the one expected for it
+    HashMismatch,
+    /// A batch manifest couldn't be read
+    Manifest,
+    /// embedded.mobileprovision or the main executable is malformed
+    BundleContent,
+}
+
+impl IpaError {
+    /// Which class of failure this is
+    /// Matches every variant by name, so a new one has to be classified here
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            IpaError::Io(_) => ErrorKind::Io,
+            IpaError::Zip(zip::result::ZipError::Io(_)) => ErrorKind::Io,
+            IpaError::Zip(zip::result::ZipError::UnsupportedArchive(msg))
+                if *msg == zip::result::ZipError::PASSWORD_REQUIRED =>
+            {
+                ErrorKind::Encrypted
+            }
+            IpaError::Zip(_) | IpaError::InvalidIpa(_) => ErrorKind::NotAnIpa,
+            IpaError::InfoPlistNotFound
+            | IpaError::MissingField(_)
+            | IpaError::InvalidBundleId(_)
+            | IpaError::Plist(_) => ErrorKind::MissingPlist,
+            IpaError::Image(_)
+            | IpaError::PngNormalization(_)
+            | IpaError::AssetCatalog(_)
+            | IpaError::DeclaredIconsMissing(_) => ErrorKind::Icon,
+            IpaError::HashMismatch { .. } => ErrorKind::HashMismatch,
+            IpaError::Manifest(_) => ErrorKind::Manifest,
+            IpaError::Provisioning(_) | IpaError::MachO(_) => ErrorKind::BundleContent,
+            IpaError::Json(_) | IpaError::Unsupported(_) => ErrorKind::Internal,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, IpaError>;
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+/// Digest algorithm for `FileHash`, hash-named icons and manifest hash checks
+/// MD5 is the default so existing icon names stay the same
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum HashAlgo {
+    #[default]
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgo {
+    /// Digest of an in-memory buffer
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finish()
+    }
+    
+    /// An incremental hasher, for data read in chunks
+    pub fn hasher(self) -> Hasher {
+        match self {
+            HashAlgo::Md5 => Hasher::Md5(md5::Context::new()),
+            HashAlgo::Sha1 => Hasher::Sha1(sha1::Sha1::default()),
+            HashAlgo::Sha256 => Hasher::Sha256(sha2::Sha256::default()),
+        }
+    }
+}
+
+/// Running state of one of the `HashAlgo` digests
+pub enum Hasher {
+    Md5(md5::Context),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+        
+        match self {
+            Hasher::Md5(context) => context.consume(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+    
+    /// Raw digest bytes (16 for MD5, 20 for SHA-1, 32 for SHA-256)
+    pub fn finish(self) -> Vec<u8> {
+        use sha2::Digest;
+        
+        match self {
+            Hasher::Md5(context) => context.compute().0.to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Text encoding for digests used in `FileHash` and icon file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum HashEncoding {
+    /// `d41d8cd9...`, the historical format
+    #[default]
+    LowerHex,
+    /// `D41D8CD9...`
+    UpperHex,
+    /// RFC 4648 base32 alphabet, unpadded
+    Base32,
+    /// RFC 4648 URL-safe base64, unpadded (safe in file names)
+    Base64Url,
+}
+
+impl HashEncoding {
+    /// Encodes raw digest bytes
+    pub fn encode(self, digest: &[u8]) -> String {
+        match self {
+            HashEncoding::LowerHex => digest.iter().map(|b| format!("{:02x}", b)).collect(),
+            HashEncoding::UpperHex => digest.iter().map(|b| format!("{:02X}", b)).collect(),
+            HashEncoding::Base32 => encode_bits(digest, 5, b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567"),
+            HashEncoding::Base64Url => encode_bits(
+                digest,
+                6,
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+            ),
+        }
+    }
+}
+
+/// Emits one alphabet symbol per `bits`-bit group, zero-padding the final group
+fn encode_bits(data: &[u8], bits: u32, alphabet: &[u8]) -> String {
+    let mask = (1u32 << bits) - 1;
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(bits as usize));
+    let mut buffer = 0u32;
+    let mut pending = 0u32;
+    
+    for &byte in data {
+        buffer = (buffer << 8) | u32::from(byte);
+        pending += 8;
+        while pending >= bits {
+            pending -= bits;
+            out.push(alphabet[((buffer >> pending) & mask) as usize] as char);
+        }
+    }
+    if pending > 0 {
+        out.push(alphabet[((buffer << (bits - pending)) & mask) as usize] as char);
+    }
+    
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_algorithms() {
+        let hex = |algo: HashAlgo| HashEncoding::LowerHex.encode(&algo.digest(b"abc"));
+        assert_eq!(hex(HashAlgo::Md5), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex(HashAlgo::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex(HashAlgo::Sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        
+        let mut hasher = HashAlgo::Sha256.hasher();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+        assert_eq!(hasher.finish(), HashAlgo::Sha256.digest(b"abc"));
+    }
+
+    #[test]
+    fn test_hash_encodings() {
+        // RFC 4648 test vector "foobar"
+        assert_eq!(HashEncoding::LowerHex.encode(b"foobar"), "666f6f626172");
+        assert_eq!(HashEncoding::UpperHex.encode(b"\xab\xcd"), "ABCD");
+        assert_eq!(HashEncoding::Base32.encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(HashEncoding::Base64Url.encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(HashEncoding::Base64Url.encode(b"\xfb\xff"), "-_8");
+    }
+}
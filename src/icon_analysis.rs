@@ -0,0 +1,115 @@
+use crate::error::Result;
+use crate::png_normalizer::{decode_png, DecodeLimits};
+use image::imageops::FilterType;
+
+/// Computes a 64-bit difference hash (dHash) of a PNG, as 16 hex characters
+///
+/// The image is reduced to 9x8 grayscale and each bit records whether a pixel
+/// is brighter than its right-hand neighbour. Visually similar icons produce
+/// hashes with a small Hamming distance, unlike a content hash.
+pub fn dhash(png: &[u8], limits: DecodeLimits) -> Result<String> {
+    let image = decode_png(png, limits)?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    
+    let mut bits = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            bits = (bits << 1) | u64::from(left > right);
+        }
+    }
+    
+    Ok(format!("{:016x}", bits))
+}
+
+/// Icons with at most this many distinct (quantized) colors count as placeholders
+pub const PLACEHOLDER_MAX_COLORS: usize = 4;
+
+/// Heuristically detects placeholder icons: solid fills or a handful of flat colors
+///
+/// Colors are quantized to 5 bits per channel on a 64x64 downsample so that
+/// compression noise and anti-aliased edges don't count as distinct colors.
+/// Fully transparent pixels are ignored. Detailed artwork that happens to use
+/// very few colors will also be flagged, so treat the result as a hint.
+pub fn is_placeholder_icon(png: &[u8], limits: DecodeLimits) -> Result<bool> {
+    let image = decode_png(png, limits)?;
+    let small = image.resize_exact(64, 64, FilterType::Nearest).to_rgba8();
+    
+    let mut colors = std::collections::HashSet::new();
+    for pixel in small.pixels().filter(|p| p[3] != 0) {
+        colors.insert([pixel[0] >> 3, pixel[1] >> 3, pixel[2] >> 3]);
+        if colors.len() > PLACEHOLDER_MAX_COLORS {
+            return Ok(false);
+        }
+    }
+    
+    Ok(true)
+}
+
+/// Edge length of each tile in an icon strip
+pub const STRIP_TILE_SIZE: u32 = 128;
+
+/// Places PNG icons side by side, left to right, on a transparent background
+///
+/// Every icon is scaled to `STRIP_TILE_SIZE` square. Returns PNG bytes.
+pub fn compose_icon_strip(icons: &[Vec<u8>], limits: DecodeLimits) -> Result<Vec<u8>> {
+    let mut strip = image::RgbaImage::new(STRIP_TILE_SIZE * icons.len() as u32, STRIP_TILE_SIZE);
+    
+    for (i, png) in icons.iter().enumerate() {
+        let icon = decode_png(png, limits)?;
+        let tile = icon.resize_exact(STRIP_TILE_SIZE, STRIP_TILE_SIZE, FilterType::Triangle).to_rgba8();
+        image::imageops::replace(&mut strip, &tile, i64::from(i as u32 * STRIP_TILE_SIZE), 0);
+    }
+    
+    let mut buffer = Vec::new();
+    image::DynamicImage::ImageRgba8(strip)
+        .write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageOutputFormat::Png)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn gradient_png(size: u32) -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(size, size, |x, _| {
+            let v = 255 - (x * 255 / size) as u8;
+            image::Rgba([v, v, v, 255])
+        });
+        let mut buffer = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_dhash_is_scale_invariant() {
+        let large = dhash(&gradient_png(120), DecodeLimits::default()).unwrap();
+        let small = dhash(&gradient_png(60), DecodeLimits::default()).unwrap();
+        
+        assert_eq!(large.len(), 16);
+        assert_eq!(large, "ffffffffffffffff");
+        assert_eq!(large, small);
+    }
+
+    #[test]
+    fn test_is_placeholder_icon() {
+        let solid = crate::test_support::rgba_png(32, 32, [0, 122, 255, 255]);
+        assert!(is_placeholder_icon(&solid, DecodeLimits::default()).unwrap());
+        assert!(!is_placeholder_icon(&gradient_png(64), DecodeLimits::default()).unwrap());
+    }
+
+    #[test]
+    fn test_compose_icon_strip() {
+        let red = crate::test_support::rgba_png(16, 16, [255, 0, 0, 255]);
+        let strip = compose_icon_strip(&[red, gradient_png(256)], DecodeLimits::default()).unwrap();
+        let strip = image::load_from_memory(&strip).unwrap().to_rgba8();
+        
+        assert_eq!(strip.dimensions(), (2 * STRIP_TILE_SIZE, STRIP_TILE_SIZE));
+        assert_eq!(strip.get_pixel(STRIP_TILE_SIZE - 1, 0).0, [255, 0, 0, 255]);
+        assert_ne!(strip.get_pixel(STRIP_TILE_SIZE, 0).0, [255, 0, 0, 255]);
+    }
+}
@@ -1,82 +1,616 @@
+use crate::archive_scan::read_to_vec;
 use crate::error::Result;
-use crate::png_normalizer::normalize_cgbi_png;
+use crate::plist_parser::PlistInfo;
+use crate::png_normalizer::{normalize_cgbi_png, DecodeLimits};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use zip::ZipArchive;
 
+/// Image set Xcode names the app icon in new projects, tried when Info.plist names none
+pub const DEFAULT_ICON_SET: &str = "AppIcon";
+
+/// File format extracted icons are saved in (see `png_normalizer::encode_icon`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum IconFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl IconFormat {
+    /// File name extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::Jpeg => "jpg",
+            IconFormat::Webp => "webp",
+        }
+    }
+}
+
+/// What saved icon (and strip) file names are built on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum IconNaming {
+    /// A `hash_algorithm` digest of the whole archive, which is read in full for it
+    #[default]
+    Hash,
+    /// The app's CFBundleIdentifier; builds of the same app share one file.
+    /// In a batch the build latest in input order keeps it, whatever order the
+    /// parses finish in, and the rest only point `IconName` at it; a later batch
+    /// or single parse replaces it
+    #[cfg_attr(feature = "native", value(name = "bundleid"))]
+    BundleId,
+    /// `1`, `2`, …, skipping numbers whose icon is already in the output directory
+    /// In a batch numbers follow input order; a file that fails or has no icon
+    /// leaves its number unused
+    Sequential,
+}
+
 /// Extracts the largest app icon from the IPA archive
-/// Returns the path where the icon was saved, or None if no icon found
+/// Returns the path where the icon was saved and its width and height from
+/// IHDR, or None if no icon found. The icon is picked by `select_app_icon`,
+/// so it is the one a parse would report; its warnings are dropped.
+/// `limits` bounds every decode (`ParseOptions::icon_decode_limits`)
 pub fn extract_app_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    plist_info: &PlistInfo,
+    output_dir: &Path,
+    hash: &str,
+    limits: DecodeLimits,
+) -> Result<Option<(PathBuf, u32, u32)>> {
+    match find_app_icon(archive, plist_info, limits)? {
+        Some(normalized) => {
+            let (width, height) = crate::png_normalizer::png_dimensions(&normalized)?;
+            Ok(Some((save_icon(&normalized, output_dir, hash)?, width, height)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Saves every matching icon, not just the largest, as `<hash>_<W>x<H>.png`
+/// using the dimensions from its IHDR. An icon whose dimensions are already
+/// taken by different bytes falls back to `<hash>_<original name>.png`;
+/// identical bytes are written once. Returns the saved paths in archive order;
+/// `limits` bounds the CgBI normalization of each icon
+pub fn extract_all_app_icons<R: Read + std::io::Seek>(
     archive: &mut ZipArchive<R>,
     icon_names: &[String],
     output_dir: &Path,
     hash: &str,
-) -> Result<Option<PathBuf>> {
+    limits: DecodeLimits,
+) -> Result<Vec<PathBuf>> {
+    let mut saved = Vec::new();
+    let mut seen_entries = HashSet::new();
+    let mut seen_icons = HashSet::new();
+    let mut used_names = HashSet::new();
+    
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        
+        if !seen_entries.insert(name.clone())
+            || crate::archive_scan::is_macos_junk(&name)
+            || !name.ends_with(".png")
+            || !is_icon_match(&name, icon_names)
+        {
+            continue;
+        }
+        
+        let size = file.size();
+        let data = read_to_vec(&mut file, size)?;
+        drop(file);
+        let normalized = normalize_cgbi_png(&data, limits)?;
+        if !seen_icons.insert(md5::compute(&normalized).0) {
+            continue;
+        }
+        
+        let (width, height) = crate::png_normalizer::png_dimensions(&normalized)?;
+        let by_size = format!("{}_{}x{}.png", hash, width, height);
+        let file_name = if used_names.contains(&by_size) {
+            icon_file_name_with_original(hash, &name)
+        } else {
+            by_size
+        };
+        // Same original name in another bundle: the first one wins
+        if !used_names.insert(file_name.clone()) {
+            continue;
+        }
+        
+        saved.push(save_icon_as(&normalized, output_dir, &file_name)?);
+    }
+    
+    Ok(saved)
+}
+
+/// Finds the app icon `select_app_icon` picks and returns its normalized PNG bytes
+/// Nothing is written to disk, and a catalog that can't be decoded counts as
+/// no icon; `limits` bounds every decode
+pub fn find_app_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    plist_info: &PlistInfo,
+    limits: DecodeLimits,
+) -> Result<Option<Vec<u8>>> {
+    Ok(select_app_icon(archive, plist_info, limits, &mut Vec::new())?.map(|(_, icon)| icon))
+}
+
+/// Picks the app icon, returning the archive entry or Assets.car image set it
+/// came from and its normalized PNG bytes
+/// The largest loose PNG matching a primary icon name wins. Asset-catalog-only
+/// apps get the CFBundleIconName image set in `<app_root>Assets.car`, or
+/// `DEFAULT_ICON_SET` when the plist names none. Alternate icons only stand in
+/// when the primary one can't be found at all, however large they are.
+/// An unreadable catalog, or another image set standing in for a missing named
+/// one, is reported in `warnings`; `limits` bounds every decode
+pub fn select_app_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    plist_info: &PlistInfo,
+    limits: DecodeLimits,
+    warnings: &mut Vec<String>,
+) -> Result<Option<(String, Vec<u8>)>> {
+    if let Some(icon) = find_icon(archive, &plist_info.icon_files, None, limits)? {
+        return Ok(Some(icon));
+    }
+    
+    let name = plist_info.icon_asset_name.as_deref().unwrap_or(DEFAULT_ICON_SET);
+    let icon = find_asset_catalog_icon(archive, &plist_info.app_root, name, limits).unwrap_or_else(|e| {
+        warnings.push(format!("could not read {}Assets.car: {}", plist_info.app_root, e));
+        None
+    });
+    if let Some(icon) = icon {
+        if icon.fallback {
+            warnings.push(format!(
+                "{}Assets.car has no {} image set; using {}, which may not be the app icon",
+                plist_info.app_root, name, icon.name
+            ));
+        }
+        return Ok(Some((icon.name, icon.png)));
+    }
+    
+    find_icon(archive, &plist_info.alternate_icon_files, None, limits)
+}
+
+/// Like `find_app_icon`, but only looks at loose PNGs and also returns the
+/// archive entry name the icon came from. `limits` bounds the CgBI normalization
+pub fn find_app_icon_entry<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+    limits: DecodeLimits,
+) -> Result<Option<(String, Vec<u8>)>> {
+    find_icon(archive, icon_names, None, limits)
+}
+
+/// Pulls the `icon_name` image set (CFBundleIconName) out of `<app_root>Assets.car`
+/// See `asset_catalog::find_icon` for which renditions can be decoded and when
+/// another image set stands in (`CarIcon::fallback`)
+pub fn find_asset_catalog_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    app_root: &str,
+    icon_name: &str,
+    limits: DecodeLimits,
+) -> Result<Option<crate::asset_catalog::CarIcon>> {
+    let Some(car) = crate::archive_scan::read_entry(archive, &format!("{}Assets.car", app_root))? else {
+        return Ok(None);
+    };
+    
+    crate::asset_catalog::find_icon(&car, icon_name, limits)
+}
+
+/// Like `find_app_icon`, but only considers files directly inside `bundle_root`
+/// Used for nested bundles, whose icon names often collide with the main app's
+pub fn find_bundle_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    bundle_root: &str,
+    icon_names: &[String],
+    limits: DecodeLimits,
+) -> Result<Option<Vec<u8>>> {
+    Ok(find_icon(archive, icon_names, Some(bundle_root), limits)?.map(|(_, icon)| icon))
+}
+
+/// Normalizes every matching icon into memory, keyed by its file name in the archive
+/// When several entries share a file name (e.g. across nested bundles) the first wins;
+/// `limits` bounds the CgBI normalization of each icon
+pub fn extract_all_icons_map<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+    limits: DecodeLimits,
+) -> Result<HashMap<String, Vec<u8>>> {
+    let mut icons = HashMap::new();
+    
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let name = file.name().to_string();
+        let file_name = Path::new(&name).file_name().and_then(|n| n.to_str()).unwrap_or("");
+        
+        if crate::archive_scan::is_macos_junk(&name)
+            || !name.ends_with(".png")
+            || !is_icon_match(&name, icon_names)
+            || icons.contains_key(file_name)
+        {
+            continue;
+        }
+        
+        let size = file.size();
+        let data = read_to_vec(&mut file, size)?;
+        icons.insert(file_name.to_string(), normalize_cgbi_png(&data, limits)?);
+    }
+    
+    Ok(icons)
+}
+
+fn find_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+    bundle_root: Option<&str>,
+    limits: DecodeLimits,
+) -> Result<Option<(String, Vec<u8>)>> {
+    let Some(index) = largest_icon_index(archive, icon_names, bundle_root)? else {
+        return Ok(None);
+    };
+    
+    let mut file = archive.by_index(index)?;
+    let name = file.name().to_string();
+    let size = file.size();
+    let data = read_to_vec(&mut file, size)?;
+    
+    // Normalize PNG if needed
+    Ok(Some((name, normalize_cgbi_png(&data, limits)?)))
+}
+
+/// Picks the matching icon entry with the largest uncompressed size
+/// Only names and sizes are read; ties go to the earliest entry
+fn largest_icon_index<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+    bundle_root: Option<&str>,
+) -> Result<Option<usize>> {
     if icon_names.is_empty() {
         return Ok(None);
     }
     
     // Track only the largest icon (single-pass optimization)
-    let mut largest_icon: Option<(Vec<u8>, u64)> = None;
+    let mut largest_icon: Option<(usize, u64)> = None;
+    // Duplicate entry names resolve to their first occurrence
+    let mut seen = HashSet::new();
     
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let name = file.name().to_string(); // Store as owned String
+        let file = archive.by_index_raw(i)?;
+        let name = file.name();
+        
+        if !seen.insert(name.to_string()) || crate::archive_scan::is_macos_junk(name) {
+            continue;
+        }
+        
+        if let Some(root) = bundle_root {
+            match name.strip_prefix(root) {
+                Some(rest) if !rest.contains('/') => {}
+                _ => continue,
+            }
+        }
         
         // Check if this file matches any icon name
-        if is_icon_match(&name, icon_names) && name.ends_with(".png") {
+        if is_icon_match(name, icon_names) && name.ends_with(".png") {
             let size = file.size();
-            
-            // Only read if this is potentially the largest
-            if largest_icon.as_ref().map_or(true, |(_, s)| size > *s) {
-                let mut data = Vec::with_capacity(size as usize);
-                file.read_to_end(&mut data)?;
-                largest_icon = Some((data, size));
+            if largest_icon.is_none_or(|(_, s)| size > s) {
+                largest_icon = Some((i, size));
             }
         }
     }
     
-    // Extract the largest icon if found
-    if let Some((icon_data, _)) = largest_icon {
-        // Normalize PNG if needed
-        let normalized = normalize_cgbi_png(&icon_data)?;
-        
-        // Save to output directory
-        fs::create_dir_all(output_dir)?;
-        let output_path = output_dir.join(format!("{}.png", hash));
-        fs::write(&output_path, normalized)?;
+    Ok(largest_icon.map(|(i, _)| i))
+}
+
+/// Whether any entry matches the declared icon names (junk entries don't count)
+pub fn has_matching_icon<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+) -> Result<bool> {
+    Ok(largest_icon_index(archive, icon_names, None)?.is_some())
+}
+
+/// Width and height of the loose PNG `find_app_icon_entry` would pick, read from its IHDR
+/// Only the first few hundred bytes of that one entry are decompressed
+pub fn largest_icon_dimensions<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    icon_names: &[String],
+) -> Result<Option<(u32, u32)>> {
+    let Some(index) = largest_icon_index(archive, icon_names, None)? else {
+        return Ok(None);
+    };
+    
+    // Signature, an optional CgBI chunk and IHDR fit well within this
+    let mut header = Vec::with_capacity(256);
+    archive.by_index(index)?.take(256).read_to_end(&mut header)?;
+    
+    crate::png_normalizer::png_dimensions(&header).map(Some)
+}
+
+/// Writes normalized icon bytes to `<output_dir>/<hash>.png`
+pub fn save_icon(normalized: &[u8], output_dir: &Path, hash: &str) -> Result<PathBuf> {
+    save_icon_as(normalized, output_dir, &icon_file_name(hash))
+}
+
+/// Writes normalized icon bytes to `<output_dir>/<file_name>`
+/// `file_name` must be a name this crate built, e.g. from `icon_file_name`;
+/// a content-addressed shard directory in it is created as needed
+pub fn save_icon_as(normalized: &[u8], output_dir: &Path, file_name: &str) -> Result<PathBuf> {
+    let output_path = output_dir.join(file_name);
+    fs::create_dir_all(output_path.parent().unwrap_or(output_dir))?;
+    fs::write(&output_path, normalized)?;
+    
+    Ok(output_path)
+}
+
+/// Queued icon writes that failed, keyed by target path, with the error message
+pub(crate) type FailedIconWrites = HashMap<PathBuf, String>;
+
+/// Where parsed icons get written: inline, or queued to one IO thread
+pub(crate) struct IconWriter {
+    queue: Option<std::sync::mpsc::SyncSender<(PathBuf, String, Vec<u8>)>>,
+    /// Targets already claimed by `write_once`, shared across the batch
+    written: std::sync::Mutex<std::collections::HashSet<PathBuf>>,
+    /// Input index of the latest input to write each `write_latest` target
+    owners: std::sync::Mutex<HashMap<PathBuf, usize>>,
+    /// `IconNaming::Sequential` numbers handed out so far, by input index
+    sequence: std::sync::Mutex<Vec<usize>>,
+}
+
+impl IconWriter {
+    /// A writer that saves every icon inline on the calling thread
+    pub(crate) fn direct() -> Self {
+        IconWriter { queue: None, written: Default::default(), owners: Default::default(), sequence: Default::default() }
+    }
+    
+    /// A writer whose saves all happen on a new IO thread, plus that thread's
+    /// handle. The thread exits once the writer is dropped; join it to wait for
+    /// the last queued icons to land and get back the writes that failed
+    pub(crate) fn queued() -> (Self, std::thread::JoinHandle<FailedIconWrites>) {
+        // Bounded so a slow disk applies backpressure instead of buffering every icon
+        let (sender, receiver) = std::sync::mpsc::sync_channel::<(PathBuf, String, Vec<u8>)>(64);
+        let io_thread = std::thread::spawn(move || {
+            let mut failed = FailedIconWrites::new();
+            for (dir, name, bytes) in receiver {
+                if let Err(e) = save_icon_as(&bytes, &dir, &name) {
+                    failed.insert(dir.join(&name), e.to_string());
+                }
+            }
+            failed
+        });
         
-        Ok(Some(output_path))
+        let writer = IconWriter {
+            queue: Some(sender),
+            written: Default::default(),
+            owners: Default::default(),
+            sequence: Default::default(),
+        };
+        (writer, io_thread)
+    }
+    
+    /// The handle the input at `index` (its position in the batch) writes through
+    pub(crate) fn slot(&self, index: usize) -> IconSlot<'_> {
+        IconSlot { writer: self, index }
+    }
+    
+    /// Writes `<output_dir>/<file_name>` now, or queues it for the IO thread
+    /// A queued write can't fail here, since the caller has moved on; the IO
+    /// thread hands its failures back when joined (see `with_icon_writer`)
+    fn write(&self, normalized: Vec<u8>, output_dir: &Path, file_name: &str) -> Result<()> {
+        match &self.queue {
+            None => save_icon_as(&normalized, output_dir, file_name).map(|_| ()),
+            Some(sender) => {
+                // The receiver only goes away once every sender is dropped
+                let _ = sender.send((output_dir.to_path_buf(), file_name.to_string(), normalized));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// One batch input's handle on the batch's `IconWriter`. Its position in the
+/// input, not the order parses finish in, decides which build keeps a shared
+/// icon and which `IconNaming::Sequential` number it gets
+#[derive(Clone, Copy)]
+pub(crate) struct IconSlot<'a> {
+    writer: &'a IconWriter,
+    index: usize,
+}
+
+impl IconSlot<'_> {
+    /// Writes `<output_dir>/<file_name>`, see `IconWriter::write`
+    pub(crate) fn write(self, normalized: Vec<u8>, output_dir: &Path, file_name: &str) -> Result<()> {
+        self.writer.write(normalized, output_dir, file_name)
+    }
+    
+    /// Like `write`, for a target several inputs share: the one latest in input
+    /// order keeps it, and an earlier one arriving after it is skipped. Writes to
+    /// one target never overlap. A file left by an earlier run is still overwritten
+    pub(crate) fn write_latest(self, normalized: Vec<u8>, output_dir: &Path, file_name: &str) -> Result<()> {
+        let target = output_dir.join(file_name);
+        // Held through the write, so the latest input's bytes are the last to land
+        let mut owners = self.writer.owners.lock().unwrap_or_else(|e| e.into_inner());
+        if owners.get(&target).is_some_and(|&owner| owner > self.index) {
+            return Ok(());
+        }
+        owners.insert(target, self.index);
+        self.writer.write(normalized, output_dir, file_name)
+    }
+    
+    /// Like `write`, but only the first call for a target writes it, and targets
+    /// that exist on disk are skipped. Only safe for content-addressed names,
+    /// where the same name always means the same bytes
+    pub(crate) fn write_once(self, normalized: Vec<u8>, output_dir: &Path, file_name: &str) -> Result<()> {
+        let target = output_dir.join(file_name);
+        let claimed = self.writer.written.lock().unwrap_or_else(|e| e.into_inner()).insert(target.clone());
+        if !claimed || target.is_file() {
+            return Ok(());
+        }
+        self.writer.write(normalized, output_dir, file_name)
+    }
+    
+    /// This input's `IconNaming::Sequential` number. Numbers follow input order,
+    /// skipping any that `taken` reports as already saved when they are reached;
+    /// an input that fails or has no icon leaves its number unused
+    pub(crate) fn sequence_number(self, taken: impl Fn(usize) -> bool) -> usize {
+        let mut numbers = self.writer.sequence.lock().unwrap_or_else(|e| e.into_inner());
+        // Only numbers past the last one handed out are checked, and this batch
+        // hasn't saved any of those, so the result doesn't depend on timing
+        while numbers.len() <= self.index {
+            let mut next = numbers.last().map_or(1, |last| last + 1);
+            while taken(next) {
+                next += 1;
+            }
+            numbers.push(next);
+        }
+        numbers[self.index]
+    }
+}
+
+/// Runs `f` with an `IconWriter`; when `serialize` is set, every write goes
+/// through a single thread so parallel parses don't contend for the disk.
+/// Also returns the queued writes that failed, which results returned by `f`
+/// may still name (see `crate::drop_failed_icon_writes`)
+pub(crate) fn with_icon_writer<T>(serialize: bool, f: impl FnOnce(&IconWriter) -> T) -> (T, FailedIconWrites) {
+    if !serialize {
+        return (f(&IconWriter::direct()), FailedIconWrites::new());
+    }
+    
+    let (writer, io_thread) = IconWriter::queued();
+    let result = f(&writer);
+    drop(writer);
+    match io_thread.join() {
+        Ok(failed) => (result, failed),
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// File name an icon is saved under for the given hash
+#[inline]
+pub fn icon_file_name(hash: &str) -> String {
+    format!("{}.png", hash)
+}
+
+/// Content-addressed path for an icon: the lowercase hex digest of the normalized
+/// bytes, sharded git-style on its first two characters (`ab/cdef….png`)
+pub fn content_addressed_icon_name(normalized: &[u8], algorithm: crate::hashing::HashAlgo) -> String {
+    let hash = crate::hashing::HashEncoding::LowerHex.encode(&algorithm.digest(normalized));
+    format!("{}/{}.png", &hash[..2], &hash[2..])
+}
+
+/// Swaps the `.png` extension of an icon file name for `format`'s
+pub fn with_icon_extension(file_name: String, format: IconFormat) -> String {
+    match file_name.strip_suffix(".png") {
+        Some(stem) if format != IconFormat::Png => format!("{}.{}", stem, format.extension()),
+        _ => file_name,
+    }
+}
+
+/// `<hash>_<original stem>.png`; the hash prefix keeps names unique
+/// The original comes from the archive, so it is reduced to a safe bare name
+pub fn icon_file_name_with_original(hash: &str, original: &str) -> String {
+    let stem = sanitize_file_name(original);
+    let stem = stem.strip_suffix(".png").unwrap_or(&stem);
+    
+    if stem.is_empty() {
+        icon_file_name(hash)
+    } else {
+        format!("{}_{}.png", hash, stem)
+    }
+}
+
+/// Icon file stem for `IconNaming::BundleId`, safe to use as a file name
+pub fn bundle_id_icon_stem(bundle_id: &str) -> String {
+    let stem = sanitize_file_name(bundle_id);
+    if stem.is_empty() {
+        "unknown".to_string()
     } else {
-        Ok(None)
+        stem
     }
 }
 
+/// Reduces an archive entry name to a bare file name that can't escape the
+/// output directory: directories are dropped, anything outside
+/// `[A-Za-z0-9._@-]` becomes `_`, and leading dots are removed
+pub fn sanitize_file_name(entry_name: &str) -> String {
+    let base = entry_name.rsplit(['/', '\\']).next().unwrap_or("");
+    let safe: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "._@-".contains(c) { c } else { '_' })
+        .collect();
+    
+    safe.trim_start_matches('.').to_string()
+}
+
+/// File name a sub-bundle icon strip is saved under for the given hash
+#[inline]
+pub fn icon_strip_file_name(hash: &str) -> String {
+    format!("{}_strip.png", hash)
+}
+
 /// Checks if a file path matches any of the icon names
+/// The file stem must be the declared name (without `.png`), optionally followed
+/// by a pixel size (`60x60`, `83.5x83.5` or legacy `-76`, for base names like
+/// `AppIcon`), then any scale (`@2x`) and idiom (`~ipad`, `~iphone`) suffixes.
+/// `AppIcon60x60` matches `AppIcon60x60@2x.png` but not `AppIcon60x60Badge.png`
 #[inline]
-fn is_icon_match(file_path: &str, icon_names: &[String]) -> bool {
+pub(crate) fn is_icon_match(file_path: &str, icon_names: &[String]) -> bool {
     let file_name = Path::new(file_path)
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("");
+    let stem = file_name.strip_suffix(".png").unwrap_or(file_name);
     
-    for icon_name in icon_names {
-        // Match exact name or name without extension
-        if file_name == *icon_name 
-            || file_name.starts_with(icon_name) 
-            || file_name.starts_with(&icon_name.trim_end_matches(".png"))
-        {
-            return true;
+    icon_names.iter().any(|icon_name| {
+        let base = icon_name.strip_suffix(".png").unwrap_or(icon_name);
+        !base.is_empty()
+            && stem
+                .strip_prefix(base)
+                .is_some_and(|rest| is_icon_suffix(strip_size_suffix(rest)))
+    })
+}
+
+/// Drops a leading `<W>x<H>` or `-<N>` pixel size, if there is one
+fn strip_size_suffix(rest: &str) -> &str {
+    let number_len = |s: &str| s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    
+    if let Some(size) = rest.strip_prefix('-') {
+        let n = number_len(size);
+        return if n > 0 { &size[n..] } else { rest };
+    }
+    
+    let w = number_len(rest);
+    match rest[w..].strip_prefix('x') {
+        Some(after) if w > 0 && number_len(after) > 0 => &after[number_len(after)..],
+        _ => rest,
+    }
+}
+
+/// Whether `rest` is nothing but `@<N>x`, `~ipad` and `~iphone` suffixes
+fn is_icon_suffix(mut rest: &str) -> bool {
+    while !rest.is_empty() {
+        if let Some(scale) = rest.strip_prefix('@') {
+            let digits = scale.find(|c: char| !c.is_ascii_digit()).unwrap_or(scale.len());
+            match scale[digits..].strip_prefix('x') {
+                Some(after) if digits > 0 => rest = after,
+                _ => return false,
+            }
+        } else if let Some(after) = rest.strip_prefix("~ipad").or_else(|| rest.strip_prefix("~iphone")) {
+            rest = after;
+        } else {
+            return false;
         }
     }
     
-    false
+    true
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
 
     #[test]
     fn test_is_icon_match() {
@@ -89,5 +623,171 @@ mod tests {
         assert!(is_icon_match("Payload/App.app/AppIcon76x76.png", &icon_names));
         assert!(is_icon_match("Payload/App.app/AppIcon76x76@2x.png", &icon_names));
         assert!(!is_icon_match("Payload/App.app/SomeOther.png", &icon_names));
+        assert!(!is_icon_match("Payload/App.app/AppIcon76x76Badge.png", &icon_names));
+        assert!(!is_icon_match("Payload/App.app/AppIcon60x60@2xBackground.png", &icon_names));
+        
+        let icon_names = vec!["Icon".to_string()];
+        assert!(is_icon_match("Payload/App.app/Icon.png", &icon_names));
+        assert!(is_icon_match("Payload/App.app/Icon@2x~ipad.png", &icon_names));
+        assert!(is_icon_match("Payload/App.app/Icon-76@2x.png", &icon_names));
+        assert!(is_icon_match("Payload/App.app/Icon83.5x83.5@2x~ipad.png", &icon_names));
+        assert!(!is_icon_match("Payload/App.app/IconBackground.png", &icon_names));
+        assert!(!is_icon_match("Payload/App.app/Icon-Settings.png", &icon_names));
+        assert!(!is_icon_match("Payload/App.app/Icon@x.png", &icon_names));
+    }
+
+    #[test]
+    fn test_extract_all_icons_map() {
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let small = rgba_png(2, 2, [1, 2, 3, 255]);
+        let large = rgba_png(4, 4, [1, 2, 3, 255]);
+        let data = build_zip(&[
+            ("Payload/App.app/AppIcon60x60@2x.png", &small),
+            ("Payload/App.app/AppIcon76x76@2x.png", &large),
+            ("Payload/App.app/Watch/W.app/AppIcon60x60@2x.png", &large),
+            ("Payload/App.app/Other.png", &large),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let icons = extract_all_icons_map(&mut archive, &["AppIcon".to_string()], DecodeLimits::default()).unwrap();
+        assert_eq!(icons.len(), 2);
+        assert_eq!(icons["AppIcon60x60@2x.png"], small);
+        assert_eq!(icons["AppIcon76x76@2x.png"], large);
+    }
+
+    #[test]
+    fn test_extract_all_app_icons() {
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let phone = rgba_png(4, 4, [1, 2, 3, 255]);
+        let data = build_zip(&[
+            ("Payload/App.app/AppIcon60x60@2x.png", &rgba_png(2, 2, [1, 2, 3, 255])),
+            ("Payload/App.app/AppIcon60x60@3x.png", &phone),
+            ("Payload/App.app/AppIcon76x76@2x~ipad.png", &phone),
+            ("Payload/App.app/AppIcon40x40@2x.png", &rgba_png(4, 4, [9, 9, 9, 255])),
+            ("Payload/App.app/Other.png", &phone),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let dir = TempDir::new("all-icons");
+        
+        let saved = extract_all_app_icons(&mut archive, &["AppIcon".to_string()], &dir, "abc", DecodeLimits::default()).unwrap();
+        let names: Vec<_> = saved.iter().map(|p| p.file_name().unwrap().to_str().unwrap().to_string()).collect();
+        let all_written = saved.iter().all(|p| p.is_file());
+        
+        assert_eq!(names, ["abc_2x2.png", "abc_4x4.png", "abc_AppIcon40x40@2x.png"]);
+        assert!(all_written);
+    }
+
+    /// Info.plist declaring `primary` and a "Dark" alternate icon, and its parse
+    fn icon_plist(primary: &str, alternate: &str) -> (Vec<u8>, PlistInfo) {
+        use crate::test_support::{info_plist, plist_xml};
+        
+        let icon_dict = |name: &str| {
+            let mut dict = plist::Dictionary::new();
+            dict.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec![name.into()]));
+            dict
+        };
+        let mut alternates = plist::Dictionary::new();
+        alternates.insert("Dark".to_string(), icon_dict(alternate).into());
+        let mut icons = plist::Dictionary::new();
+        icons.insert("CFBundlePrimaryIcon".to_string(), icon_dict(primary).into());
+        icons.insert("CFBundleAlternateIcons".to_string(), alternates.into());
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIcons".to_string(), icons.into());
+        
+        let data = plist_xml(plist);
+        let mut info = crate::plist_parser::parse_info_dict(&crate::plist_parser::read_plist_dict(&data).unwrap().unwrap()).unwrap();
+        info.app_root = "Payload/Demo.app/".to_string();
+        (data, info)
+    }
+
+    #[test]
+    fn test_extract_app_icon_prefers_primary() {
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let dir = TempDir::new("primary-icon");
+        let (_, info) = icon_plist("AppIcon60x60", "DarkIcon60x60");
+        let primary = rgba_png(4, 4, [1, 2, 3, 255]);
+        let alternate = rgba_png(16, 16, [4, 5, 6, 255]);
+        
+        let data = build_zip(&[
+            ("Payload/Demo.app/DarkIcon60x60@3x.png", &alternate),
+            ("Payload/Demo.app/AppIcon60x60@2x.png", &primary),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let (path, width, _) = extract_app_icon(&mut archive, &info, &dir, "a", DecodeLimits::default()).unwrap().unwrap();
+        assert_eq!((width, fs::read(&path).unwrap()), (4, primary));
+        
+        // With no primary icon in the archive, an alternate stands in
+        let data = build_zip(&[("Payload/Demo.app/DarkIcon60x60@3x.png", &alternate)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let (_, width, _) = extract_app_icon(&mut archive, &info, &dir, "b", DecodeLimits::default()).unwrap().unwrap();
+        assert_eq!(width, 16);
+    }
+
+    #[test]
+    fn test_find_app_icon_falls_back_to_asset_catalog() {
+        use crate::asset_catalog::tests::{build_car, png_rendition};
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let icon = rgba_png(8, 8, [9, 8, 7, 255]);
+        let alternate = rgba_png(16, 16, [4, 5, 6, 255]);
+        let car = build_car(&[("AppIcon", 3)], &[(3, png_rendition("AppIcon60x60@2x.png", &icon, 8))]);
+        let (_, info) = icon_plist("AppIcon60x60", "DarkIcon60x60");
+        
+        // The catalog comes before any alternate icon
+        let data = build_zip(&[
+            ("Payload/Demo.app/Assets.car", &car),
+            ("Payload/Demo.app/DarkIcon60x60@3x.png", &alternate),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        assert_eq!(find_app_icon(&mut archive, &info, DecodeLimits::default()).unwrap(), Some(icon.clone()));
+        assert_eq!(find_app_icon_entry(&mut archive, &info.icon_files, DecodeLimits::default()).unwrap(), None);
+        // Caller-supplied limits reach the catalog decode
+        let data = build_zip(&[("Payload/Demo.app/Assets.car", &car)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let tight = DecodeLimits { max_width: 4, max_height: 4, ..DecodeLimits::default() };
+        assert_eq!(find_app_icon(&mut archive, &info, tight).unwrap(), None);
+        
+        let data = build_zip(&[("Payload/Demo.app/Assets.car", b"not a catalog".as_slice())]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let mut warnings = Vec::new();
+        assert_eq!(select_app_icon(&mut archive, &info, DecodeLimits::default(), &mut warnings).unwrap(), None);
+        assert_eq!(warnings.len(), 1);
+        
+        // A stand-in image set is used, with a warning, when the named one is missing
+        let car = build_car(&[("BrandIcon", 3)], &[(3, png_rendition("BrandIcon@2x.png", &icon, 8))]);
+        let data = build_zip(&[("Payload/Demo.app/Assets.car", &car)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let mut warnings = Vec::new();
+        let (_, found) = select_app_icon(&mut archive, &info, DecodeLimits::default(), &mut warnings).unwrap().unwrap();
+        assert_eq!(found, icon);
+        assert!(warnings[0].contains("has no AppIcon image set"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_largest_icon_dimensions() {
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let data = build_zip(&[
+            ("Payload/App.app/AppIcon60x60@2x.png", &rgba_png(2, 2, [1, 2, 3, 255])),
+            ("Payload/App.app/AppIcon76x76@2x.png", &rgba_png(120, 80, [1, 2, 3, 255])),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let names = ["AppIcon".to_string()];
+        assert_eq!(largest_icon_dimensions(&mut archive, &names).unwrap(), Some((120, 80)));
+        assert_eq!(largest_icon_dimensions(&mut archive, &["Missing".to_string()]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_icon_file_name_with_original() {
+        assert_eq!(
+            icon_file_name_with_original("abc", "Payload/App.app/AppIcon60x60@2x.png"),
+            "abc_AppIcon60x60@2x.png"
+        );
+        assert_eq!(icon_file_name_with_original("abc", "Payload/../..\\evil name.png"), "abc_evil_name.png");
+        assert_eq!(icon_file_name_with_original("abc", "Payload/App.app/.."), "abc.png");
     }
 }
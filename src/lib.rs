@@ -1,14 +1,38 @@
+pub mod app_dir;
+pub mod archive_scan;
+pub mod asset_catalog;
+#[cfg(feature = "async")]
+pub mod async_parse;
 pub mod error;
+pub mod hashing;
+pub mod icon_analysis;
 pub mod icon_extractor;
+pub mod macho;
+pub mod manifest;
 pub mod plist_parser;
 pub mod png_normalizer;
+pub mod provisioning_parser;
+pub mod spill;
+pub mod strings_file;
+
+#[cfg(test)]
+mod test_support;
+
+pub use app_dir::parse_app_dir;
+#[cfg(feature = "async")]
+pub use async_parse::{parse_ipa_async, parse_multiple_ipas_async};
 
 use error::Result;
+use hashing::{HashAlgo, HashEncoding};
+#[cfg(feature = "native")]
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
+use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use zip::ZipArchive;
 
 /// Options for parsing IPA files
@@ -18,8 +42,96 @@ pub struct ParseOptions {
     pub extract_icons: bool,
     /// Directory to save extracted icons
     pub icon_output_dir: PathBuf,
-    /// Key strategy for multiple IPAs: "filename", "bundleid", or None
-    pub key_by: Option<String>,
+    /// Key multiple-IPA results by file name or bundle id instead of returning an array
+    pub key_by: Option<KeyStrategy>,
+    /// Group multiple-IPA results by the first N bundle id components (takes precedence over key_by)
+    pub group_by_vendor: Option<usize>,
+    /// Read the main executable's load commands (`MachOMinOS`, `MachOSDK`) and
+    /// check them against the plist's MinimumOSVersion (`MinOSConsistent`)
+    pub parse_macho: bool,
+    /// List the CPU architectures of the main executable (`Architectures`), one per fat slice
+    pub detect_architectures: bool,
+    /// Check the main executable for FairPlay encryption (`IsEncrypted`)
+    pub detect_encryption: bool,
+    /// Guess whether the app uses the SwiftUI `App` lifecycle (`LikelySwiftUI`)
+    /// Reads the executable's linked dylibs as well as the plist; see `likely_swiftui`
+    pub detect_swiftui_lifecycle: bool,
+    /// Read `embedded.mobileprovision` into `ProvisioningProfile` (team, devices, expiry)
+    pub parse_provisioning_profile: bool,
+    /// Extra plists to attach as JSON under `ExtraPlists`, keyed by the path given
+    /// Paths are tried relative to the app bundle, then to the archive root;
+    /// ones that don't exist are skipped, and malformed ones are skipped with a warning
+    pub extra_plists: Vec<String>,
+    /// Fail with `DeclaredIconsMissing` when icons are declared but none exist in the archive
+    /// (an app that declares no icons still parses). An icon found in Assets.car counts
+    pub require_declared_icons_present: bool,
+    /// With `require_declared_icons_present`, report the missing icons in `Warnings` instead of failing
+    pub declared_icons_warn_only: bool,
+    /// Fail with `InvalidBundleId` instead of just reporting `BundleIdValid: false`
+    pub strict_bundle_id: bool,
+    /// Report `ReproducibleTimestamps` and the spread of entry modification times
+    pub check_entry_timestamps: bool,
+    /// Report `IsRepackaged` (see `archive_scan::repackaging_signal` for the heuristic)
+    pub detect_repackaging: bool,
+    /// List the bundled dynamic frameworks (`Frameworks/*.framework`) as `Frameworks`
+    pub list_frameworks: bool,
+    /// Accept `Payload/<dir>/Info.plist` without the `.app` suffix when no `.app` bundle exists
+    pub lenient_layout: bool,
+    /// Scan the archive for entry names that appear more than once
+    pub detect_duplicate_entries: bool,
+    /// List the preference keys declared in Settings.bundle/Root.plist
+    pub parse_settings_bundle: bool,
+    /// Rewrite fully-opaque icons as RGB instead of RGBA
+    pub flatten_opaque_icons: bool,
+    /// Downscale the saved icon to fit within an N x N box (see `png_normalizer::resize_png`)
+    pub icon_resize: Option<u32>,
+    /// Format to save the icon in; the file name's extension follows it
+    pub icon_format: icon_extractor::IconFormat,
+    /// Compute a perceptual hash of the extracted icon (requires decoding it)
+    pub compute_icon_phash: bool,
+    /// Flag icons that look like placeholders (see `icon_analysis::is_placeholder_icon`)
+    pub detect_placeholder_icons: bool,
+    /// Report whether any icon pixel is translucent (requires decoding it)
+    pub detect_icon_transparency: bool,
+    /// Record a `hash_algorithm` digest of the saved icon bytes (`IconChecksum`)
+    pub compute_icon_checksum: bool,
+    /// How digests are written in `FileHash`, `IconChecksum`, icon file names and
+    /// manifest `expected_hash` values
+    pub hash_encoding: HashEncoding,
+    /// Digest behind `FileHash`, `IconChecksum`, hash-named icons and manifest `expected_hash` checks
+    pub hash_algorithm: HashAlgo,
+    /// Always record a SHA-256 of the whole archive as `FileSHA256`, for
+    /// deduplication and tamper checks; independent of the icon-naming hash
+    pub include_file_hash: bool,
+    /// In batch parsing, collapse IPAs with the same `FileSHA256` (computed for
+    /// this even without `include_file_hash`) into the first one, listing the
+    /// others' file names in its `Aliases`. Applied after `filter`, so only
+    /// copies that pass it are collapsed
+    pub dedup_by_hash: bool,
+    /// What icon file names are built on; anything but `IconNaming::Hash` skips
+    /// hashing the archive, leaving `FileHash` unset
+    pub icon_naming: icon_extractor::IconNaming,
+    /// Save icons as `<hash>_<original name>.png` instead of `<hash>.png`
+    pub icon_keep_original_name: bool,
+    /// Name icons by a `hash_algorithm` digest of their normalized bytes, sharded as `ab/cdef….png`
+    /// under `icon_output_dir`; `IconName` is that relative path. Identical icons
+    /// share one file, written once per batch. Takes precedence over `icon_keep_original_name`
+    pub content_addressed_icons: bool,
+    /// Size caps applied whenever an icon is decoded (flattening, phash,
+    /// placeholder detection, strips); exceeding them fails with `IpaError::Image`
+    pub icon_decode_limits: png_normalizer::DecodeLimits,
+    /// Re-check every chunk CRC of the normalized icon, failing the parse on a mismatch
+    pub verify_icon_crcs: bool,
+    /// Save the main, Watch and App Clip icons side by side as `<hash>_strip.png`
+    /// Only `parse_ipa` writes the strip, and only when there is more than one icon
+    pub compose_icon_strip: bool,
+    /// In batch parsing, write icons from one IO thread while parsing stays parallel
+    /// Helps on spinning disks and network storage; single-file parses ignore it.
+    /// Needs OS threads, so leave it off on wasm32.
+    pub serialize_icon_writes: bool,
+    /// Drop batch results that don't match this predicate
+    /// Every file is still parsed; this filters the output, not the work
+    pub filter: Option<ResultFilter>,
 }
 
 impl Default for ParseOptions {
@@ -28,12 +140,127 @@ impl Default for ParseOptions {
             extract_icons: true,
             icon_output_dir: PathBuf::from("icons"),
             key_by: None,
+            group_by_vendor: None,
+            parse_macho: false,
+            detect_architectures: false,
+            detect_encryption: false,
+            detect_swiftui_lifecycle: false,
+            parse_provisioning_profile: false,
+            extra_plists: Vec::new(),
+            require_declared_icons_present: false,
+            declared_icons_warn_only: false,
+            strict_bundle_id: false,
+            check_entry_timestamps: false,
+            detect_repackaging: false,
+            list_frameworks: false,
+            lenient_layout: false,
+            detect_duplicate_entries: false,
+            parse_settings_bundle: false,
+            flatten_opaque_icons: false,
+            icon_resize: None,
+            icon_format: icon_extractor::IconFormat::Png,
+            compute_icon_phash: false,
+            detect_placeholder_icons: false,
+            detect_icon_transparency: false,
+            compute_icon_checksum: false,
+            hash_encoding: HashEncoding::LowerHex,
+            hash_algorithm: HashAlgo::Md5,
+            include_file_hash: false,
+            dedup_by_hash: false,
+            icon_naming: icon_extractor::IconNaming::Hash,
+            icon_keep_original_name: false,
+            content_addressed_icons: false,
+            icon_decode_limits: png_normalizer::DecodeLimits::default(),
+            verify_icon_crcs: false,
+            compose_icon_strip: false,
+            serialize_icon_writes: false,
+            filter: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Starts from the defaults; see `ParseOptionsBuilder`
+    pub fn builder() -> ParseOptionsBuilder {
+        ParseOptionsBuilder::default()
+    }
+}
+
+/// Chainable construction of `ParseOptions` for callers that only change a few fields
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptionsBuilder {
+    options: ParseOptions,
+}
+
+impl ParseOptionsBuilder {
+    pub fn extract_icons(mut self, extract_icons: bool) -> Self {
+        self.options.extract_icons = extract_icons;
+        self
+    }
+    
+    pub fn icon_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.icon_output_dir = dir.into();
+        self
+    }
+    
+    pub fn key_by(mut self, strategy: KeyStrategy) -> Self {
+        self.options.key_by = Some(strategy);
+        self
+    }
+    
+    pub fn build(self) -> ParseOptions {
+        self.options
+    }
+}
+
+/// What keys the object returned for a batch when `key_by` is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(clap::ValueEnum))]
+pub enum KeyStrategy {
+    /// The IPA's file name
+    Filename,
+    /// The app's CFBundleIdentifier
+    #[cfg_attr(feature = "native", value(name = "bundleid"))]
+    BundleId,
+}
+
+impl KeyStrategy {
+    /// The key for one result, or `None` when the path has no usable file name
+    pub fn key(self, path: &Path, info: &IpaInfo) -> Option<String> {
+        match self {
+            KeyStrategy::Filename => path.file_name().and_then(|n| n.to_str()).map(String::from),
+            KeyStrategy::BundleId => Some(info.bundle_identifier.clone()),
         }
     }
 }
 
+/// Predicate over parsed results, shareable across rayon threads
+#[derive(Clone)]
+pub struct ResultFilter(Arc<dyn Fn(&IpaInfo) -> bool + Send + Sync>);
+
+impl ResultFilter {
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(&IpaInfo) -> bool + Send + Sync + 'static,
+    {
+        Self(Arc::new(predicate))
+    }
+    
+    /// Returns true when the result should be kept
+    #[inline]
+    pub fn matches(&self, info: &IpaInfo) -> bool {
+        (self.0)(info)
+    }
+}
+
+impl std::fmt::Debug for ResultFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ResultFilter(..)")
+    }
+}
+
 /// Information extracted from an IPA file
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IpaInfo {
     #[serde(rename = "AppName")]
     pub app_name: String,
@@ -41,18 +268,227 @@ pub struct IpaInfo {
     #[serde(rename = "AppVersion")]
     pub app_version: String,
     
+    /// CFBundleVersion, distinct from the marketing version in `AppVersion`
+    #[serde(rename = "BuildNumber", skip_serializing_if = "Option::is_none")]
+    pub build_number: Option<String>,
+    
     #[serde(rename = "AppBundleIdentifier")]
     pub bundle_identifier: String,
     
-    #[serde(rename = "AppSize")]
-    pub app_size: u64,
+    /// Whether `AppBundleIdentifier` is well-formed (see `plist_parser::is_valid_bundle_id`)
+    /// Always set by a parse; `None` only for records that predate the check
+    #[serde(rename = "BundleIdValid", default, skip_serializing_if = "Option::is_none")]
+    pub bundle_id_valid: Option<bool>,
+    
+    /// Archive size in bytes; `None` when the source has no known length
+    #[serde(rename = "AppSize", skip_serializing_if = "Option::is_none")]
+    pub app_size: Option<u64>,
+    
+    /// Sum of the archive entries' uncompressed sizes, roughly the space the app takes once installed
+    #[serde(rename = "InstalledSize")]
+    pub installed_size: u64,
     
     #[serde(rename = "IconName", skip_serializing_if = "Option::is_none")]
     pub icon_name: Option<String>,
     
+    /// Pixel size of the extracted icon, as saved (after any resizing)
+    #[serde(rename = "IconWidth", skip_serializing_if = "Option::is_none")]
+    pub icon_width: Option<u32>,
+    
+    #[serde(rename = "IconHeight", skip_serializing_if = "Option::is_none")]
+    pub icon_height: Option<u32>,
+    
+    /// dHash of the icon as hex; compare with Hamming distance
+    #[serde(rename = "IconPHash", skip_serializing_if = "Option::is_none")]
+    pub icon_phash: Option<String>,
+    
+    /// Heuristic: the icon is a solid fill or has very few colors
+    #[serde(rename = "IconIsPlaceholder", skip_serializing_if = "Option::is_none")]
+    pub icon_is_placeholder: Option<bool>,
+    
+    /// Some pixel of the icon has alpha below 255, which App Store icons may not
+    #[serde(rename = "IconHasTransparency", skip_serializing_if = "Option::is_none")]
+    pub icon_has_transparency: Option<bool>,
+    
+    /// Hash of the saved icon file's bytes
+    #[serde(rename = "IconChecksum", skip_serializing_if = "Option::is_none")]
+    pub icon_checksum: Option<String>,
+    
+    /// Saved composite of every bundle's icon, left to right
+    #[serde(rename = "IconStrip", skip_serializing_if = "Option::is_none")]
+    pub icon_strip: Option<String>,
+    
+    /// Role of each tile in `IconStrip`: "app", "watch" or "clip"
+    #[serde(rename = "IconStripRoles", default, skip_serializing_if = "Vec::is_empty")]
+    pub icon_strip_roles: Vec<String>,
+    
+    /// UIPrerenderedIcon, from the primary icon or the top level of Info.plist
+    #[serde(rename = "PrerenderedIcon", skip_serializing_if = "Option::is_none")]
+    pub prerendered_icon: Option<bool>,
+    
+    /// `hash_algorithm` digest of the archive that the icon file name is derived from
+    /// (only set when an icon is named, with `IconNaming::Hash`; `parse_app_dir`
+    /// digests the bundle's Info.plist instead)
+    #[serde(rename = "FileHash", skip_serializing_if = "Option::is_none")]
+    pub file_hash: Option<String>,
+    
+    /// Lowercase hex SHA-256 of the whole archive (only with `include_file_hash`
+    /// or, from `parse_ipa`, `dedup_by_hash`)
+    #[serde(rename = "FileSHA256", skip_serializing_if = "Option::is_none")]
+    pub file_sha256: Option<String>,
+    
+    /// File names of byte-identical copies folded into this result by `dedup_by_hash`
+    #[serde(rename = "Aliases", default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    
+    /// Only reported for non-standard containers (see `plist_parser::ContainerLayout`)
+    #[serde(rename = "ContainerLayout", default, skip_serializing_if = "plist_parser::ContainerLayout::is_payload")]
+    pub container_layout: plist_parser::ContainerLayout,
+    
     #[serde(rename = "FileName", skip_serializing_if = "Option::is_none")]
     pub file_name: Option<String>,
     
+    #[serde(rename = "Label", skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    
+    #[serde(rename = "ExternalAccessoryProtocols", default, skip_serializing_if = "Vec::is_empty")]
+    pub external_accessory_protocols: Vec<String>,
+    
+    #[serde(rename = "GameControllerProfiles", default, skip_serializing_if = "Vec::is_empty")]
+    pub game_controller_profiles: Vec<String>,
+    
+    #[serde(rename = "SupportsGameControllers", default, skip_serializing_if = "std::ops::Not::not")]
+    pub supports_game_controllers: bool,
+    
+    #[serde(rename = "UsesNonExemptEncryption", skip_serializing_if = "Option::is_none")]
+    pub uses_non_exempt_encryption: Option<bool>,
+    
+    /// Asset-catalog color name only; the color value itself stays in Assets.car
+    #[serde(rename = "AccentColorName", skip_serializing_if = "Option::is_none")]
+    pub accent_color_name: Option<String>,
+    
+    #[serde(rename = "AllowsMixedLocalizations", skip_serializing_if = "Option::is_none")]
+    pub allows_mixed_localizations: Option<bool>,
+    
+    #[serde(rename = "DevelopmentRegion", skip_serializing_if = "Option::is_none")]
+    pub development_region: Option<String>,
+    
+    /// LSApplicationCategoryType UTI; `plist_parser::category_label` gives a display name
+    #[serde(rename = "Category", skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    
+    #[serde(rename = "ShortcutItems", default, skip_serializing_if = "Vec::is_empty")]
+    pub shortcut_items: Vec<plist_parser::ShortcutItem>,
+    
+    /// NSUserActivityTypes: Handoff, Spotlight and Siri activity/intent types
+    #[serde(rename = "UserActivityTypes", default, skip_serializing_if = "Vec::is_empty")]
+    pub user_activity_types: Vec<String>,
+    
+    /// UIDeviceFamily values: 1 = iPhone, 2 = iPad (both for universal apps)
+    #[serde(rename = "SupportedDeviceFamilies", default, skip_serializing_if = "Vec::is_empty")]
+    pub device_families: Vec<u32>,
+    
+    /// UIRequiredDeviceCapabilities the device must have, e.g. `["arm64", "metal"]`
+    #[serde(rename = "RequiredDeviceCapabilities", default, skip_serializing_if = "Vec::is_empty")]
+    pub required_capabilities: Vec<String>,
+    
+    /// Custom URL schemes from CFBundleURLTypes, in plist order
+    #[serde(rename = "URLSchemes", default, skip_serializing_if = "Vec::is_empty")]
+    pub url_schemes: Vec<String>,
+    
+    /// `NS*UsageDescription` keys and their prompt text, sorted by key
+    #[serde(rename = "PrivacyUsageDescriptions", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub usage_descriptions: BTreeMap<String, String>,
+    
+    /// Display name per lproj language from InfoPlist.strings, e.g. `{"en": "Demo"}`
+    #[serde(rename = "LocalizedNames", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub localized_names: BTreeMap<String, String>,
+    
+    /// The archive contains `__MACOSX/`, `.DS_Store` or `._*` entries (ignored for icons)
+    #[serde(rename = "HadMacosJunk", default, skip_serializing_if = "std::ops::Not::not")]
+    pub had_macos_junk: bool,
+    
+    /// Heuristic guess that the IPA was sideload-repackaged (only when requested)
+    #[serde(rename = "IsRepackaged", skip_serializing_if = "Option::is_none")]
+    pub is_repackaged: Option<bool>,
+    
+    /// Why `IsRepackaged` is true
+    #[serde(rename = "RepackagingSignals", default, skip_serializing_if = "Vec::is_empty")]
+    pub repackaging_signals: Vec<String>,
+    
+    /// True when every entry has the same modification time (only when requested)
+    #[serde(rename = "ReproducibleTimestamps", skip_serializing_if = "Option::is_none")]
+    pub reproducible_timestamps: Option<bool>,
+    
+    #[serde(rename = "EntryTimestamps", skip_serializing_if = "Option::is_none")]
+    pub entry_timestamps: Option<archive_scan::EntryTimestamps>,
+    
+    #[serde(rename = "HasSettingsBundle", default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_settings_bundle: bool,
+    
+    /// The app bundle carries on-demand resource packs under `OnDemandResources/`
+    #[serde(rename = "HasOnDemandResources", default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_on_demand_resources: bool,
+    
+    /// The IPA is an App Thinning variant (it has a `BundleMetadata.plist`)
+    #[serde(rename = "IsThinned", default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_thinned: bool,
+    
+    #[serde(rename = "SettingsKeys", default, skip_serializing_if = "Vec::is_empty")]
+    pub settings_keys: Vec<String>,
+    
+    /// Bundled `Frameworks/*.framework` names, sorted, without the suffix (only when requested)
+    #[serde(rename = "Frameworks", default, skip_serializing_if = "Vec::is_empty")]
+    pub frameworks: Vec<String>,
+    
+    #[serde(rename = "DuplicateEntries", default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_entries: Vec<String>,
+    
+    #[serde(rename = "ExtraPlists", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_plists: BTreeMap<String, serde_json::Value>,
+    
+    #[serde(rename = "MinimumOSVersion", skip_serializing_if = "Option::is_none")]
+    pub minimum_os_version: Option<String>,
+    
+    /// SDK and build OS from the plist's DT* keys; unset when it has none of them
+    #[serde(rename = "BuildInfo", skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<plist_parser::BuildInfo>,
+    
+    /// Deployment target from the executable's LC_BUILD_VERSION / LC_VERSION_MIN_IPHONEOS
+    #[serde(rename = "MachOMinOS", skip_serializing_if = "Option::is_none")]
+    pub macho_min_os: Option<String>,
+    
+    #[serde(rename = "MachOSDK", skip_serializing_if = "Option::is_none")]
+    pub macho_sdk: Option<String>,
+    
+    /// Whether MinimumOSVersion agrees with `MachOMinOS` (only when both are known)
+    #[serde(rename = "MinOSConsistent", skip_serializing_if = "Option::is_none")]
+    pub min_os_consistent: Option<bool>,
+    
+    /// CPU architectures of the main executable, e.g. ["arm64"] (only with
+    /// `detect_architectures`; empty when the executable is missing or unreadable)
+    #[serde(rename = "Architectures", default, skip_serializing_if = "Vec::is_empty")]
+    pub architectures: Vec<String>,
+    
+    /// Whether the main executable's LC_ENCRYPTION_INFO has a non-zero `cryptid`
+    /// (only with `detect_encryption`; unset when the executable can't be read)
+    #[serde(rename = "IsEncrypted", skip_serializing_if = "Option::is_none")]
+    pub is_encrypted: Option<bool>,
+    
+    /// Best-effort guess at the SwiftUI `App` lifecycle vs. UIKit's app delegate
+    /// (only with `detect_swiftui_lifecycle`; unset when the executable can't be read)
+    #[serde(rename = "LikelySwiftUI", skip_serializing_if = "Option::is_none")]
+    pub likely_swiftui: Option<bool>,
+    
+    /// From the bundle's embedded.mobileprovision (only with `parse_provisioning_profile`;
+    /// unset when there is none, as in App Store builds)
+    #[serde(rename = "ProvisioningProfile", skip_serializing_if = "Option::is_none")]
+    pub provisioning_profile: Option<provisioning_parser::ProvisioningInfo>,
+    
+    /// Non-fatal problems found while parsing
+    #[serde(rename = "Warnings", default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    
     #[serde(rename = "Timestamp")]
     pub timestamp: u64,
 }
@@ -60,178 +496,2310 @@ pub struct IpaInfo {
 /// Parses a single IPA file
 /// This is the main entry point for single-file parsing
 pub fn parse_ipa<P: AsRef<Path>>(ipa_path: P, options: &ParseOptions) -> Result<IpaInfo> {
+    parse_ipa_with_writer(ipa_path.as_ref(), options, icon_extractor::IconWriter::direct().slot(0))
+}
+
+/// `parse_ipa`, with icon writes going through `slot`
+pub(crate) fn parse_ipa_with_writer(
+    ipa_path: &Path,
+    options: &ParseOptions,
+    slot: icon_extractor::IconSlot<'_>,
+) -> Result<IpaInfo> {
+    log::debug!("Parsing {}", ipa_path.display());
+    let file = File::open(ipa_path)?;
+    let file_size = file.metadata()?.len();
+    
+    let (mut info, _) = parse_source(file, options, Some(slot), |_, algorithm| {
+        compute_file_digest(ipa_path, algorithm)
+    })?;
+    info.app_size = Some(file_size);
+    info.file_name = Some(ipa_file_name(ipa_path));
+    Ok(info)
+}
+
+/// Parses a single IPA file like `parse_ipa`, but hands back the normalized icon
+/// (in `icon_format`) instead of writing it, so nothing touches `icon_output_dir`.
+/// `IconName` is the name `parse_ipa` would save it under; no icon strip is composed
+pub fn parse_ipa_with_icon_bytes<P: AsRef<Path>>(
+    ipa_path: P,
+    options: &ParseOptions,
+) -> Result<(IpaInfo, Option<Vec<u8>>)> {
     let ipa_path = ipa_path.as_ref();
     let file = File::open(ipa_path)?;
     let file_size = file.metadata()?.len();
-    let file_name = ipa_path
+    
+    let (mut info, icon) = parse_source(file, options, None, |_, algorithm| compute_file_digest(ipa_path, algorithm))?;
+    info.app_size = Some(file_size);
+    info.file_name = Some(ipa_file_name(ipa_path));
+    Ok((info, icon))
+}
+
+/// `FileName` for an IPA on disk
+fn ipa_file_name(ipa_path: &Path) -> String {
+    ipa_path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown.ipa")
-        .to_string();
-    
-    let mut archive = ZipArchive::new(file)?;
-    
-    // Extract plist info
-    let plist_info = plist_parser::extract_plist_info(&mut archive)?;
-    
-    // Extract icon if requested
-    let icon_name = if options.extract_icons {
-        // Generate MD5 hash for unique filename
-        let hash = compute_file_hash(ipa_path)?;
-        
-        icon_extractor::extract_app_icon(
-            &mut archive,
-            &plist_info.icon_files,
-            &options.icon_output_dir,
-            &hash,
-        )?
-        .and_then(|path| path.file_name().and_then(|n| n.to_str()).map(String::from))
-    } else {
-        None
-    };
-    
-    Ok(IpaInfo {
-        app_name: plist_info.app_name,
-        app_version: plist_info.app_version,
-        bundle_identifier: plist_info.bundle_identifier,
-        app_size: file_size,
-        icon_name,
-        file_name: Some(file_name),
-        timestamp: current_timestamp(),
-    })
+        .to_string()
 }
 
-/// Parses multiple IPA files in parallel
-/// Returns a HashMap or Vec depending on key_by option
-pub fn parse_multiple_ipas<P: AsRef<Path>>(
-    ipa_paths: &[P],
+/// Parses an IPA from any seekable reader and saves its icon, like `parse_ipa`
+/// A reader has no name or on-disk size of its own, so `FileName` and `AppSize`
+/// come from `file_name` and `size` (left unset when None). The icon is named
+/// by a hash of the whole stream, which is read again from the start for it.
+/// `parse_ipa_reader` is the same parse with the icon handed back instead of saved
+pub fn parse_ipa_reader_and_save_icon<R: Read + Seek>(
+    reader: R,
+    file_name: Option<String>,
+    size: Option<u64>,
     options: &ParseOptions,
-) -> Result<serde_json::Value> {
-    // Convert to PathBuf for parallel iteration
-    let paths: Vec<PathBuf> = ipa_paths.iter().map(|p| p.as_ref().to_path_buf()).collect();
+) -> Result<IpaInfo> {
+    let writer = icon_extractor::IconWriter::direct();
+    let (mut info, _) = parse_source(reader, options, Some(writer.slot(0)), rewound_digest)?;
+    info.app_size = size;
+    info.file_name = file_name;
+    Ok(info)
+}
+
+/// The pipeline behind every single-IPA entry point; they only differ in where
+/// the archive comes from and what they know about it (name, size)
+///
+/// With a `slot` the icon and strip are saved and None is returned in their place.
+/// Without one the normalized icon is handed back, `IconName` is the name it
+/// would be saved under, and no strip is composed; nothing on disk is looked at,
+/// so `IconNaming::Sequential` names it `1`. `digest` hashes the whole
+/// archive, which `reader` no longer points at the start of, for `FileHash`
+/// and `FileSHA256`; sources with a cheaper route than rereading can ignore it
+fn parse_source<R: Read + Seek>(
+    reader: R,
+    options: &ParseOptions,
+    slot: Option<icon_extractor::IconSlot<'_>>,
+    mut digest: impl FnMut(&mut R, HashAlgo) -> Result<Vec<u8>>,
+) -> Result<(IpaInfo, Option<Vec<u8>>)> {
+    let mut archive = open_archive(reader)?;
+    let compose_strip = slot.is_some() && options.compose_icon_strip;
+    let ParsedArchive { mut info, icon, icon_entry, icon_strip } = parse_archive(&mut archive, options, compose_strip)?;
+    let mut reader = archive.into_inner();
     
-    // Process in parallel for maximum speed
-    let results: Vec<_> = paths
-        .par_iter()
-        .filter_map(|path| {
-            match parse_ipa(path, options) {
-                Ok(info) => {
-                    let key = match &options.key_by {
-                        Some(k) if k == "filename" => path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .map(String::from),
-                        Some(k) if k == "bundleid" => Some(info.bundle_identifier.clone()),
-                        _ => None,
-                    };
-                    Some((key, info))
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Warning: Failed to parse {}: {}",
-                        path.display(),
-                        e
-                    );
-                    None
-                }
+    let icon = match slot {
+        Some(slot) => {
+            if icon.is_some() || icon_strip.is_some() {
+                // By default the archive hash gives a unique filename
+                let stem = icon_stem(&mut info, options, Some(slot), || digest(&mut reader, options.hash_algorithm))?;
+                save_images(&mut info, icon, icon_entry, icon_strip, &stem, options, slot)?;
             }
-        })
-        .collect();
-    
-    // Convert to appropriate format
-    if options.key_by.is_some() {
-        let mut map = HashMap::new();
-        for (key, info) in results {
-            if let Some(k) = key {
-                map.insert(k, info);
+            None
+        }
+        None => {
+            if let Some(icon) = &icon {
+                let stem = icon_stem(&mut info, options, None, || digest(&mut reader, options.hash_algorithm))?;
+                info.icon_name = Some(icon_save_name(&stem, icon, icon_entry.as_deref(), options));
             }
+            icon
         }
-        Ok(serde_json::to_value(map)?)
-    } else {
-        let vec: Vec<_> = results.into_iter().map(|(_, info)| info).collect();
-        Ok(serde_json::to_value(vec)?)
+    };
+    
+    if options.include_file_hash || options.dedup_by_hash {
+        info.file_sha256 = Some(HashEncoding::LowerHex.encode(&digest(&mut reader, HashAlgo::Sha256)?));
     }
+    Ok((info, icon))
 }
 
-/// Finds all IPA files in a directory
-pub fn find_ipa_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
-    let dir = dir.as_ref();
-    let mut ipa_files = Vec::new();
-    
-    if !dir.is_dir() {
-        return Ok(ipa_files);
+/// `parse_source` digest for plain readers: hash everything from the start again
+fn rewound_digest<R: Read + Seek>(reader: &mut R, algorithm: HashAlgo) -> Result<Vec<u8>> {
+    reader.seek(std::io::SeekFrom::Start(0))?;
+    compute_reader_digest(reader, algorithm)
+}
+
+/// Writes the extracted icon and strip under names derived from `stem`
+/// (see `icon_stem`) and records those names in `info`
+pub(crate) fn save_images(
+    info: &mut IpaInfo,
+    icon: Option<Vec<u8>>,
+    icon_entry: Option<String>,
+    icon_strip: Option<Vec<u8>>,
+    stem: &str,
+    options: &ParseOptions,
+    slot: icon_extractor::IconSlot<'_>,
+) -> Result<()> {
+    // Builds of one app share a bundle-id name; the latest in input order keeps it
+    let shared_stem = options.icon_naming == icon_extractor::IconNaming::BundleId;
+    if let Some(icon) = icon {
+        let name = icon_save_name(stem, &icon, icon_entry.as_deref(), options);
+        if options.content_addressed_icons {
+            slot.write_once(icon, &options.icon_output_dir, &name)?;
+        } else if shared_stem {
+            slot.write_latest(icon, &options.icon_output_dir, &name)?;
+        } else {
+            slot.write(icon, &options.icon_output_dir, &name)?;
+        }
+        info.icon_name = Some(name);
+    }
+    if let Some(strip) = icon_strip {
+        let name = icon_extractor::icon_strip_file_name(stem);
+        if shared_stem {
+            slot.write_latest(strip, &options.icon_output_dir, &name)?;
+        } else {
+            slot.write(strip, &options.icon_output_dir, &name)?;
+        }
+        info.icon_strip = Some(name);
+    }
+    Ok(())
+}
+
+/// Clears `IconName` and `IconStrip` when the IO thread failed to write the file
+/// they name, recording the failure in `Warnings` instead
+pub(crate) fn drop_failed_icon_writes(
+    info: &mut IpaInfo,
+    failed: &icon_extractor::FailedIconWrites,
+    options: &ParseOptions,
+) {
+    if failed.is_empty() {
+        return;
+    }
+    for name in [&mut info.icon_name, &mut info.icon_strip] {
+        let target = name.as_ref().map(|name| options.icon_output_dir.join(name));
+        if let Some(e) = target.and_then(|target| failed.get(&target)) {
+            info.warnings.push(format!("could not write icon {}: {}", name.take().unwrap_or_default(), e));
+        }
     }
+}
+
+/// Stem the icon and strip file names are built on, per `icon_naming`
+/// Only `IconNaming::Hash` calls `digest`, recording the encoded result as `FileHash`.
+/// Without a `slot` nothing will be saved, and `Sequential` is `1` without
+/// looking at the output directory
+pub(crate) fn icon_stem(
+    info: &mut IpaInfo,
+    options: &ParseOptions,
+    slot: Option<icon_extractor::IconSlot<'_>>,
+    digest: impl FnOnce() -> Result<Vec<u8>>,
+) -> Result<String> {
+    Ok(match options.icon_naming {
+        icon_extractor::IconNaming::Hash => {
+            let hash = options.hash_encoding.encode(&digest()?);
+            info.file_hash = Some(hash.clone());
+            hash
+        }
+        icon_extractor::IconNaming::BundleId => icon_extractor::bundle_id_icon_stem(&info.bundle_identifier),
+        icon_extractor::IconNaming::Sequential => {
+            let Some(slot) = slot else {
+                return Ok("1".to_string());
+            };
+            let taken = |n: usize| {
+                let stem = n.to_string();
+                let icon = icon_extractor::with_icon_extension(icon_extractor::icon_file_name(&stem), options.icon_format);
+                [icon, icon_extractor::icon_strip_file_name(&stem)]
+                    .iter()
+                    .any(|name| options.icon_output_dir.join(name).is_file())
+            };
+            slot.sequence_number(taken).to_string()
+        }
+    })
+}
+
+/// Parses an IPA held entirely in memory, without touching the filesystem
+/// Returns the metadata plus the normalized icon bytes (in `icon_format`) when `extract_icons` is set.
+/// `IconName` is the name the icon would be saved under, hashed over `data`
+/// (`1` with `IconNaming::Sequential`, as the output directory isn't looked at).
+pub fn parse_ipa_bytes(
+    data: &[u8],
+    file_name: Option<String>,
+    options: &ParseOptions,
+) -> Result<(IpaInfo, Option<Vec<u8>>)> {
+    let reader = std::io::Cursor::new(data);
+    let (mut info, icon) = parse_source(reader, options, None, |_, algorithm| Ok(algorithm.digest(data)))?;
+    info.app_size = Some(data.len() as u64);
+    info.file_name = file_name;
+    Ok((info, icon))
+}
+
+/// Parses an IPA from any seekable reader, without touching the filesystem
+/// `parse_ipa_bytes` for data that isn't in one slice: `IconName` is hashed over
+/// the whole stream, and `AppSize` and `FileName` are left unset since the source
+/// has no length or name of its own. `parse_ipa_reader_and_save_icon` is the same
+/// parse with the icon saved to `icon_output_dir`, and takes a name and size
+pub fn parse_ipa_reader<R: Read + Seek>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<(IpaInfo, Option<Vec<u8>>)> {
+    parse_source(reader, options, None, rewound_digest)
+}
+
+/// Opens a ZIP archive, explaining central-directory failures, which are
+/// what a download cut short produces
+fn open_archive<R: Read + Seek>(reader: R) -> Result<ZipArchive<R>> {
+    ZipArchive::new(reader).map_err(|e| match e {
+        zip::result::ZipError::InvalidArchive(msg) => error::IpaError::InvalidIpa(format!(
+            "{}; the file may be truncated or not a ZIP archive",
+            msg
+        )),
+        zip::result::ZipError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            error::IpaError::InvalidIpa("archive ends early; the file may be truncated".to_string())
+        }
+        e => e.into(),
+    })
+}
+
+/// Fails unless the archive has an app bundle directory: `Payload/<Name>.app/`,
+/// a root-level `<Name>.app/`, or with `lenient_layout` any `Payload/<dir>/`
+/// Returns the total uncompressed size of the entries, summed on the same pass
+fn check_app_bundle<R: Read + Seek>(archive: &mut ZipArchive<R>, lenient_layout: bool) -> Result<u64> {
+    let mut found = false;
+    let mut installed_size = 0u64;
     
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext.eq_ignore_ascii_case("ipa") {
-                    ipa_files.push(path);
-                }
-            }
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        installed_size = installed_size.saturating_add(file.size());
+        if found {
+            continue;
         }
+        let name = file.name();
+        let Some((dir, _)) = name.strip_prefix("Payload/").unwrap_or(name).split_once('/') else {
+            continue;
+        };
+        let in_payload = name.starts_with("Payload/");
+        found = dir.ends_with(".app") || (in_payload && lenient_layout && !dir.is_empty());
     }
     
-    Ok(ipa_files)
+    if !found {
+        return Err(error::IpaError::InvalidIpa("archive contains no Payload/*.app directory".to_string()));
+    }
+    Ok(installed_size)
 }
 
-/// Computes MD5 hash of a file efficiently using memory mapping for large files
-#[inline]
-fn compute_file_hash<P: AsRef<Path>>(path: P) -> Result<String> {
-    use std::io::Read;
+/// Output of `parse_archive`: metadata plus any images the caller may save
+struct ParsedArchive {
+    info: IpaInfo,
+    icon: Option<Vec<u8>>,
+    /// Archive entry the icon was read from
+    icon_entry: Option<String>,
+    icon_strip: Option<Vec<u8>>,
+}
+
+/// File name for a saved icon, honouring `content_addressed_icons` and `icon_keep_original_name`
+fn icon_save_name(stem: &str, icon: &[u8], icon_entry: Option<&str>, options: &ParseOptions) -> String {
+    let name = if options.content_addressed_icons {
+        icon_extractor::content_addressed_icon_name(icon, options.hash_algorithm)
+    } else {
+        match icon_entry {
+            Some(entry) if options.icon_keep_original_name => {
+                icon_extractor::icon_file_name_with_original(stem, entry)
+            }
+            _ => icon_extractor::icon_file_name(stem),
+        }
+    };
+    icon_extractor::with_icon_extension(name, options.icon_format)
+}
+
+/// Extracts metadata and (optionally) the normalized icon from an open archive
+/// Source-specific fields (size, file name, icon name) are left for the caller to fill in
+fn parse_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    options: &ParseOptions,
+    compose_strip: bool,
+) -> Result<ParsedArchive> {
+    let installed_size = check_app_bundle(archive, options.lenient_layout)?;
     
-    let path = path.as_ref();
-    let file = File::open(path)?;
-    let metadata = file.metadata()?;
-    let file_size = metadata.len();
+    // Extract plist info
+    let plist_info = plist_parser::extract_plist_info_with(archive, options.lenient_layout)?;
+    check_bundle_id(&plist_info.bundle_identifier, options)?;
     
-    let mut hasher = md5::Context::new();
+    let scan = archive_scan::scan_archive(
+        archive,
+        &plist_info.app_root,
+        options.detect_duplicate_entries,
+    )?;
     
-    // Use memory mapping for files larger than 1MB for better performance
-    if file_size > 1_048_576 {
-        // Memory-mapped I/O for large files
-        unsafe {
-            let mmap = memmap2::Mmap::map(&file)?;
-            hasher.consume(&mmap[..]);
+    let settings_keys = if options.parse_settings_bundle && scan.has_settings_bundle {
+        let root_plist = format!("{}Settings.bundle/Root.plist", plist_info.app_root);
+        match archive_scan::read_entry(archive, &root_plist)? {
+            Some(data) => plist_parser::read_plist_dict(&data)?
+                .map(|dict| plist_parser::extract_settings_keys(&dict))
+                .unwrap_or_default(),
+            None => Vec::new(),
         }
     } else {
-        // Buffered reading for small files with larger buffer
-        let mut file = file;
-        let mut buffer = vec![0; 65536]; // 64KB buffer for better performance
-        
-        loop {
-            let n = file.read(&mut buffer)?;
-            if n == 0 {
-                break;
+        Vec::new()
+    };
+    
+    let mut extra_plists = BTreeMap::new();
+    let mut warnings = Vec::new();
+    for path in &options.extra_plists {
+        let data = match archive_scan::read_entry(archive, &format!("{}{}", plist_info.app_root, path))? {
+            Some(data) => Some(data),
+            None => archive_scan::read_entry(archive, path)?,
+        };
+        if let Some(data) = data {
+            // A corrupt extra plist only costs its own entry
+            match plist::Value::from_reader(std::io::Cursor::new(data)) {
+                Ok(value) => {
+                    extra_plists.insert(path.clone(), plist_parser::plist_to_json(&value));
+                }
+                Err(e) => warnings.push(format!("could not read extra plist {}: {}", path, e)),
+            }
+        }
+    }
+    
+    let macho_info = if options.parse_macho
+        || options.detect_swiftui_lifecycle
+        || options.detect_architectures
+        || options.detect_encryption
+    {
+        read_executable_info(archive, &plist_info, &mut warnings)?
+    } else {
+        None
+    };
+    let likely_swiftui = match &macho_info {
+        Some(macho) if options.detect_swiftui_lifecycle => Some(likely_swiftui(&plist_info, macho)),
+        _ => None,
+    };
+    let architectures = match &macho_info {
+        Some(macho) if options.detect_architectures => macho.architectures.clone(),
+        _ => Vec::new(),
+    };
+    let is_encrypted = match &macho_info {
+        Some(macho) if options.detect_encryption => Some(macho.encrypted),
+        _ => None,
+    };
+    let (macho_min_os, macho_sdk) = match macho_info {
+        Some(macho) if options.parse_macho => (macho.min_os, macho.sdk),
+        _ => (None, None),
+    };
+    let min_os_consistent = match (&plist_info.minimum_os_version, &macho_min_os) {
+        (Some(plist_min), Some(macho_min)) => {
+            let consistent = macho::versions_match(plist_min, macho_min);
+            if consistent == Some(false) {
+                warnings.push(format!(
+                    "MinimumOSVersion {} does not match the executable's deployment target {}",
+                    plist_min, macho_min
+                ));
             }
-            hasher.consume(&buffer[..n]);
+            consistent
+        }
+        _ => None,
+    };
+    
+    let provisioning_profile = if options.parse_provisioning_profile {
+        provisioning_parser::extract_provisioning_info(archive).unwrap_or_else(|e| {
+            warnings.push(format!("could not read embedded.mobileprovision: {}", e));
+            None
+        })
+    } else {
+        None
+    };
+    
+    // Extract icon if requested
+    let icon = if options.extract_icons {
+        icon_extractor::select_app_icon(archive, &plist_info, options.icon_decode_limits, &mut warnings)?
+    } else {
+        None
+    };
+    let (icon_entry, icon) = icon.unzip();
+    
+    // Checked the same way whether or not icons are extracted, and an alternate
+    // icon standing in for the primary one doesn't count
+    if options.require_declared_icons_present
+        && !plist_info.icon_files.is_empty()
+        && !declared_icon_present(archive, &plist_info, options.icon_decode_limits)?
+    {
+        let missing = error::IpaError::DeclaredIconsMissing(plist_info.icon_files.join(", "));
+        if !options.declared_icons_warn_only {
+            return Err(missing);
         }
+        warnings.push(missing.to_string());
     }
+    let (icon, icon_details) = prepare_icon(icon, options)?;
+    
+    let entry_timestamps = if options.check_entry_timestamps {
+        scan.entry_timestamp_summary()
+    } else {
+        None
+    };
+    
+    let (is_repackaged, repackaging_signals) = if options.detect_repackaging {
+        (Some(!scan.repackaging_signals.is_empty()), scan.repackaging_signals)
+    } else {
+        (None, Vec::new())
+    };
+    
+    let frameworks = if options.list_frameworks {
+        scan.frameworks.into_iter().collect()
+    } else {
+        Vec::new()
+    };
+    
+    let (icon_strip, icon_strip_roles) = if compose_strip && options.extract_icons {
+        compose_bundle_strip(archive, icon.as_ref(), &scan.sub_bundles, options.icon_decode_limits)?
+    } else {
+        (None, Vec::new())
+    };
     
-    Ok(format!("{:x}", hasher.compute()))
+    let (icon, icon_checksum) = finish_icon(icon, options)?;
+    
+    let info = IpaInfo {
+        installed_size,
+        icon_width: icon_details.dimensions.map(|(width, _)| width),
+        icon_height: icon_details.dimensions.map(|(_, height)| height),
+        icon_phash: icon_details.phash,
+        icon_is_placeholder: icon_details.is_placeholder,
+        icon_has_transparency: icon_details.has_transparency,
+        icon_checksum,
+        icon_strip_roles,
+        had_macos_junk: scan.had_macos_junk,
+        is_repackaged,
+        repackaging_signals,
+        reproducible_timestamps: entry_timestamps.as_ref().map(|t| t.distinct == 1),
+        entry_timestamps,
+        has_settings_bundle: scan.has_settings_bundle,
+        has_on_demand_resources: scan.has_on_demand_resources,
+        is_thinned: scan.is_thinned,
+        settings_keys,
+        frameworks,
+        duplicate_entries: scan.duplicate_entries,
+        extra_plists,
+        macho_min_os,
+        macho_sdk,
+        min_os_consistent,
+        architectures,
+        is_encrypted,
+        likely_swiftui,
+        provisioning_profile,
+        warnings,
+        ..info_from_plist(plist_info)
+    };
+    
+    Ok(ParsedArchive { info, icon, icon_entry, icon_strip })
 }
 
-/// Gets current Unix timestamp
-#[inline]
-fn current_timestamp() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+/// With `strict_bundle_id`, fails on a bundle id `is_valid_bundle_id` rejects
+pub(crate) fn check_bundle_id(bundle_id: &str, options: &ParseOptions) -> Result<()> {
+    if options.strict_bundle_id && !plist_parser::is_valid_bundle_id(bundle_id) {
+        return Err(error::IpaError::InvalidBundleId(bundle_id.to_string()));
+    }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// The fields that come straight from Info.plist (and its localizations); the
+/// rest keep their defaults, apart from `Timestamp`
+pub(crate) fn info_from_plist(plist_info: plist_parser::PlistInfo) -> IpaInfo {
+    let build_info = plist_parser::BuildInfo::from_plist_info(&plist_info);
+    IpaInfo {
+        container_layout: plist_info.layout,
+        prerendered_icon: plist_info.prerendered_icon,
+        app_name: plist_info.app_name,
+        app_version: plist_info.app_version,
+        build_number: plist_info.build_number,
+        bundle_id_valid: Some(plist_parser::is_valid_bundle_id(&plist_info.bundle_identifier)),
+        bundle_identifier: plist_info.bundle_identifier,
+        external_accessory_protocols: plist_info.external_accessory_protocols,
+        game_controller_profiles: plist_info.game_controller_profiles,
+        supports_game_controllers: plist_info.supports_game_controllers,
+        uses_non_exempt_encryption: plist_info.uses_non_exempt_encryption,
+        accent_color_name: plist_info.accent_color_name,
+        allows_mixed_localizations: plist_info.allows_mixed_localizations,
+        development_region: plist_info.development_region,
+        category: plist_info.category,
+        shortcut_items: plist_info.shortcut_items,
+        user_activity_types: plist_info.user_activity_types,
+        device_families: plist_info.device_families,
+        required_capabilities: plist_info.required_capabilities,
+        url_schemes: plist_info.url_schemes,
+        usage_descriptions: plist_info.usage_descriptions.into_iter().collect(),
+        localized_names: plist_info.localized_names.into_iter().collect(),
+        build_info,
+        minimum_os_version: plist_info.minimum_os_version,
+        timestamp: current_timestamp(),
+        ..Default::default()
+    }
+}
 
-    #[test]
-    fn test_default_options() {
-        let opts = ParseOptions::default();
-        assert!(opts.extract_icons);
+/// What the output reports about the normalized icon, before it is re-encoded
+#[derive(Default)]
+pub(crate) struct IconDetails {
+    pub(crate) dimensions: Option<(u32, u32)>,
+    pub(crate) phash: Option<String>,
+    pub(crate) is_placeholder: Option<bool>,
+    pub(crate) has_transparency: Option<bool>,
+}
+
+/// Applies the icon options that work on the normalized PNG: CRC checks,
+/// resizing and flattening, then dimensions, phash, placeholder and transparency detection
+pub(crate) fn prepare_icon(icon: Option<Vec<u8>>, options: &ParseOptions) -> Result<(Option<Vec<u8>>, IconDetails)> {
+    let Some(icon) = icon else {
+        return Ok((None, IconDetails::default()));
+    };
+    let limits = options.icon_decode_limits;
+    
+    if options.verify_icon_crcs {
+        png_normalizer::verify_chunk_crcs(&icon)?;
+    }
+    let icon = match options.icon_resize {
+        Some(size) => png_normalizer::resize_png(&icon, size, limits)?,
+        None => icon,
+    };
+    let icon = if options.flatten_opaque_icons {
+        png_normalizer::flatten_opaque_png(&icon, limits)?
+    } else {
+        icon
+    };
+    
+    let details = IconDetails {
+        dimensions: Some(png_normalizer::png_dimensions(&icon)?),
+        phash: options.compute_icon_phash.then(|| icon_analysis::dhash(&icon, limits)).transpose()?,
+        is_placeholder: options
+            .detect_placeholder_icons
+            .then(|| icon_analysis::is_placeholder_icon(&icon, limits))
+            .transpose()?,
+        has_transparency: options
+            .detect_icon_transparency
+            .then(|| png_normalizer::has_transparency(&icon, limits))
+            .transpose()?,
+    };
+    Ok((Some(icon), details))
+}
+
+/// Converts the icon to `icon_format` and checksums the result
+/// Runs last, since everything before it decodes the icon as PNG
+pub(crate) fn finish_icon(icon: Option<Vec<u8>>, options: &ParseOptions) -> Result<(Option<Vec<u8>>, Option<String>)> {
+    let icon = match icon {
+        Some(icon) if options.icon_format != icon_extractor::IconFormat::Png => {
+            Some(png_normalizer::encode_icon(&icon, options.icon_format, options.icon_decode_limits)?)
+        }
+        icon => icon,
+    };
+    
+    let checksum = match &icon {
+        Some(icon) if options.compute_icon_checksum => {
+            Some(options.hash_encoding.encode(&options.hash_algorithm.digest(icon)))
+        }
+        _ => None,
+    };
+    Ok((icon, checksum))
+}
+
+/// SwiftUI lifecycle heuristic: the executable links SwiftUI, the plist has a
+/// UIApplicationSceneManifest, and nothing names a UIKit app delegate, storyboard
+/// or scene delegate. UIKit apps that host SwiftUI views name one of those, and
+/// a SwiftUI `App` gets its scene manifest generated by Xcode. An app that sets
+/// its delegate in code only and never links SwiftUI directly can still fool it
+fn likely_swiftui(plist_info: &plist_parser::PlistInfo, macho: &macho::MachOInfo) -> bool {
+    macho.links_framework("SwiftUI") && plist_info.has_scene_manifest && !plist_info.declares_uikit_entry_point
+}
+
+/// Reads the load commands of the bundle's CFBundleExecutable
+/// A missing or unreadable executable becomes a warning rather than a failed parse
+fn read_executable_info<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    plist_info: &plist_parser::PlistInfo,
+    warnings: &mut Vec<String>,
+) -> Result<Option<macho::MachOInfo>> {
+    let Some(executable) = &plist_info.executable else {
+        warnings.push("CFBundleExecutable is not set; skipped Mach-O checks".to_string());
+        return Ok(None);
+    };
+    let path = format!("{}{}", plist_info.app_root, executable);
+    
+    for i in 0..archive.len() {
+        if archive.by_index_raw(i)?.name() == path {
+            return match macho::read_macho_info(archive.by_index(i)?) {
+                Ok(info) => Ok(Some(info)),
+                Err(e) => {
+                    warnings.push(format!("could not read {}: {}", path, e));
+                    Ok(None)
+                }
+            };
+        }
+    }
+    
+    warnings.push(format!("executable {} not found", path));
+    Ok(None)
+}
+
+/// Whether a primary declared icon exists as a loose PNG or a decodable Assets.car
+/// rendition; `CFBundleAlternateIcons` are not consulted
+fn declared_icon_present<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    plist_info: &plist_parser::PlistInfo,
+    limits: png_normalizer::DecodeLimits,
+) -> Result<bool> {
+    if icon_extractor::has_matching_icon(archive, &plist_info.icon_files)? {
+        return Ok(true);
+    }
+    let name = plist_info.icon_asset_name.as_deref().unwrap_or(icon_extractor::DEFAULT_ICON_SET);
+    let icon = icon_extractor::find_asset_catalog_icon(archive, &plist_info.app_root, name, limits);
+    // Some other "icon" set standing in for the declared one doesn't count
+    Ok(icon.ok().flatten().is_some_and(|icon| !icon.fallback))
+}
+
+/// Lays out the main icon and each sub-bundle's icon side by side
+/// Bundles without an icon are left out; returns no strip unless at least two remain
+fn compose_bundle_strip<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    app_icon: Option<&Vec<u8>>,
+    sub_bundles: &[archive_scan::SubBundle],
+    limits: png_normalizer::DecodeLimits,
+) -> Result<(Option<Vec<u8>>, Vec<String>)> {
+    let mut icons = Vec::new();
+    let mut roles = Vec::new();
+    
+    if let Some(icon) = app_icon {
+        icons.push(icon.clone());
+        roles.push("app".to_string());
+    }
+    
+    for bundle in sub_bundles {
+        let plist_path = format!("{}Info.plist", bundle.root);
+        // A malformed nested plist only costs that bundle its tile
+        let Some(dict) = archive_scan::read_entry(archive, &plist_path)?
+            .and_then(|data| plist_parser::read_plist_dict(&data).ok().flatten())
+        else {
+            continue;
+        };
+        let icon_files = plist_parser::extract_icon_set(&dict).primary;
+        
+        if let Some(icon) = icon_extractor::find_bundle_icon(archive, &bundle.root, &icon_files, limits)? {
+            icons.push(icon);
+            roles.push(bundle.role.to_string());
+        }
+    }
+    
+    if icons.len() < 2 {
+        return Ok((None, Vec::new()));
+    }
+    
+    Ok((Some(icon_analysis::compose_icon_strip(&icons, limits)?), roles))
+}
+
+/// Parses multiple IPA files in parallel (sequentially without the `native` feature)
+/// Returns an object keyed by `key_by` (in key order) or an array in input order
+pub fn parse_multiple_ipas<P: AsRef<Path>>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+) -> Result<serde_json::Value> {
+    parse_multiple_ipas_with_progress(ipa_paths, options, |_, _| {})
+}
+
+/// `parse_multiple_ipas`, calling `progress(completed, total)` as each file
+/// finishes, whether or not it parsed. Calls come from the worker threads, so
+/// they can arrive out of order; `completed` still counts up from 1 to `total`
+pub fn parse_multiple_ipas_with_progress<P, F>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+    progress: F,
+) -> Result<serde_json::Value>
+where
+    P: AsRef<Path>,
+    F: Fn(usize, usize) + Send + Sync,
+{
+    let results = drop_failures(parse_detailed_with_progress(ipa_paths, options, &progress));
+    results_to_value(results, options)
+}
+
+/// `parse_multiple_ipas` keyed by the caller's `key_fn(path, info)` instead of a
+/// `KeyStrategy`, e.g. `bundleid@version`; `key_by` and `group_by_vendor` are ignored.
+/// Failed files and anything rejected by `filter` are left out, and when two results
+/// share a key the later one in input order wins
+pub fn parse_multiple_ipas_keyed<P, F>(ipa_paths: &[P], options: &ParseOptions, key_fn: F) -> HashMap<String, IpaInfo>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path, &IpaInfo) -> String + Send + Sync,
+{
+    let mut results = parse_each_ipa(ipa_paths, options);
+    retain_matching(&mut results, options);
+    dedup_by_hash(&mut results, options);
+    key_results(results, |path, info| Some(key_fn(path, info)))
+}
+
+/// Drops the paths whose `key_by` key is already in `processed`, e.g. the keys of
+/// an earlier run's output, so an incremental batch only parses new files. File
+/// names are compared as they are; bundle ids need each archive's Info.plist,
+/// which is all that is read. Files whose plist can't be read are kept, for the
+/// full parse to report. Without `key_by` nothing is dropped
+pub fn skip_processed<P: AsRef<Path> + Sync>(
+    ipa_paths: &[P],
+    processed: &HashSet<String>,
+    options: &ParseOptions,
+) -> Vec<PathBuf> {
+    let bundle_id = |path: &Path| -> Result<String> {
+        let mut archive = open_archive(File::open(path)?)?;
+        Ok(plist_parser::extract_plist_info_with(&mut archive, options.lenient_layout)?.bundle_identifier)
+    };
+    let keep = par_map(ipa_paths, |path| {
+        let path = path.as_ref();
+        match options.key_by {
+            None => true,
+            Some(KeyStrategy::Filename) => {
+                !path.file_name().and_then(|n| n.to_str()).is_some_and(|name| processed.contains(name))
+            }
+            Some(KeyStrategy::BundleId) => bundle_id(path).map_or(true, |id| !processed.contains(&id)),
+        }
+    });
+    
+    ipa_paths
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(path, _)| path.as_ref().to_path_buf())
+        .collect()
+}
+
+/// Parses multiple IPA files in parallel and returns each result next to its path,
+/// in input order. Files that fail to parse are logged as warnings and left out
+pub fn parse_each_ipa<P: AsRef<Path>>(ipa_paths: &[P], options: &ParseOptions) -> Vec<(PathBuf, IpaInfo)> {
+    drop_failures(parse_multiple_ipas_detailed(ipa_paths, options))
+}
+
+fn drop_failures(results: Vec<(PathBuf, Result<IpaInfo>)>) -> Vec<(PathBuf, IpaInfo)> {
+    results
+        .into_iter()
+        .filter_map(|(path, result)| match result {
+            Ok(info) => Some((path, info)),
+            Err(e) => {
+                log::warn!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses multiple IPA files in parallel and returns every outcome, failures
+/// included, next to its path in input order
+pub fn parse_multiple_ipas_detailed<P: AsRef<Path>>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+) -> Vec<(PathBuf, Result<IpaInfo>)> {
+    parse_detailed_with_progress(ipa_paths, options, &|_, _| {})
+}
+
+/// Parses the IPA files `find_ipa_files(dir)` lists one at a time, in path order,
+/// as the iterator is advanced. Only one result is held at a time and dropping the
+/// iterator skips the remaining files, which suits streaming into a database.
+/// Batch-only options (`key_by`, `group_by_vendor`, `filter`, `dedup_by_hash`,
+/// `serialize_icon_writes`) don't apply; failures are yielded, not logged
+pub fn iter_ipas_in_dir<'a, P: AsRef<Path>>(
+    dir: P,
+    options: &'a ParseOptions,
+) -> Result<impl Iterator<Item = (PathBuf, Result<IpaInfo>)> + 'a> {
+    // Shared so `content_addressed_icons` still writes each icon once per directory
+    let writer = icon_extractor::IconWriter::direct();
+    Ok(find_ipa_files(dir)?.into_iter().enumerate().map(move |(index, path)| {
+        let result = parse_ipa_with_writer(&path, options, writer.slot(index));
+        (path, result)
+    }))
+}
+
+fn parse_detailed_with_progress<P: AsRef<Path>>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Vec<(PathBuf, Result<IpaInfo>)> {
+    // Convert to PathBuf for parallel iteration
+    let paths: Vec<(usize, PathBuf)> = ipa_paths.iter().map(|p| p.as_ref().to_path_buf()).enumerate().collect();
+    let completed = AtomicUsize::new(0);
+    
+    // Process in parallel for maximum speed
+    let (mut results, failed) = icon_extractor::with_icon_writer(options.serialize_icon_writes, |writer| {
+        par_map(&paths, |(index, path)| {
+            let result = parse_ipa_with_writer(path, options, writer.slot(*index));
+            progress(completed.fetch_add(1, Ordering::Relaxed) + 1, paths.len());
+            (path.clone(), result)
+        })
+    });
+    for info in results.iter_mut().filter_map(|(_, result)| result.as_mut().ok()) {
+        drop_failed_icon_writes(info, &failed, options);
+    }
+    results
+}
+
+/// Where split output for `ipa_path` goes: its path relative to `input_root`,
+/// recreated under `output_root` with `.json` appended to the file name.
+/// The original extension stays, so `App.ipa` and `App.tipa` get separate files.
+/// Paths outside `input_root` fall back to just the file name
+pub fn split_output_path(output_root: &Path, input_root: &Path, ipa_path: &Path) -> PathBuf {
+    let relative = match ipa_path.strip_prefix(input_root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative,
+        _ => ipa_path.file_name().map(Path::new).unwrap_or(ipa_path),
+    };
+    let mut target = output_root.join(relative).into_os_string();
+    target.push(".json");
+    PathBuf::from(target)
+}
+
+/// Shapes batch results into the output form selected by group_by_vendor/key_by,
+/// after dropping anything rejected by the configured filter and then folding
+/// byte-identical copies together (filtering first, so a rejected first copy
+/// doesn't take the copies that pass with it)
+pub(crate) fn results_to_value(
+    mut results: Vec<(PathBuf, IpaInfo)>,
+    options: &ParseOptions,
+) -> Result<serde_json::Value> {
+    retain_matching(&mut results, options);
+    dedup_by_hash(&mut results, options);
+    
+    if let Some(depth) = options.group_by_vendor {
+        let infos: Vec<_> = results.into_iter().map(|(_, info)| info).collect();
+        Ok(serde_json::to_value(group_by_vendor(infos, depth))?)
+    } else if let Some(key_by) = options.key_by {
+        let map: BTreeMap<_, _> = key_results(results, |path, info| key_by.key(path, info));
+        Ok(serde_json::to_value(map)?)
+    } else {
+        let vec: Vec<_> = results.into_iter().map(|(_, info)| info).collect();
+        Ok(serde_json::to_value(vec)?)
+    }
+}
+
+/// With `dedup_by_hash`, keeps the first result for each `FileSHA256` and records
+/// the file names of later copies in its `Aliases`; results without a hash all stay.
+/// Runs after `retain_matching`, so only copies that passed the filter are folded
+fn dedup_by_hash(results: &mut Vec<(PathBuf, IpaInfo)>, options: &ParseOptions) {
+    if !options.dedup_by_hash {
+        return;
+    }
+    
+    let mut first_by_hash: HashMap<String, usize> = HashMap::new();
+    let mut kept: Vec<(PathBuf, IpaInfo)> = Vec::with_capacity(results.len());
+    for (path, info) in results.drain(..) {
+        let Some(hash) = info.file_sha256.clone() else {
+            kept.push((path, info));
+            continue;
+        };
+        match first_by_hash.get(&hash) {
+            Some(&index) => {
+                let alias = info.file_name.unwrap_or_else(|| path.display().to_string());
+                kept[index].1.aliases.push(alias);
+            }
+            None => {
+                first_by_hash.insert(hash, kept.len());
+                kept.push((path, info));
+            }
+        }
+    }
+    *results = kept;
+}
+
+fn retain_matching(results: &mut Vec<(PathBuf, IpaInfo)>, options: &ParseOptions) {
+    if let Some(filter) = &options.filter {
+        results.retain(|(_, info)| filter.matches(info));
+    }
+}
+
+/// Collects results under their keys, skipping those `key_fn` has no key for;
+/// later results replace earlier ones with the same key
+fn key_results<M, F>(results: Vec<(PathBuf, IpaInfo)>, key_fn: F) -> M
+where
+    M: FromIterator<(String, IpaInfo)>,
+    F: Fn(&Path, &IpaInfo) -> Option<String>,
+{
+    results
+        .into_iter()
+        .filter_map(|(path, info)| Some((key_fn(&path, &info)?, info)))
+        .collect()
+}
+
+/// Maps over items in parallel with rayon, or sequentially without the `native` feature
+pub(crate) fn par_map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "native")]
+    return items.par_iter().map(f).collect();
+    
+    #[cfg(not(feature = "native"))]
+    items.iter().map(f).collect()
+}
+
+/// Groups parsed results by the reverse-DNS vendor prefix of their bundle ids
+/// e.g. depth 2 puts `com.acme.app` and `com.acme.tool` under `com.acme`
+pub fn group_by_vendor(infos: Vec<IpaInfo>, depth: usize) -> BTreeMap<String, Vec<IpaInfo>> {
+    let mut groups: BTreeMap<String, Vec<IpaInfo>> = BTreeMap::new();
+    
+    for info in infos {
+        let prefix = vendor_prefix(&info.bundle_identifier, depth);
+        groups.entry(prefix).or_default().push(info);
+    }
+    
+    groups
+}
+
+/// Counts of capability fields across a batch of parsed apps
+/// Each count is the number of apps where the field is set / true / non-empty
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    #[serde(rename = "TotalApps")]
+    pub total_apps: usize,
+    #[serde(rename = "SupportsGameControllers")]
+    pub supports_game_controllers: usize,
+    #[serde(rename = "GameControllerProfiles")]
+    pub game_controller_profiles: usize,
+    #[serde(rename = "ExternalAccessoryProtocols")]
+    pub external_accessory_protocols: usize,
+    /// ITSAppUsesNonExemptEncryption = true
+    #[serde(rename = "UsesNonExemptEncryption")]
+    pub uses_non_exempt_encryption: usize,
+    /// ITSAppUsesNonExemptEncryption = false
+    #[serde(rename = "DeclaresExemptEncryption")]
+    pub declares_exempt_encryption: usize,
+    #[serde(rename = "AccentColorName")]
+    pub accent_color_name: usize,
+    /// CFBundleAllowMixedLocalizations = true
+    #[serde(rename = "AllowsMixedLocalizations")]
+    pub allows_mixed_localizations: usize,
+    #[serde(rename = "ShortcutItems")]
+    pub shortcut_items: usize,
+    #[serde(rename = "HasSettingsBundle")]
+    pub has_settings_bundle: usize,
+    /// Only counted when placeholder detection was enabled
+    #[serde(rename = "PlaceholderIcons")]
+    pub placeholder_icons: usize,
+    #[serde(rename = "DuplicateEntries")]
+    pub duplicate_entries: usize,
+    /// Apps per CFBundleDevelopmentRegion
+    #[serde(rename = "DevelopmentRegions")]
+    pub development_regions: BTreeMap<String, usize>,
+}
+
+/// Reduces a batch of results to capability counts
+pub fn summarize(infos: &[IpaInfo]) -> BatchSummary {
+    let mut summary = BatchSummary {
+        total_apps: infos.len(),
+        ..Default::default()
+    };
+    
+    for info in infos {
+        summary.supports_game_controllers += usize::from(info.supports_game_controllers);
+        summary.game_controller_profiles += usize::from(!info.game_controller_profiles.is_empty());
+        summary.external_accessory_protocols += usize::from(!info.external_accessory_protocols.is_empty());
+        summary.uses_non_exempt_encryption += usize::from(info.uses_non_exempt_encryption == Some(true));
+        summary.declares_exempt_encryption += usize::from(info.uses_non_exempt_encryption == Some(false));
+        summary.accent_color_name += usize::from(info.accent_color_name.is_some());
+        summary.allows_mixed_localizations += usize::from(info.allows_mixed_localizations == Some(true));
+        summary.shortcut_items += usize::from(!info.shortcut_items.is_empty());
+        summary.has_settings_bundle += usize::from(info.has_settings_bundle);
+        summary.placeholder_icons += usize::from(info.icon_is_placeholder == Some(true));
+        summary.duplicate_entries += usize::from(!info.duplicate_entries.is_empty());
+        if let Some(region) = &info.development_region {
+            *summary.development_regions.entry(region.clone()).or_default() += 1;
+        }
+    }
+    
+    summary
+}
+
+/// Returns the first `depth` dot-separated components of a bundle id
+/// Bundle ids with fewer components are returned unchanged
+pub fn vendor_prefix(bundle_id: &str, depth: usize) -> String {
+    bundle_id
+        .split('.')
+        .take(depth.max(1))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Finds all IPA files (`.ipa` and `.tipa`) in a directory, sorted by path
+pub fn find_ipa_files<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let mut ipa_files = Vec::new();
+    
+    if !dir.is_dir() {
+        return Ok(ipa_files);
+    }
+    
+    scan_dir(dir, &mut ipa_files, None)?;
+    ipa_files.sort();
+    Ok(ipa_files)
+}
+
+/// Finds all IPA files under a directory, descending into subdirectories,
+/// sorted by path. Symlinked directories are not followed, so link cycles
+/// can't recurse forever; subdirectories that can't be read (e.g. permission
+/// denied) are skipped, each one logged with `log::warn!`
+pub fn find_ipa_files_recursive<P: AsRef<Path>>(dir: P) -> Result<Vec<PathBuf>> {
+    let mut ipa_files = Vec::new();
+    let mut pending = Vec::new();
+    
+    if !dir.as_ref().is_dir() {
+        return Ok(ipa_files);
+    }
+    
+    // The top-level directory itself must be readable
+    scan_dir(dir.as_ref(), &mut ipa_files, Some(&mut pending))?;
+    while let Some(dir) = pending.pop() {
+        if let Err(e) = scan_dir(&dir, &mut ipa_files, Some(&mut pending)) {
+            log::warn!("Skipping {}: {}", dir.display(), e);
+        }
+    }
+    
+    ipa_files.sort();
+    Ok(ipa_files)
+}
+
+/// Adds `dir`'s IPA files to `ipa_files` and, when given, its real (non-symlink)
+/// subdirectories to `subdirs`
+fn scan_dir(dir: &Path, ipa_files: &mut Vec<PathBuf>, mut subdirs: Option<&mut Vec<PathBuf>>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        
+        if let Some(subdirs) = subdirs.as_deref_mut() {
+            if entry.file_type()?.is_dir() {
+                subdirs.push(path);
+                continue;
+            }
+        }
+        
+        if path.is_file() {
+            if let Some(ext) = path.extension() {
+                if ext.eq_ignore_ascii_case("ipa") || ext.eq_ignore_ascii_case("tipa") {
+                    ipa_files.push(path);
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Computes a file's digest, written out like `FileHash`
+#[inline]
+pub(crate) fn compute_file_hash<P: AsRef<Path>>(path: P, algorithm: HashAlgo, encoding: HashEncoding) -> Result<String> {
+    Ok(encoding.encode(&compute_file_digest(path, algorithm)?))
+}
+
+/// Computes a file's digest efficiently using memory mapping for large files
+pub(crate) fn compute_file_digest<P: AsRef<Path>>(path: P, algorithm: HashAlgo) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+    
+    // Use memory mapping for files larger than 1MB for better performance
+    #[cfg(feature = "native")]
+    if file.metadata()?.len() > 1_048_576 {
+        // Memory-mapped I/O for large files
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        return Ok(algorithm.digest(&mmap[..]));
+    }
+    
+    compute_reader_digest(&mut file, algorithm)
+}
+
+/// Computes the digest of everything left in a reader
+pub(crate) fn compute_reader_digest<R: Read>(reader: &mut R, algorithm: HashAlgo) -> Result<Vec<u8>> {
+    let mut hasher = algorithm.hasher();
+    
+    // Buffered reading with a larger buffer
+    let mut buffer = vec![0; 65536]; // 64KB buffer for better performance
+    
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    
+    Ok(hasher.finish())
+}
+
+/// Gets current Unix timestamp
+/// wasm32-unknown-unknown has no system clock, so it reports 0 there
+#[inline]
+fn current_timestamp() -> u64 {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    return 0;
+    
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::TempDir;
+
+    #[test]
+    fn test_default_options() {
+        let opts = ParseOptions::default();
+        assert!(opts.extract_icons);
         assert_eq!(opts.icon_output_dir, PathBuf::from("icons"));
         assert!(opts.key_by.is_none());
+        assert!(opts.group_by_vendor.is_none());
+    }
+
+    #[test]
+    fn test_parse_options_builder() {
+        let opts = ParseOptions::builder()
+            .extract_icons(false)
+            .icon_output_dir("out/icons")
+            .key_by(KeyStrategy::BundleId)
+            .build();
+        assert!(!opts.extract_icons);
+        assert_eq!(opts.icon_output_dir, PathBuf::from("out/icons"));
+        assert_eq!(opts.key_by, Some(KeyStrategy::BundleId));
+        assert!(!opts.parse_macho);
+        
+        let info = IpaInfo { bundle_identifier: "com.example.demo".to_string(), ..Default::default() };
+        let path = Path::new("/ipas/demo.ipa");
+        assert_eq!(KeyStrategy::Filename.key(path, &info).as_deref(), Some("demo.ipa"));
+        assert_eq!(KeyStrategy::BundleId.key(path, &info).as_deref(), Some("com.example.demo"));
+    }
+
+    #[test]
+    fn test_group_by_vendor() {
+        let app = |id: &str| IpaInfo {
+            bundle_identifier: id.to_string(),
+            ..Default::default()
+        };
+        
+        assert_eq!(vendor_prefix("com.acme.app", 2), "com.acme");
+        assert_eq!(vendor_prefix("com.acme.app.widget", 3), "com.acme.app");
+        assert_eq!(vendor_prefix("single", 2), "single");
+        
+        let groups = group_by_vendor(
+            vec![app("com.acme.app"), app("org.example.tool"), app("com.acme.other")],
+            2,
+        );
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["com.acme"].len(), 2);
+        assert_eq!(groups["org.example"].len(), 1);
+    }
+
+    #[test]
+    fn test_filter_drops_non_matching_results() {
+        let result = |id: &str| {
+            (
+                PathBuf::from(format!("{}.ipa", id)),
+                IpaInfo {
+                    bundle_identifier: id.to_string(),
+                    ..Default::default()
+                },
+            )
+        };
+        let options = ParseOptions {
+            filter: Some(ResultFilter::new(|info| info.bundle_identifier.starts_with("com.acme."))),
+            ..Default::default()
+        };
+        
+        let value = results_to_value(vec![result("com.acme.app"), result("org.other.app")], &options)
+            .unwrap();
+        let apps = value.as_array().unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0]["AppBundleIdentifier"], "com.acme.app");
+    }
+
+    #[test]
+    fn test_installed_size() {
+        use std::io::Write;
+        use test_support::{info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("Demo", "com.example.demo"));
+        let executable = vec![0u8; 64 * 1024];
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let deflated = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        writer.add_directory("Payload/Demo.app/", deflated).unwrap();
+        writer.start_file("Payload/Demo.app/Info.plist", deflated).unwrap();
+        writer.write_all(&plist).unwrap();
+        writer.start_file("Payload/Demo.app/Demo", deflated).unwrap();
+        writer.write_all(&executable).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(info.installed_size, (plist.len() + executable.len()) as u64);
+        assert!(info.app_size.unwrap() < info.installed_size);
+    }
+
+    #[test]
+    fn test_zip64_archive() {
+        use test_support::{icon_ipa, rgba_png, zip64};
+        
+        let icon = rgba_png(4, 4, [10, 20, 30, 255]);
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &icon), ("Assets/level.pak", b"")]);
+        let options = ParseOptions { compute_icon_checksum: true, ..Default::default() };
+        let small = parse_ipa_bytes(&data, None, &options).unwrap().0.installed_size;
+        // Bigger than any 32-bit size field can hold
+        let huge = 5 * 1024 * 1024 * 1024u64;
+        let data = zip64(&data, &[("Payload/Demo.app/Assets/level.pak", huge)]);
+        
+        let (info, saved) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.bundle_identifier, "com.example.demo");
+        assert_eq!(info.installed_size, huge + small);
+        assert_eq!(info.icon_width, Some(4));
+        assert_eq!(saved.unwrap(), icon);
+    }
+
+    #[test]
+    fn test_icon_checksum_follows_hash_algorithm() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let data = icon_ipa(&["AppIcon60x60"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [10, 20, 30, 255]))]);
+        let options = ParseOptions {
+            compute_icon_checksum: true,
+            hash_algorithm: HashAlgo::Sha256,
+            hash_encoding: HashEncoding::UpperHex,
+            ..Default::default()
+        };
+        
+        let (info, saved) = parse_ipa_bytes(&data, None, &options).unwrap();
+        let checksum = info.icon_checksum.unwrap();
+        assert_eq!(checksum, HashEncoding::UpperHex.encode(&HashAlgo::Sha256.digest(&saved.unwrap())));
+        assert_eq!(checksum.len(), 64);
+        
+        let md5 = ParseOptions { compute_icon_checksum: true, ..Default::default() };
+        let (info, saved) = parse_ipa_bytes(&data, None, &md5).unwrap();
+        assert_eq!(info.icon_checksum, Some(format!("{:x}", md5::compute(saved.unwrap()))));
+    }
+
+    #[test]
+    fn test_parse_ipa_bytes() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let icon = rgba_png(4, 4, [10, 20, 30, 255]);
+        let data = icon_ipa(&["AppIcon60x60"], &[("AppIcon60x60@2x.png", &icon)]);
+        
+        let (info, icon_bytes) =
+            parse_ipa_bytes(&data, Some("demo.ipa".to_string()), &ParseOptions::default()).unwrap();
+        assert_eq!(info.app_name, "Demo");
+        assert_eq!(info.app_size, Some(data.len() as u64));
+        assert_eq!(info.file_name.as_deref(), Some("demo.ipa"));
+        assert_eq!(info.icon_name, Some(format!("{:x}.png", md5::compute(&data))));
+        assert_eq!(icon_bytes.as_ref(), Some(&icon));
+        
+        let options = ParseOptions { hash_encoding: HashEncoding::UpperHex, ..Default::default() };
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.file_hash, Some(format!("{:X}", md5::compute(&data))));
+        
+        let (from_reader, reader_icon) =
+            parse_ipa_reader(std::io::Cursor::new(&data), &ParseOptions::default()).unwrap();
+        assert_eq!(from_reader.app_size, None);
+        assert!(serde_json::to_value(&from_reader).unwrap().get("AppSize").is_none());
+        assert_eq!(from_reader.icon_name, Some(format!("{:x}.png", md5::compute(&data))));
+        assert_eq!(reader_icon, Some(icon));
+    }
+
+    #[test]
+    fn test_parse_ipa_reader_and_save_icon() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [1, 2, 3, 255]))]);
+        let dir = TempDir::new("reader");
+        let options = ParseOptions { icon_output_dir: dir.to_path_buf(), ..Default::default() };
+        
+        let info = parse_ipa_reader_and_save_icon(
+            std::io::Cursor::new(data.clone()),
+            Some("demo.ipa".to_string()),
+            Some(data.len() as u64),
+            &options,
+        )
+        .unwrap();
+        let saved = dir.join(info.icon_name.as_deref().unwrap()).is_file();
+        
+        assert!(saved);
+        assert_eq!(info.file_hash, Some(format!("{:x}", md5::compute(&data))));
+        assert_eq!(info.file_name.as_deref(), Some("demo.ipa"));
+        assert_eq!(info.app_size, Some(data.len() as u64));
+    }
+
+    #[test]
+    fn test_hash_algorithm_applies_to_file_and_bytes() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [1, 2, 3, 255]))]);
+        let dir = TempDir::new("sha");
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, &data).unwrap();
+        
+        for algorithm in [HashAlgo::Md5, HashAlgo::Sha1, HashAlgo::Sha256] {
+            let options = ParseOptions {
+                hash_algorithm: algorithm,
+                icon_output_dir: dir.to_path_buf(),
+                ..Default::default()
+            };
+            let expected = HashEncoding::LowerHex.encode(&algorithm.digest(&data));
+            
+            let from_file = parse_ipa(&path, &options).unwrap();
+            let (from_bytes, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+            assert_eq!(from_file.file_hash.as_deref(), Some(expected.as_str()));
+            assert_eq!(from_bytes.file_hash, from_file.file_hash);
+            assert_eq!(from_file.icon_name, Some(format!("{}.png", expected)));
+            assert_eq!(compute_file_hash(&path, algorithm, HashEncoding::LowerHex).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_multiple_ipas_detailed_keeps_failures() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("detailed");
+        let good = dir.join("good.ipa");
+        let bad = dir.join("bad.ipa");
+        let missing = dir.join("missing.ipa");
+        std::fs::write(&good, build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))])).unwrap();
+        std::fs::write(&bad, b"not a zip").unwrap();
+        
+        let results = parse_multiple_ipas_detailed(&[&bad, &good, &missing], &ParseOptions::default());
+        let kept = parse_each_ipa(&[&bad, &good, &missing], &ParseOptions::default());
+        
+        let paths: Vec<_> = results.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(paths, vec![bad, good.clone(), missing]);
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].1.as_ref().unwrap().app_name, "Demo");
+        assert!(results[2].1.is_err());
+        
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, good);
+    }
+
+    #[test]
+    fn test_iter_ipas_in_dir_is_lazy() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("iter");
+        let ipa = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        std::fs::write(dir.join("a.ipa"), &ipa).unwrap();
+        std::fs::write(dir.join("b.IPA"), b"not a zip").unwrap();
+        std::fs::write(dir.join("c.ipa"), &ipa).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        
+        let mut iter = iter_ipas_in_dir(&dir, &options).unwrap();
+        let (path, first) = iter.next().unwrap();
+        assert_eq!((path, first.unwrap().app_name), (dir.join("a.ipa"), "Demo".to_string()));
+        // Files are only opened when reached, so this one now fails to parse
+        std::fs::remove_file(dir.join("c.ipa")).unwrap();
+        let rest: Vec<_> = iter.collect();
+        
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].0, dir.join("b.IPA"));
+        assert!(rest.iter().all(|(_, result)| result.is_err()));
+        // A directory that has gone away yields nothing
+        let gone = dir.to_path_buf();
+        drop(dir);
+        assert_eq!(iter_ipas_in_dir(&gone, &options).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_skip_processed() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("skip");
+        let paths: Vec<PathBuf> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.{}", name)));
+                let path = dir.join(format!("{}.ipa", name));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .chain([dir.join("missing.ipa")])
+            .collect();
+        let processed = HashSet::from(["a.ipa".to_string(), "com.example.b".to_string(), "missing.ipa".to_string()]);
+        let skip = |key_by| skip_processed(&paths, &processed, &ParseOptions { key_by, ..Default::default() });
+        
+        let by_name = skip(Some(KeyStrategy::Filename));
+        let by_id = skip(Some(KeyStrategy::BundleId));
+        
+        assert_eq!(by_name, [paths[1].clone()]);
+        // The missing file's plist can't be read, so it stays for the parse to report
+        assert_eq!(by_id, [paths[0].clone(), paths[2].clone()]);
+        assert_eq!(skip(None), paths);
+    }
+
+    #[test]
+    fn test_parse_multiple_ipas_keyed() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("keyed");
+        let mut paths: Vec<PathBuf> = ["a", "b", "b"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let mut plist = info_plist("Demo", &format!("com.example.{}", name));
+                plist.insert("CFBundleShortVersionString".to_string(), format!("1.{}", i).into());
+                let path = dir.join(format!("{}.ipa", i));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(plist))])).unwrap();
+                path
+            })
+            .collect();
+        paths.push(dir.join("missing.ipa"));
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        
+        let by_version = parse_multiple_ipas_keyed(&paths, &options, |_, info| {
+            format!("{}@{}", info.bundle_identifier, info.app_version)
+        });
+        let by_id = parse_multiple_ipas_keyed(&paths, &options, |_, info| info.bundle_identifier.clone());
+        
+        let mut keys: Vec<_> = by_version.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["com.example.a@1.0", "com.example.b@1.1", "com.example.b@1.2"]);
+        assert_eq!(by_id.len(), 2);
+        assert_eq!(by_id["com.example.b"].app_version, "1.2");
+    }
+
+    #[test]
+    fn test_dedup_by_hash() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("dedup");
+        let copy = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        let other = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.other")))]);
+        let paths: Vec<PathBuf> = [("a.ipa", &copy), ("b.ipa", &other), ("c.ipa", &copy)]
+            .iter()
+            .map(|(name, data)| {
+                let path = dir.join(name);
+                std::fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+        
+        let options = ParseOptions { extract_icons: false, dedup_by_hash: true, ..Default::default() };
+        let deduped = parse_multiple_ipas(&paths, &options).unwrap();
+        let all = parse_multiple_ipas(&paths, &ParseOptions { dedup_by_hash: false, ..options.clone() }).unwrap();
+        
+        let apps = deduped.as_array().unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[0]["FileName"], "a.ipa");
+        assert_eq!(apps[0]["Aliases"], serde_json::json!(["c.ipa"]));
+        assert!(apps[0]["FileSHA256"].is_string());
+        assert!(apps[1].get("Aliases").is_none());
+        assert_eq!(all.as_array().unwrap().len(), 3);
+        
+        // A filter that rejects only the first copy leaves the later one standing
+        let not_a = ResultFilter::new(|info| info.file_name.as_deref() != Some("a.ipa"));
+        let filtered = parse_multiple_ipas(&paths, &ParseOptions { filter: Some(not_a), ..options }).unwrap();
+        let apps = filtered.as_array().unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(apps[1]["FileName"], "c.ipa");
+        assert!(apps[1].get("Aliases").is_none());
+    }
+
+    #[test]
+    fn test_parse_multiple_ipas_with_progress() {
+        use std::sync::Mutex;
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("progress");
+        let mut paths: Vec<PathBuf> = (0..5)
+            .map(|i| {
+                let path = dir.join(format!("{}.ipa", i));
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.app{}", i)));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .collect();
+        paths.push(dir.join("missing.ipa"));
+        
+        let calls = Mutex::new(Vec::new());
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        let value = parse_multiple_ipas_with_progress(&paths, &options, |completed, total| {
+            calls.lock().unwrap().push((completed, total));
+        })
+        .unwrap();
+        
+        assert_eq!(value.as_array().unwrap().len(), 5);
+        let mut calls = calls.into_inner().unwrap();
+        calls.sort();
+        assert_eq!(calls, (1..=6).map(|completed| (completed, 6)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_provisioning_profile_option() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let profile = provisioning_parser::tests::mobileprovision("ABCDE12345", &["udid-1", "udid-2"]);
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo"))),
+            ("Payload/Demo.app/embedded.mobileprovision", &profile),
+        ]);
+        
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert!(info.provisioning_profile.is_none());
+        
+        let options = ParseOptions { parse_provisioning_profile: true, ..Default::default() };
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["ProvisioningProfile"]["TeamIdentifier"], "ABCDE12345");
+        assert_eq!(json["ProvisioningProfile"]["ProvisionedDevices"], serde_json::json!(["udid-1", "udid-2"]));
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert!(info.provisioning_profile.is_none());
+        assert!(info.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_ipa_with_icon_bytes() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("icon-bytes");
+        let icon_dir = dir.join("icons");
+        let icon = rgba_png(4, 4, [10, 20, 30, 255]);
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &icon)]);
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, &data).unwrap();
+        
+        let options = ParseOptions { icon_output_dir: icon_dir.clone(), ..Default::default() };
+        let (info, icon_bytes) = parse_ipa_with_icon_bytes(&path, &options).unwrap();
+        let icon_dir_exists = icon_dir.exists();
+        let saved = parse_ipa(&path, &options).unwrap();
+        
+        assert_eq!(icon_bytes, Some(icon));
+        assert!(!icon_dir_exists);
+        assert_eq!(info.icon_name, saved.icon_name);
+        assert_eq!(info.file_hash, saved.file_hash);
+        assert_eq!(info.file_name.as_deref(), Some("demo.ipa"));
+        assert_eq!(info.app_size, Some(data.len() as u64));
+    }
+
+    #[test]
+    fn test_icon_transparency_option() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let transparency = |pixel, extract_icons| {
+            let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &rgba_png(4, 4, pixel))]);
+            let options = ParseOptions { detect_icon_transparency: true, extract_icons, ..Default::default() };
+            parse_ipa_bytes(&data, None, &options).unwrap().0.icon_has_transparency
+        };
+        
+        assert_eq!(transparency([10, 20, 30, 200], true), Some(true));
+        assert_eq!(transparency([10, 20, 30, 255], true), Some(false));
+        assert_eq!(transparency([10, 20, 30, 200], false), None);
+    }
+
+    #[test]
+    fn test_icon_naming_option() {
+        use icon_extractor::IconNaming;
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("icon-naming");
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &rgba_png(4, 4, [10, 20, 30, 255]))]);
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, &data).unwrap();
+        
+        let parse = |icon_naming| {
+            let options = ParseOptions { icon_output_dir: dir.join("icons"), icon_naming, ..Default::default() };
+            parse_ipa(&path, &options).unwrap()
+        };
+        let by_id = parse(IconNaming::BundleId);
+        let first = parse(IconNaming::Sequential);
+        let second = parse(IconNaming::Sequential);
+        let by_hash = parse(IconNaming::Hash);
+        let saved = dir.join("icons").join("com.example.demo.png").is_file();
+        
+        assert_eq!(by_id.icon_name.as_deref(), Some("com.example.demo.png"));
+        assert!(saved);
+        assert_eq!(by_id.file_hash, None);
+        // Separate parses don't share a counter, so the second skips the saved 1.png
+        assert_eq!(first.icon_name.as_deref(), Some("1.png"));
+        assert_eq!(second.icon_name.as_deref(), Some("2.png"));
+        assert_eq!(second.file_hash, None);
+        let hash = format!("{:x}", md5::compute(&data));
+        assert_eq!(by_hash.icon_name, Some(format!("{}.png", hash)));
+        assert_eq!(by_hash.file_hash, Some(hash));
+        
+        assert_eq!(icon_extractor::bundle_id_icon_stem("../com.example"), "com.example");
+        assert_eq!(icon_extractor::bundle_id_icon_stem(""), "unknown");
+    }
+
+    #[test]
+    fn test_bundle_id_icons_written_once_per_batch() {
+        use icon_extractor::IconNaming;
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("bundle-id-icons");
+        let icons = dir.join("icons");
+        std::fs::create_dir_all(&icons).unwrap();
+        // Two builds of one app with different icons, and a stale file from an earlier run
+        let builds: Vec<(PathBuf, Vec<u8>)> = (0u8..2)
+            .map(|build| {
+                let icon = rgba_png(4, 4, [build, 0, 0, 255]);
+                let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &icon)]);
+                let path = dir.join(format!("demo{}.ipa", build));
+                std::fs::write(&path, data).unwrap();
+                (path, icon)
+            })
+            .collect();
+        std::fs::write(icons.join("com.example.demo.png"), b"stale").unwrap();
+        let paths: Vec<&PathBuf> = builds.iter().map(|(path, _)| path).collect();
+        let options = ParseOptions { icon_output_dir: icons.clone(), icon_naming: IconNaming::BundleId, ..Default::default() };
+        
+        let results = parse_each_ipa(&paths, &options);
+        let saved = std::fs::read(icons.join("com.example.demo.png")).unwrap();
+        let files = std::fs::read_dir(&icons).unwrap().count();
+        
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, info)| info.icon_name.as_deref() == Some("com.example.demo.png")));
+        assert_eq!(files, 1);
+        // The later build in input order keeps the icon, whichever finished first
+        assert_eq!(saved, builds[1].1);
+    }
+
+    #[test]
+    fn test_sequential_icons_follow_input_order() {
+        use icon_extractor::IconNaming;
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("sequential-icons");
+        let icons = dir.join("icons");
+        std::fs::create_dir_all(&icons).unwrap();
+        std::fs::write(icons.join("2.png"), b"from an earlier run").unwrap();
+        let mut paths: Vec<PathBuf> = (0u8..6)
+            .map(|i| {
+                let data = icon_ipa(&["AppIcon"], &[("AppIcon.png", &rgba_png(4, 4, [i, 0, 0, 255]))]);
+                let path = dir.join(format!("demo{}.ipa", i));
+                std::fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+        paths.insert(1, dir.join("missing.ipa"));
+        let options = ParseOptions { icon_output_dir: icons.clone(), icon_naming: IconNaming::Sequential, ..Default::default() };
+        
+        let results = parse_multiple_ipas_detailed(&paths, &options);
+        let (bytes_info, _) = parse_ipa_bytes(&std::fs::read(&paths[0]).unwrap(), None, &options).unwrap();
+        
+        let names: Vec<_> = results.iter().map(|(_, r)| r.as_ref().ok().and_then(|i| i.icon_name.clone())).collect();
+        // The missing file keeps its number (3) unused; the earlier run's 2.png is skipped
+        let expected = [Some("1.png"), None, Some("4.png"), Some("5.png"), Some("6.png"), Some("7.png"), Some("8.png")];
+        assert_eq!(names, expected.map(|name| name.map(String::from)));
+        // Without saving, the output directory isn't looked at
+        assert_eq!(bytes_info.icon_name.as_deref(), Some("1.png"));
+    }
+
+    #[test]
+    fn test_icon_format_option() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon@2x.png", &rgba_png(8, 8, [10, 20, 30, 255]))]);
+        let hash = format!("{:x}", md5::compute(&data));
+        
+        let options = ParseOptions { icon_format: icon_extractor::IconFormat::Webp, ..Default::default() };
+        let (info, icon) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.icon_name, Some(format!("{}.webp", hash)));
+        assert_eq!(image::guess_format(&icon.unwrap()).unwrap(), image::ImageFormat::WebP);
+        assert_eq!((info.icon_width, info.icon_height), (Some(8), Some(8)));
+        
+        let options = ParseOptions {
+            icon_format: icon_extractor::IconFormat::Jpeg,
+            icon_keep_original_name: true,
+            ..Default::default()
+        };
+        let (info, icon) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.icon_name, Some(format!("{}_AppIcon@2x.jpg", hash)));
+        assert_eq!(image::guess_format(&icon.unwrap()).unwrap(), image::ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn test_icon_dimensions() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let data = icon_ipa(
+            &["AppIcon"],
+            &[("AppIcon@2x.png", &rgba_png(6, 4, [10, 20, 30, 255])), ("AppIcon.png", &rgba_png(3, 2, [10, 20, 30, 255]))],
+        );
+        
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!((info.icon_width, info.icon_height), (Some(6), Some(4)));
+        
+        let options = ParseOptions { icon_resize: Some(3), ..Default::default() };
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!((info.icon_width, info.icon_height), (Some(3), Some(2)));
+        
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json.get("IconWidth").is_none() && json.get("IconHeight").is_none());
+    }
+
+    #[test]
+    fn test_alternate_icon_fallback() {
+        use test_support::{build_zip, info_plist, plist_xml, rgba_png};
+        
+        let icon_dict = |name: &str| {
+            let mut icon = plist::Dictionary::new();
+            icon.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec![name.into()]));
+            icon
+        };
+        let mut primary = icon_dict("AppIcon60x60");
+        primary.insert("UIPrerenderedIcon".to_string(), true.into());
+        let mut alternates = plist::Dictionary::new();
+        alternates.insert("Dark".to_string(), icon_dict("DarkIcon60x60").into());
+        let mut icons = plist::Dictionary::new();
+        icons.insert("CFBundlePrimaryIcon".to_string(), primary.into());
+        icons.insert("CFBundleAlternateIcons".to_string(), alternates.into());
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIcons".to_string(), icons.into());
+        let plist = plist_xml(plist);
+        let (small, large) = (rgba_png(2, 2, [1, 2, 3, 255]), rgba_png(8, 8, [4, 5, 6, 255]));
+        
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/DarkIcon60x60@3x.png", &large),
+            ("Payload/Demo.app/AppIcon60x60@2x.png", &small),
+        ]);
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!((info.icon_width, info.prerendered_icon), (Some(2), Some(true)));
+        assert_eq!(serde_json::to_value(&info).unwrap()["PrerenderedIcon"], true);
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist), ("Payload/Demo.app/DarkIcon60x60@3x.png", &large)]);
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(info.icon_width, Some(8));
+    }
+
+    #[test]
+    fn test_category() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let mut plist = info_plist("Demo", "com.example.demo");
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(plist.clone()))]);
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert!(serde_json::to_value(&info).unwrap().get("Category").is_none());
+        
+        plist.insert("LSApplicationCategoryType".to_string(), "public.app-category.games".into());
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(plist))]);
+        let (info, _) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(serde_json::to_value(&info).unwrap()["Category"], "public.app-category.games");
+        
+        assert_eq!(plist_parser::category_label("public.app-category.games"), Some("Games"));
+        assert_eq!(plist_parser::category_label("public.app-category.role-playing-games"), Some("Role Playing Games"));
+        assert_eq!(plist_parser::category_label("public.app-category.unknown"), None);
+        assert_eq!(plist_parser::category_label("games"), None);
+    }
+
+    #[test]
+    fn test_archive_preflight_errors() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let message = |data: &[u8]| match parse_ipa_bytes(data, None, &ParseOptions::default()) {
+            Err(error::IpaError::InvalidIpa(msg)) => msg,
+            other => panic!("expected InvalidIpa, got {:?}", other.map(|(info, _)| info)),
+        };
+        
+        let no_payload = build_zip(&[("Docs/readme.txt", b"hi".as_slice())]);
+        assert_eq!(message(&no_payload), "archive contains no Payload/*.app directory");
+        
+        let valid = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        assert!(message(&valid[..valid.len() / 2]).contains("truncated"));
+        
+        // An app directory without Info.plist is still a missing plist, not a non-IPA
+        let no_plist = build_zip(&[("Payload/Demo.app/Demo", b"".as_slice())]);
+        assert!(matches!(
+            parse_ipa_bytes(&no_plist, None, &ParseOptions::default()),
+            Err(error::IpaError::InfoPlistNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_include_file_hash() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        let expected = HashEncoding::LowerHex.encode(&HashAlgo::Sha256.digest(&data));
+        let dir = TempDir::new("sha256");
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, &data).unwrap();
+        let options = ParseOptions { include_file_hash: true, ..Default::default() };
+        
+        let from_file = parse_ipa(&path, &options).unwrap();
+        let without = parse_ipa(&path, &ParseOptions::default()).unwrap();
+        let (from_bytes, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        let (from_reader, _) = parse_ipa_reader(std::io::Cursor::new(&data), &options).unwrap();
+        
+        assert_eq!(from_file.file_sha256.as_deref(), Some(expected.as_str()));
+        assert_eq!(from_bytes.file_sha256, from_file.file_sha256);
+        assert_eq!(from_reader.file_sha256, from_file.file_sha256);
+        // No icon, so there is no icon-naming hash either way
+        assert_eq!(from_file.file_hash, None);
+        assert!(serde_json::to_value(&without).unwrap().get("FileSHA256").is_none());
+    }
+
+    #[test]
+    fn test_minimum_os_version_in_json() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let to_json = |minimum_os: Option<&str>| {
+            let mut plist = info_plist("Demo", "com.example.demo");
+            if let Some(minimum_os) = minimum_os {
+                plist.insert("MinimumOSVersion".to_string(), minimum_os.into());
+            }
+            let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(plist))]);
+            serde_json::to_value(parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap().0).unwrap()
+        };
+        
+        assert_eq!(to_json(Some("13.0"))["MinimumOSVersion"], "13.0");
+        assert!(to_json(None).get("MinimumOSVersion").is_none());
+    }
+
+    #[test]
+    fn test_min_os_consistency() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let parse = |minimum_os: &str| {
+            let mut plist = info_plist("Demo", "com.example.demo");
+            plist.insert("CFBundleExecutable".to_string(), "Demo".into());
+            plist.insert("MinimumOSVersion".to_string(), minimum_os.into());
+            let data = build_zip(&[
+                ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+                ("Payload/Demo.app/Demo", &macho::tests::thin_macho(0x000f_0000, 0x0011_0000)),
+            ]);
+            let options = ParseOptions { parse_macho: true, ..Default::default() };
+            parse_ipa_bytes(&data, None, &options).unwrap().0
+        };
+        
+        let info = parse("15.0");
+        assert_eq!(info.macho_min_os.as_deref(), Some("15.0"));
+        assert_eq!(info.min_os_consistent, Some(true));
+        assert!(info.warnings.is_empty());
+        
+        let info = parse("14.0");
+        assert_eq!(info.min_os_consistent, Some(false));
+        assert_eq!(info.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_likely_swiftui() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let parse = |dylibs: &[&str], scene_delegate: bool| {
+            let mut configuration = plist::Dictionary::new();
+            if scene_delegate {
+                configuration.insert("UISceneDelegateClassName".to_string(), "SceneDelegate".into());
+            }
+            let mut configurations = plist::Dictionary::new();
+            configurations.insert(
+                "UIWindowSceneSessionRoleApplication".to_string(),
+                plist::Value::Array(vec![configuration.into()]),
+            );
+            let mut manifest = plist::Dictionary::new();
+            manifest.insert("UIApplicationSupportsMultipleScenes".to_string(), true.into());
+            manifest.insert("UISceneConfigurations".to_string(), configurations.into());
+            
+            let mut plist = info_plist("Demo", "com.example.demo");
+            plist.insert("CFBundleExecutable".to_string(), "Demo".into());
+            plist.insert("UIApplicationSceneManifest".to_string(), manifest.into());
+            let data = build_zip(&[
+                ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+                ("Payload/Demo.app/Demo", &macho::tests::thin_macho_linking(0x000f_0000, 0x0011_0000, dylibs)),
+            ]);
+            let options = ParseOptions { detect_swiftui_lifecycle: true, ..Default::default() };
+            parse_ipa_bytes(&data, None, &options).unwrap().0
+        };
+        let swiftui = ["/System/Library/Frameworks/SwiftUI.framework/SwiftUI"];
+        
+        let info = parse(&swiftui, false);
+        assert_eq!(info.likely_swiftui, Some(true));
+        // Only asked for the lifecycle, so the deployment target stays out
+        assert_eq!(info.macho_min_os, None);
+        assert_eq!(parse(&swiftui, true).likely_swiftui, Some(false));
+        assert_eq!(parse(&[], false).likely_swiftui, Some(false));
+    }
+
+    #[test]
+    fn test_architectures_option() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleExecutable".to_string(), "Demo".into());
+        let plist = plist_xml(plist);
+        let options = ParseOptions { detect_architectures: true, ..Default::default() };
+        
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/Demo", &macho::tests::thin_macho(0x000f_0000, 0x0011_0000)),
+        ]);
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.architectures, vec!["arm64"]);
+        assert_eq!(info.macho_min_os, None);
+        assert!(parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap().0.architectures.is_empty());
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist)]);
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert!(info.architectures.is_empty());
+        assert_eq!(info.warnings, vec!["executable Payload/Demo.app/Demo not found"]);
+    }
+
+    #[test]
+    fn test_encryption_option() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleExecutable".to_string(), "Demo".into());
+        let plist = plist_xml(plist);
+        let options = ParseOptions { detect_encryption: true, ..Default::default() };
+        
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/Demo", &macho::tests::thin_macho(0x000f_0000, 0x0011_0000)),
+        ]);
+        assert_eq!(parse_ipa_bytes(&data, None, &options).unwrap().0.is_encrypted, Some(false));
+        assert_eq!(parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap().0.is_encrypted, None);
+        
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/Demo", b"#!/bin/sh\n".as_slice()),
+        ]);
+        assert_eq!(parse_ipa_bytes(&data, None, &options).unwrap().0.is_encrypted, None);
+    }
+
+    #[test]
+    fn test_icon_from_asset_catalog() {
+        use test_support::{build_zip, info_plist, plist_xml, rgba_png};
+        
+        let icon = rgba_png(8, 8, [9, 8, 7, 255]);
+        let car = asset_catalog::tests::build_car(
+            &[("AppIcon", 3)],
+            &[(3, asset_catalog::tests::png_rendition("AppIcon60x60@2x.png", &icon, 8))],
+        );
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIconName".to_string(), "AppIcon".into());
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+            ("Payload/Demo.app/Assets.car", &car),
+        ]);
+        
+        let (info, extracted) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(extracted, Some(icon.clone()));
+        assert!(info.icon_name.is_some());
+        assert!(info.warnings.is_empty());
+        
+        // No CFBundleIconName: the default AppIcon set is still found
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo"))),
+            ("Payload/Demo.app/Assets.car", &car),
+        ]);
+        let (_, extracted) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(extracted, Some(icon.clone()));
+        
+        // A set named in the plist that isn't in the catalog: another "…Icon" set stands in, with a warning
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIconName".to_string(), "BrandIcon".into());
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+            ("Payload/Demo.app/Assets.car", &car),
+        ]);
+        let (info, extracted) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert_eq!(extracted, Some(icon));
+        assert_eq!(
+            info.warnings,
+            vec!["Payload/Demo.app/Assets.car has no BrandIcon image set; using AppIcon60x60@2x.png, which may not be the app icon"]
+        );
+    }
+
+    #[test]
+    fn test_extra_plists() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("Demo", "com.example.demo"));
+        let mut changelog = plist::Dictionary::new();
+        changelog.insert("1.0".to_string(), "Initial release".into());
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/Changelog.plist", &plist_xml(changelog)),
+            ("Payload/Demo.app/Corrupt.plist", b"<?xml version=\"1.0\"?><plist><dict><key>"),
+        ]);
+        let options = ParseOptions {
+            extra_plists: vec!["Changelog.plist".to_string(), "Missing.plist".to_string(), "Corrupt.plist".to_string()],
+            ..Default::default()
+        };
+        
+        let (info, _) = parse_ipa_bytes(&data, None, &options).unwrap();
+        assert_eq!(info.extra_plists.len(), 1);
+        assert_eq!(info.extra_plists["Changelog.plist"], serde_json::json!({ "1.0": "Initial release" }));
+        assert_eq!(info.warnings.len(), 1);
+        assert!(info.warnings[0].starts_with("could not read extra plist Corrupt.plist: "), "{:?}", info.warnings);
+    }
+
+    #[test]
+    fn test_require_declared_icons_present() {
+        use test_support::{build_zip, icon_ipa, info_plist, plist_xml};
+        
+        let declared = icon_ipa(&["AppIcon60x60"], &[]);
+        let undeclared = icon_ipa(&[], &[]);
+        let options = ParseOptions { require_declared_icons_present: true, ..Default::default() };
+        
+        assert!(parse_ipa_bytes(&declared, None, &ParseOptions::default()).is_ok());
+        assert!(matches!(
+            parse_ipa_bytes(&declared, None, &options),
+            Err(error::IpaError::DeclaredIconsMissing(names)) if names == "AppIcon60x60"
+        ));
+        assert!(parse_ipa_bytes(&undeclared, None, &options).is_ok());
+        
+        let warn_only = ParseOptions { declared_icons_warn_only: true, ..options.clone() };
+        let (info, _) = parse_ipa_bytes(&declared, None, &warn_only).unwrap();
+        assert_eq!(info.warnings, vec!["Declared icons not found in the bundle: AppIcon60x60"]);
+        
+        // An icon that only lives in Assets.car is present, extracted or not
+        let car = asset_catalog::tests::build_car(
+            &[("AppIcon", 3)],
+            &[(3, asset_catalog::tests::png_rendition("AppIcon60x60@2x.png", &test_support::rgba_png(8, 8, [1, 2, 3, 255]), 8))],
+        );
+        let catalog = icon_ipa(&["AppIcon60x60"], &[("Assets.car", &car)]);
+        assert!(parse_ipa_bytes(&catalog, None, &options).is_ok());
+        let no_icons = ParseOptions { extract_icons: false, ..options.clone() };
+        assert!(parse_ipa_bytes(&catalog, None, &no_icons).is_ok());
+        assert!(parse_ipa_bytes(&declared, None, &no_icons).is_err());
+        
+        // An alternate icon standing in for a missing primary one doesn't count
+        let mut alternate = plist::Dictionary::new();
+        alternate.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["DarkIcon60x60".into()]));
+        let mut alternates = plist::Dictionary::new();
+        alternates.insert("Dark".to_string(), alternate.into());
+        let mut icons = plist::Dictionary::new();
+        icons.insert("CFBundleAlternateIcons".to_string(), alternates.into());
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["AppIcon60x60".into()]));
+        plist.insert("CFBundleIcons".to_string(), icons.into());
+        let alternate_only = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+            ("Payload/Demo.app/DarkIcon60x60@3x.png", &test_support::rgba_png(8, 8, [4, 5, 6, 255])),
+        ]);
+        assert!(parse_ipa_bytes(&alternate_only, None, &ParseOptions::default()).unwrap().0.icon_width.is_some());
+        assert!(parse_ipa_bytes(&alternate_only, None, &options).is_err());
+        assert!(parse_ipa_bytes(&alternate_only, None, &no_icons).is_err());
+    }
+
+    #[test]
+    fn test_strict_bundle_id() {
+        use test_support::{build_zip, info_plist, plist_xml};
+        
+        let valid = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        let invalid = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "my app")))]);
+        let strict = ParseOptions { strict_bundle_id: true, ..Default::default() };
+        
+        assert_eq!(parse_ipa_bytes(&valid, None, &ParseOptions::default()).unwrap().0.bundle_id_valid, Some(true));
+        assert!(parse_ipa_bytes(&valid, None, &strict).is_ok());
+        let (info, _) = parse_ipa_bytes(&invalid, None, &ParseOptions::default()).unwrap();
+        assert_eq!(info.bundle_id_valid, Some(false));
+        let mut json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["BundleIdValid"], false);
+        
+        // A record written before the check existed isn't read back as invalid
+        json.as_object_mut().unwrap().remove("BundleIdValid");
+        let earlier: IpaInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(earlier.bundle_id_valid, None);
+        assert!(serde_json::to_value(&earlier).unwrap().get("BundleIdValid").is_none());
+        assert!(matches!(
+            parse_ipa_bytes(&invalid, None, &strict),
+            Err(error::IpaError::InvalidBundleId(id)) if id == "my app"
+        ));
+    }
+
+    #[test]
+    fn test_macos_junk_is_ignored_for_icons() {
+        use test_support::{build_zip, info_plist, plist_xml, rgba_png};
+        
+        let mut plist = info_plist("Demo", "com.example.demo");
+        plist.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["AppIcon".into()]));
+        let icon = rgba_png(4, 4, [1, 2, 3, 255]);
+        let junk = vec![0u8; 4096];
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist_xml(plist)),
+            ("Payload/Demo.app/AppIcon60x60@2x.png", &icon),
+            // Larger than the real icon, so they would win the size comparison
+            ("__MACOSX/Payload/Demo.app/AppIcon60x60@2x.png", &junk),
+            ("Payload/Demo.app/._AppIcon60x60@3x.png", &junk),
+        ]);
+        
+        let (info, icon_bytes) = parse_ipa_bytes(&data, None, &ParseOptions::default()).unwrap();
+        assert!(info.had_macos_junk);
+        assert_eq!(serde_json::to_value(&info).unwrap()["HadMacosJunk"], true);
+        assert_eq!(icon_bytes, Some(icon));
+        
+        // Flags that are off stay out of the output, like the other optional keys
+        let json = serde_json::to_value(IpaInfo::default()).unwrap();
+        for key in ["SupportsGameControllers", "HadMacosJunk", "HasSettingsBundle", "HasOnDemandResources", "IsThinned"] {
+            assert!(json.get(key).is_none(), "{key}");
+        }
+    }
+
+    #[test]
+    fn test_icon_strip_covers_sub_bundles() {
+        use test_support::{build_zip, info_plist, plist_xml, rgba_png};
+        
+        let with_icon = |name: &str, bundle_id: &str| {
+            let mut plist = info_plist(name, bundle_id);
+            plist.insert("CFBundleIconFiles".to_string(), plist::Value::Array(vec!["AppIcon".into()]));
+            plist_xml(plist)
+        };
+        let app_plist = with_icon("Demo", "com.example.demo");
+        let clip_plist = with_icon("Clip", "com.example.demo.clip");
+        let icon = rgba_png(8, 8, [0, 0, 255, 255]);
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &app_plist),
+            ("Payload/Demo.app/AppIcon60x60@2x.png", &icon),
+            ("Payload/Demo.app/AppClips/Clip.app/Info.plist", &clip_plist),
+            ("Payload/Demo.app/AppClips/Clip.app/AppIcon60x60@2x.png", &icon),
+            // Not an App Clip or Watch app, so no tile
+            ("Payload/Demo.app/PlugIns/Widget.appex/Info.plist", &clip_plist),
+        ]);
+        
+        let dir = TempDir::new("strip");
+        let ipa = dir.join("demo.ipa");
+        std::fs::write(&ipa, &data).unwrap();
+        let options = ParseOptions {
+            icon_output_dir: dir.join("icons"),
+            compose_icon_strip: true,
+            ..Default::default()
+        };
+        
+        let info = parse_ipa(&ipa, &options).unwrap();
+        let strip = info.icon_strip.as_ref().map(|name| std::fs::read(dir.join("icons").join(name)));
+        
+        assert_eq!(info.icon_strip_roles, vec!["app", "clip"]);
+        let strip = image::load_from_memory(&strip.unwrap().unwrap()).unwrap();
+        assert_eq!(strip.width(), 2 * icon_analysis::STRIP_TILE_SIZE);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let apps = vec![
+            IpaInfo {
+                supports_game_controllers: true,
+                uses_non_exempt_encryption: Some(false),
+                development_region: Some("en".to_string()),
+                ..Default::default()
+            },
+            IpaInfo {
+                uses_non_exempt_encryption: Some(true),
+                development_region: Some("en".to_string()),
+                ..Default::default()
+            },
+            IpaInfo::default(),
+        ];
+        
+        let summary = summarize(&apps);
+        assert_eq!(summary.total_apps, 3);
+        assert_eq!(summary.supports_game_controllers, 1);
+        assert_eq!(summary.uses_non_exempt_encryption, 1);
+        assert_eq!(summary.declares_exempt_encryption, 1);
+        assert_eq!(summary.development_regions.get("en"), Some(&2));
+    }
+
+    #[test]
+    fn test_serialized_icon_writes() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("writer");
+        let paths: Vec<PathBuf> = (0..4u8)
+            .map(|i| {
+                let data = icon_ipa(&["AppIcon"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [i, 0, 0, 255]))]);
+                let path = dir.join(format!("demo{}.ipa", i));
+                std::fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions {
+            icon_output_dir: dir.join("icons"),
+            serialize_icon_writes: true,
+            ..Default::default()
+        };
+        
+        let value = parse_multiple_ipas(&paths, &options).unwrap();
+        let written = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|info| dir.join("icons").join(info["IconName"].as_str().unwrap()).is_file())
+            .count();
+        
+        assert_eq!(written, 4);
+    }
+
+    #[test]
+    fn test_failed_queued_icon_write_becomes_warning() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("write-fail");
+        let data = icon_ipa(&["AppIcon"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [1, 2, 3, 255]))]);
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, data).unwrap();
+        // A file where the icon directory should be makes every write fail
+        std::fs::write(dir.join("blocked"), b"").unwrap();
+        let options = ParseOptions {
+            icon_output_dir: dir.join("blocked/icons"),
+            serialize_icon_writes: true,
+            ..Default::default()
+        };
+        
+        let results = parse_multiple_ipas_detailed(&[&path], &options);
+        
+        let info = results[0].1.as_ref().unwrap();
+        assert_eq!(info.icon_name, None);
+        assert_eq!(info.warnings.len(), 1);
+        assert!(info.warnings[0].starts_with("could not write icon "), "{:?}", info.warnings);
+    }
+
+    #[test]
+    fn test_recursive_discovery_and_split_output_paths() {
+        let dir = TempDir::new("tree");
+        std::fs::create_dir_all(dir.join("team/beta")).unwrap();
+        for file in ["top.ipa", "top.tipa", "team/beta/nested.tipa", "team/notes.txt", "team/Alpha.IPA"] {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+        // A link back to the root must not send the walk round in circles
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("team/loop")).unwrap();
+        
+        let found = find_ipa_files_recursive(&dir).unwrap();
+        let outputs: Vec<PathBuf> = found
+            .iter()
+            .map(|path| split_output_path(Path::new("out"), &dir, path))
+            .collect();
+        let flat = find_ipa_files(&dir).unwrap();
+        
+        assert_eq!(flat.len(), 2);
+        assert_eq!(
+            outputs,
+            vec![
+                PathBuf::from("out/team/Alpha.IPA.json"),
+                PathBuf::from("out/team/beta/nested.tipa.json"),
+                PathBuf::from("out/top.ipa.json"),
+                PathBuf::from("out/top.tipa.json"),
+            ]
+        );
+        assert_eq!(
+            split_output_path(Path::new("out"), Path::new("elsewhere"), Path::new("/tmp/app.ipa")),
+            PathBuf::from("out/app.ipa.json")
+        );
+    }
+
+    #[test]
+    fn test_content_addressed_icons_dedupe_across_batch() {
+        use test_support::{icon_ipa, rgba_png};
+        
+        let dir = TempDir::new("cas");
+        // demo0 and demo2 share an icon
+        let paths: Vec<PathBuf> = [0u8, 1, 0]
+            .iter()
+            .enumerate()
+            .map(|(i, &shade)| {
+                let data = icon_ipa(&["AppIcon"], &[("AppIcon60x60@2x.png", &rgba_png(4, 4, [shade, 0, 0, 255]))]);
+                let path = dir.join(format!("demo{}.ipa", i));
+                std::fs::write(&path, data).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions {
+            icon_output_dir: dir.join("icons"),
+            content_addressed_icons: true,
+            ..Default::default()
+        };
+        
+        let results = parse_each_ipa(&paths, &options);
+        let names: Vec<&str> = results.iter().map(|(_, info)| info.icon_name.as_deref().unwrap()).collect();
+        let saved_files: usize = std::fs::read_dir(dir.join("icons"))
+            .unwrap()
+            .map(|shard| std::fs::read_dir(shard.unwrap().path()).unwrap().count())
+            .sum();
+        let saved = dir.join("icons").join(names[0]).is_file();
+        
+        assert_eq!(names[0], names[2]);
+        assert_ne!(names[0], names[1]);
+        assert_eq!(names[0].len(), 2 + 1 + 30 + 4);
+        assert_eq!(names[0].as_bytes()[2], b'/');
+        assert!(saved);
+        assert_eq!(saved_files, 2);
     }
 }
@@ -0,0 +1,338 @@
+use crate::error::{IpaError, Result};
+use std::io::Read;
+
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+const CPU_TYPE_X86: u32 = 7;
+const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+const CPU_TYPE_ARM: u32 = 12;
+const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+const CPU_TYPE_ARM64_32: u32 = 0x0200_000c;
+const LC_VERSION_MIN_IPHONEOS: u32 = 0x25;
+const LC_BUILD_VERSION: u32 = 0x32;
+const LC_LOAD_DYLIB: u32 = 0xc;
+const LC_LOAD_WEAK_DYLIB: u32 = 0x8000_0018;
+const LC_ENCRYPTION_INFO: u32 = 0x21;
+const LC_ENCRYPTION_INFO_64: u32 = 0x2c;
+/// Load commands are tiny; anything bigger than this is not a real binary
+const MAX_LOAD_COMMANDS_SIZE: u32 = 16 * 1024 * 1024;
+
+/// What the executable's load commands say about its deployment target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MachOInfo {
+    /// Deployment target (`minos`), e.g. "15.0"
+    pub min_os: Option<String>,
+    /// SDK the binary was built against
+    pub sdk: Option<String>,
+    /// Install names of linked (including weakly linked) dylibs, in load order,
+    /// e.g. "/System/Library/Frameworks/SwiftUI.framework/SwiftUI"
+    pub linked_dylibs: Vec<String>,
+    /// CPU architecture of each slice, e.g. `["armv7", "arm64"]` for a fat binary
+    pub architectures: Vec<String>,
+    /// True when an LC_ENCRYPTION_INFO command has a non-zero `cryptid`, as in
+    /// FairPlay-protected App Store downloads
+    pub encrypted: bool,
+}
+
+impl MachOInfo {
+    /// True when the binary links the named system framework, e.g. "SwiftUI"
+    pub fn links_framework(&self, name: &str) -> bool {
+        let suffix = format!("/{0}.framework/{0}", name);
+        self.linked_dylibs.iter().any(|dylib| dylib.ends_with(&suffix))
+    }
+}
+
+/// Reads the header and load commands of a (possibly fat) Mach-O from a stream
+/// For fat binaries the arm64 slice is used, or the first slice without one.
+/// Nothing past the load commands is read.
+pub fn read_macho_info<R: Read>(mut reader: R) -> Result<MachOInfo> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    let mut consumed = 4u64;
+    let mut architectures = Vec::new();
+    
+    let magic = match u32::from_be_bytes(magic) {
+        fat @ (FAT_MAGIC | FAT_MAGIC_64) => {
+            let (offset, slices) = fat_slice_offset(&mut reader, fat == FAT_MAGIC_64, &mut consumed)?;
+            architectures = slices;
+            let skip = offset.checked_sub(consumed).ok_or_else(|| invalid("fat slice overlaps the header"))?;
+            std::io::copy(&mut (&mut reader).take(skip), &mut std::io::sink())?;
+            reader.read_exact(&mut magic)?;
+            u32::from_be_bytes(magic)
+        }
+        other => other,
+    };
+    
+    // Thin headers are little-endian on every Apple platform still in use
+    let is_64 = match u32::from_le_bytes(magic.to_be_bytes()) {
+        MH_MAGIC_64 => true,
+        MH_MAGIC => false,
+        _ => return Err(invalid("not a Mach-O executable")),
+    };
+    
+    // cputype, cpusubtype, filetype, ncmds, sizeofcmds, flags (+ reserved on 64-bit)
+    let mut header = [0u8; 28];
+    reader.read_exact(&mut header[..if is_64 { 28 } else { 24 }])?;
+    if architectures.is_empty() {
+        architectures.push(arch_name(le_u32(&header, 0), le_u32(&header, 4)));
+    }
+    let ncmds = le_u32(&header, 12);
+    let sizeofcmds = le_u32(&header, 16);
+    if sizeofcmds > MAX_LOAD_COMMANDS_SIZE {
+        return Err(invalid("load commands are implausibly large"));
+    }
+    
+    let mut commands = vec![0u8; sizeofcmds as usize];
+    reader.read_exact(&mut commands)?;
+    
+    let mut info = MachOInfo { architectures, ..Default::default() };
+    let mut pos = 0usize;
+    for _ in 0..ncmds {
+        if pos + 8 > commands.len() {
+            break;
+        }
+        let cmd = le_u32(&commands, pos);
+        let cmdsize = le_u32(&commands, pos + 4) as usize;
+        if cmdsize < 8 || pos + cmdsize > commands.len() {
+            break;
+        }
+        
+        match cmd {
+            // platform, minos, sdk
+            LC_BUILD_VERSION if cmdsize >= 20 => {
+                info.min_os = Some(format_version(le_u32(&commands, pos + 12)));
+                info.sdk = Some(format_version(le_u32(&commands, pos + 16)));
+            }
+            // Older binaries; LC_BUILD_VERSION wins when both are present
+            LC_VERSION_MIN_IPHONEOS if cmdsize >= 16 && info.min_os.is_none() => {
+                info.min_os = Some(format_version(le_u32(&commands, pos + 8)));
+                info.sdk = Some(format_version(le_u32(&commands, pos + 12)));
+            }
+            // dylib_command: the install name sits at the offset in its first field
+            LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB if cmdsize >= 24 => {
+                let name_offset = le_u32(&commands, pos + 8) as usize;
+                if let Some(name) = commands[pos..pos + cmdsize].get(name_offset..) {
+                    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                    info.linked_dylibs.push(String::from_utf8_lossy(&name[..end]).into_owned());
+                }
+            }
+            // cryptoff, cryptsize, cryptid (plus padding on 64-bit)
+            LC_ENCRYPTION_INFO | LC_ENCRYPTION_INFO_64 if cmdsize >= 20 => {
+                info.encrypted |= le_u32(&commands, pos + 16) != 0;
+            }
+            _ => {}
+        }
+        pos += cmdsize;
+    }
+    
+    Ok(info)
+}
+
+/// Reads the fat arch table and returns the file offset of the slice to use,
+/// along with the architecture name of every slice
+fn fat_slice_offset<R: Read>(reader: &mut R, is_64: bool, consumed: &mut u64) -> Result<(u64, Vec<String>)> {
+    let mut count = [0u8; 4];
+    reader.read_exact(&mut count)?;
+    let count = u32::from_be_bytes(count);
+    if count == 0 || count > 64 {
+        return Err(invalid("bad fat architecture count"));
+    }
+    
+    // fat_arch is 20 bytes (32-bit offsets); fat_arch_64 is 32 bytes
+    let entry_size = if is_64 { 32 } else { 20 };
+    let mut table = vec![0u8; count as usize * entry_size];
+    reader.read_exact(&mut table)?;
+    *consumed += 4 + table.len() as u64;
+    
+    let slices: Vec<(u32, u32, u64)> = table
+        .chunks_exact(entry_size)
+        .map(|arch| {
+            let cputype = u32::from_be_bytes([arch[0], arch[1], arch[2], arch[3]]);
+            let cpusubtype = u32::from_be_bytes([arch[4], arch[5], arch[6], arch[7]]);
+            let offset = if is_64 {
+                u64::from_be_bytes(arch[8..16].try_into().unwrap_or_default())
+            } else {
+                u64::from(u32::from_be_bytes([arch[8], arch[9], arch[10], arch[11]]))
+            };
+            (cputype, cpusubtype, offset)
+        })
+        .collect();
+    
+    let offset = slices
+        .iter()
+        .find(|(cputype, _, _)| *cputype == CPU_TYPE_ARM64)
+        .unwrap_or(&slices[0])
+        .2;
+    let names = slices.iter().map(|&(cputype, cpusubtype, _)| arch_name(cputype, cpusubtype)).collect();
+    Ok((offset, names))
+}
+
+/// Conventional name (as printed by `lipo -info`) for a cputype/cpusubtype pair
+pub fn arch_name(cputype: u32, cpusubtype: u32) -> String {
+    // The high byte of the subtype holds capability flags (e.g. pointer authentication ABI)
+    let name = match (cputype, cpusubtype & 0x00ff_ffff) {
+        (CPU_TYPE_ARM64, 2) => "arm64e",
+        (CPU_TYPE_ARM64, _) => "arm64",
+        (CPU_TYPE_ARM64_32, _) => "arm64_32",
+        (CPU_TYPE_ARM, 6) => "armv6",
+        (CPU_TYPE_ARM, 9) => "armv7",
+        (CPU_TYPE_ARM, 11) => "armv7s",
+        (CPU_TYPE_ARM, 12) => "armv7k",
+        (CPU_TYPE_ARM, _) => "arm",
+        (CPU_TYPE_X86_64, _) => "x86_64",
+        (CPU_TYPE_X86, _) => "i386",
+        _ => return format!("cputype {:#x}", cputype),
+    };
+    name.to_string()
+}
+
+/// Formats a packed `xxxx.yy.zz` version, dropping a zero patch component
+pub fn format_version(packed: u32) -> String {
+    let (major, minor, patch) = (packed >> 16, (packed >> 8) & 0xff, packed & 0xff);
+    
+    if patch == 0 {
+        format!("{}.{}", major, minor)
+    } else {
+        format!("{}.{}.{}", major, minor, patch)
+    }
+}
+
+/// Compares dotted versions numerically, treating missing components as 0
+/// ("15" == "15.0" == "15.0.0"); None if either isn't a version
+pub fn versions_match(a: &str, b: &str) -> Option<bool> {
+    let parse = |v: &str| -> Option<Vec<u32>> {
+        let mut parts = v.trim().split('.').map(|p| p.parse().ok()).collect::<Option<Vec<u32>>>()?;
+        while parts.len() > 1 && parts.last() == Some(&0) {
+            parts.pop();
+        }
+        Some(parts)
+    };
+    
+    Some(parse(a)? == parse(b)?)
+}
+
+#[inline]
+fn le_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+fn invalid(msg: &str) -> IpaError {
+    IpaError::MachO(msg.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A minimal 64-bit arm64 executable whose only load command is LC_BUILD_VERSION
+    pub(crate) fn thin_macho(minos: u32, sdk: u32) -> Vec<u8> {
+        thin_macho_linking(minos, sdk, &[])
+    }
+    
+    /// Like `thin_macho`, followed by an LC_LOAD_DYLIB for each install name
+    pub(crate) fn thin_macho_linking(minos: u32, sdk: u32, dylibs: &[&str]) -> Vec<u8> {
+        let mut command = Vec::new();
+        for word in [LC_BUILD_VERSION, 24, 2, minos, sdk, 0] {
+            command.extend_from_slice(&word.to_le_bytes());
+        }
+        for dylib in dylibs {
+            // name offset, timestamp, current and compatibility version, then the padded name
+            let size = (24 + dylib.len() + 1).next_multiple_of(8);
+            for word in [LC_LOAD_DYLIB, size as u32, 24, 0, 0, 0] {
+                command.extend_from_slice(&word.to_le_bytes());
+            }
+            command.extend_from_slice(dylib.as_bytes());
+            command.resize(command.len() + size - 24 - dylib.len(), 0);
+        }
+        
+        let mut data = Vec::new();
+        for word in [MH_MAGIC_64, CPU_TYPE_ARM64, 0, 2, 1 + dylibs.len() as u32, command.len() as u32, 0, 0] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(&command);
+        data
+    }
+
+    #[test]
+    fn test_read_thin_and_fat_macho() {
+        let thin = thin_macho(0x000f_0000, 0x0011_0200);
+        let mut expected = MachOInfo {
+            min_os: Some("15.0".to_string()),
+            sdk: Some("17.2".to_string()),
+            architectures: vec!["arm64".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(read_macho_info(&thin[..]).unwrap(), expected);
+        
+        // One armv7 slice we don't read, then the arm64 slice at 0x1000
+        let mut fat = Vec::new();
+        for word in [FAT_MAGIC, 2, CPU_TYPE_ARM, 9, 0x40, 0, 0, CPU_TYPE_ARM64, 0, 0x1000, thin.len() as u32, 0] {
+            fat.extend_from_slice(&word.to_be_bytes());
+        }
+        fat.resize(0x1000, 0);
+        fat.extend_from_slice(&thin);
+        expected.architectures = vec!["armv7".to_string(), "arm64".to_string()];
+        assert_eq!(read_macho_info(&fat[..]).unwrap(), expected);
+        
+        assert!(read_macho_info(&b"#!/bin/sh\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_linked_dylibs() {
+        let data = thin_macho_linking(
+            0x000f_0000,
+            0x0011_0200,
+            &["/System/Library/Frameworks/SwiftUI.framework/SwiftUI", "/usr/lib/libobjc.A.dylib"],
+        );
+        let info = read_macho_info(&data[..]).unwrap();
+        
+        assert_eq!(info.linked_dylibs.len(), 2);
+        assert_eq!(info.linked_dylibs[1], "/usr/lib/libobjc.A.dylib");
+        assert!(info.links_framework("SwiftUI"));
+        assert!(!info.links_framework("UIKit"));
+        assert_eq!(info.min_os.as_deref(), Some("15.0"));
+    }
+
+    #[test]
+    fn test_encryption_info() {
+        let mut data = thin_macho(0x000f_0000, 0x0011_0200);
+        assert!(!read_macho_info(&data[..]).unwrap().encrypted);
+        
+        // Append an LC_ENCRYPTION_INFO_64 and bump ncmds / sizeofcmds to match
+        for cryptid in [0, 1] {
+            let mut encrypted = data.clone();
+            for word in [LC_ENCRYPTION_INFO_64, 24, 0x4000, 0x8000, cryptid, 0] {
+                encrypted.extend_from_slice(&word.to_le_bytes());
+            }
+            encrypted[16..20].copy_from_slice(&2u32.to_le_bytes());
+            encrypted[20..24].copy_from_slice(&48u32.to_le_bytes());
+            assert_eq!(read_macho_info(&encrypted[..]).unwrap().encrypted, cryptid == 1);
+        }
+        
+        // A truncated command is ignored rather than misread
+        data.extend_from_slice(&LC_ENCRYPTION_INFO.to_le_bytes());
+        data.extend_from_slice(&12u32.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data[16..20].copy_from_slice(&2u32.to_le_bytes());
+        data[20..24].copy_from_slice(&36u32.to_le_bytes());
+        assert!(!read_macho_info(&data[..]).unwrap().encrypted);
+    }
+
+    #[test]
+    fn test_arch_name() {
+        assert_eq!(arch_name(CPU_TYPE_ARM64, 0), "arm64");
+        assert_eq!(arch_name(CPU_TYPE_ARM64, 0x8000_0002), "arm64e");
+        assert_eq!(arch_name(CPU_TYPE_ARM, 11), "armv7s");
+        assert_eq!(arch_name(CPU_TYPE_X86_64, 3), "x86_64");
+        assert_eq!(arch_name(0x12, 0), "cputype 0x12");
+    }
+
+    #[test]
+    fn test_versions_match() {
+        assert_eq!(versions_match("15", "15.0.0"), Some(true));
+        assert_eq!(versions_match("15.0", "15.1"), Some(false));
+        assert_eq!(versions_match("15.x", "15.0"), None);
+    }
+}
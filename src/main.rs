@@ -1,12 +1,21 @@
 use clap::{Parser, ValueEnum};
-use ipa_parser::{find_ipa_files, parse_ipa, parse_multiple_ipas, ParseOptions};
-use std::path::PathBuf;
+use ipa_parser::error::{ErrorKind, IpaError};
+use ipa_parser::hashing::{HashAlgo, HashEncoding};
+use ipa_parser::icon_extractor::{IconFormat, IconNaming};
+use ipa_parser::manifest::parse_manifest;
+use ipa_parser::png_normalizer::DecodeLimits;
+use ipa_parser::spill::{for_each_spilled, parse_multiple_ipas_spilled};
+use ipa_parser::{
+    find_ipa_files, find_ipa_files_recursive, parse_app_dir, parse_each_ipa, parse_ipa, parse_multiple_ipas,
+    skip_processed, split_output_path, summarize, IpaInfo, KeyStrategy, ParseOptions,
+};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "ipa-parser")]
 #[command(about = "Blazing-fast IPA file parser for extracting metadata and icons", long_about = None)]
 struct Cli {
-    /// IPA file to parse (omit for multiple mode)
+    /// IPA file, or unpacked .app directory, to parse (omit for multiple mode)
     #[arg(short, long, value_name = "FILE")]
     file: Option<PathBuf>,
 
@@ -14,10 +23,73 @@ struct Cli {
     #[arg(short, long)]
     multiple: bool,
 
+    /// Manifest (JSON or TOML) listing IPAs with optional labels and expected hashes
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["file", "multiple"])]
+    manifest: Option<PathBuf>,
+
     /// Directory containing IPA files (for multiple mode)
     #[arg(short, long, value_name = "DIR", default_value = ".")]
     directory: PathBuf,
 
+    /// Also look for IPA files in subdirectories of --directory (multiple mode)
+    #[arg(short, long, requires = "multiple")]
+    recursive: bool,
+
+    /// Write one JSON file per IPA into DIR, mirroring each IPA's path under
+    /// --directory with the extension changed to .json (multiple mode)
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "multiple",
+        conflicts_with_all = ["outfile", "spill_dir", "summary", "icon_checksums", "group_by_vendor", "key_by"]
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// Skip IPAs whose --key-by key is already in this earlier keyed JSON output,
+    /// and merge its entries into the new output (a missing file counts as empty).
+    /// Only plain JSON output can be read back, so no --summary or other --format
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires_all = ["multiple", "key_by"],
+        conflicts_with_all = ["group_by_vendor", "spill_dir", "output_dir", "summary"]
+    )]
+    resume: Option<PathBuf>,
+
+    /// Parse with at most N threads (default: one per CPU, or RAYON_NUM_THREADS)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Only parse N of the discovered IPA files (multiple mode)
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// How --limit picks files
+    #[arg(long, value_enum, default_value = "first", requires = "limit")]
+    sample: SampleMode,
+
+    /// Seed for --sample random (printed to stderr when omitted)
+    #[arg(long, value_name = "SEED", requires = "limit")]
+    seed: Option<u64>,
+
+    /// Spill results to an NDJSON file in DIR and stream the output from it,
+    /// so memory stays flat on huge batches (multiple mode)
+    #[arg(
+        long,
+        value_name = "DIR",
+        requires = "multiple",
+        conflicts_with_all = ["summary", "icon_checksums", "group_by_vendor"]
+    )]
+    spill_dir: Option<PathBuf>,
+
+    /// Order the output array by this field (not applied to --key-by/--group-by-vendor maps)
+    #[arg(long, value_enum, value_name = "FIELD", conflicts_with = "spill_dir")]
+    sort_by: Option<SortField>,
+
+    /// With --sort-by, put the largest / last values first
+    #[arg(long, requires = "sort_by")]
+    sort_desc: bool,
+
     /// Output JSON file (prints to stdout if not specified)
     #[arg(short, long, value_name = "FILE")]
     outfile: Option<PathBuf>,
@@ -26,14 +98,159 @@ struct Cli {
     #[arg(short, long)]
     pretty: bool,
 
-    /// Sort JSON keys
+    /// Output format; csv writes one row per app with a fixed set of columns,
+    /// toml puts a batch array under [[Apps]]; --summary needs json, yaml or toml
+    #[arg(long, value_enum, default_value = "json", conflicts_with = "output_dir")]
+    format: OutputFormat,
+
+    /// Don't end the output with a newline
+    #[arg(long)]
+    no_trailing_newline: bool,
+
+    /// Only print errors: no progress messages or per-file warnings
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print debug messages (RUST_LOG overrides both this and --quiet)
     #[arg(short, long)]
+    verbose: bool,
+
+    /// Sort JSON object keys alphabetically (by default fields keep their documented order)
+    #[arg(short, long, conflicts_with = "spill_dir")]
     sort: bool,
 
     /// Disable icon extraction
     #[arg(long)]
     no_icons: bool,
 
+    /// Save fully-opaque icons as RGB instead of RGBA
+    #[arg(long)]
+    flatten_opaque_icons: bool,
+
+    /// Downscale saved icons to fit within an N x N box (smaller icons are kept as-is)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    icon_resize: Option<u32>,
+
+    /// File format for saved icons (JPEG puts transparent areas on white)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "png")]
+    icon_format: IconFormat,
+
+    /// Name icons by archive hash, bundle id or a running number (only hash reads the whole IPA)
+    #[arg(long, value_enum, value_name = "SCHEME", default_value = "hash")]
+    icon_naming: IconNaming,
+
+    /// Compute a perceptual hash (dHash) of each extracted icon
+    #[arg(long)]
+    icon_phash: bool,
+
+    /// Flag icons that look like placeholders (solid color or very few colors)
+    #[arg(long)]
+    detect_placeholder_icons: bool,
+
+    /// Report whether the icon has any translucent pixels
+    #[arg(long)]
+    icon_transparency: bool,
+
+    /// Wrap the output as {"Apps": ..., "Summary": ...} with capability counts across all apps
+    #[arg(long)]
+    summary: bool,
+
+    /// Read the executable's deployment target and check it against MinimumOSVersion
+    #[arg(long)]
+    macho: bool,
+
+    /// List the main executable's CPU architectures (Architectures)
+    #[arg(long)]
+    architectures: bool,
+
+    /// Check whether the executable is FairPlay-encrypted (IsEncrypted)
+    #[arg(long)]
+    encryption: bool,
+
+    /// Guess whether the app uses the SwiftUI App lifecycle (LikelySwiftUI; a heuristic)
+    #[arg(long)]
+    detect_swiftui: bool,
+
+    /// Read team, devices and expiry from embedded.mobileprovision (ProvisioningProfile)
+    #[arg(long)]
+    provisioning_profile: bool,
+
+    /// Also parse this plist (relative to the app bundle, then the archive) into ExtraPlists; repeatable
+    #[arg(long, value_name = "PATH")]
+    extra_plist: Vec<String>,
+
+    /// Fail when the plist declares icons but none of them exist in the archive
+    #[arg(long)]
+    require_declared_icons: bool,
+
+    /// With --require-declared-icons, add a warning instead of failing
+    #[arg(long, requires = "require_declared_icons")]
+    warn_missing_icons: bool,
+
+    /// Fail on a malformed CFBundleIdentifier instead of reporting BundleIdValid: false
+    #[arg(long)]
+    strict_bundle_id: bool,
+
+    /// Report whether all entries share one modification time (reproducible builds)
+    #[arg(long)]
+    entry_timestamps: bool,
+
+    /// Flag IPAs that look sideload-repackaged (injected tweak libraries, odd top-level entries)
+    #[arg(long)]
+    detect_repackaging: bool,
+
+    /// List the bundled dynamic frameworks (Frameworks/*.framework)
+    #[arg(long)]
+    frameworks: bool,
+
+    /// Write icons from a single IO thread while parsing stays parallel (for slow disks)
+    #[arg(long)]
+    serialize_icon_writes: bool,
+
+    /// Also accept Payload/<dir>/Info.plist bundles without the .app suffix
+    #[arg(long)]
+    lenient_layout: bool,
+
+    /// How the archive hash is written in FileHash, icon file names and manifest expected_hash
+    #[arg(long, value_enum, default_value = "lower-hex")]
+    hash_encoding: HashEncoding,
+
+    /// Digest used for FileHash, hash-named icons and manifest expected_hash
+    #[arg(long, value_enum, default_value = "md5")]
+    hash_algorithm: HashAlgo,
+
+    /// Add FileSHA256, a SHA-256 of each whole IPA, for deduplication
+    #[arg(long)]
+    file_sha256: bool,
+
+    /// Report byte-identical IPAs once, listing the other copies under Aliases
+    #[arg(long, conflicts_with_all = ["spill_dir", "output_dir"])]
+    dedup: bool,
+
+    /// Name saved icons <hash>_<original name>.png instead of <hash>.png
+    #[arg(long)]
+    icon_keep_original_name: bool,
+
+    /// Name icons by content hash in sharded dirs (ab/cdef….png), saving each unique icon once
+    #[arg(long)]
+    content_addressed_icons: bool,
+
+    /// Refuse to decode icons wider or taller than PX (guards against decompression bombs)
+    #[arg(long, value_name = "PX", default_value_t = 4096)]
+    max_icon_dimension: u32,
+
+    /// Self-check: recompute every chunk CRC of each normalized icon
+    #[arg(long)]
+    verify_png_crcs: bool,
+
+    /// Also save the main, Watch and App Clip icons side by side as <hash>_strip.png
+    #[arg(long)]
+    icon_strip: bool,
+
+    /// Write an md5sum-style checksums file for the extracted icons, using --hash-algorithm
+    #[arg(long, value_name = "FILE")]
+    icon_checksums: Option<PathBuf>,
+
     /// Directory to save extracted icons
     #[arg(long, value_name = "DIR", default_value = "icons")]
     icon_dir: PathBuf,
@@ -41,80 +258,755 @@ struct Cli {
     /// Key strategy for multiple files
     #[arg(long, value_enum)]
     key_by: Option<KeyStrategy>,
+
+    /// Group multiple-file results by bundle id vendor prefix (number of components, default 2)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "2")]
+    group_by_vendor: Option<usize>,
+
+    /// Report ZIP entry names that appear more than once
+    #[arg(long)]
+    detect_duplicates: bool,
+
+    /// List the preference keys declared in Settings.bundle
+    #[arg(long)]
+    settings_keys: bool,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum KeyStrategy {
-    /// Use filename as key
-    Filename,
-    /// Use bundle ID as key
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum SortField {
+    /// AppSize in bytes
+    Size,
+    /// AppName, case-insensitively
+    Name,
+    /// AppVersion, comparing dotted components numerically
+    Version,
+    /// AppBundleIdentifier
     Bundleid,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// The JSON shape selected by --key-by / --group-by-vendor
+    Json,
+    /// Header plus one row per app, however the JSON would have been keyed
+    Csv,
+    /// The JSON value as YAML (always block style)
+    Yaml,
+    /// The JSON value as TOML, with a top-level array wrapped as `[[Apps]]`
+    Toml,
+}
+
+/// Columns written by `--format csv`, in order
+const CSV_COLUMNS: [&str; 7] =
+    ["AppName", "AppVersion", "AppBundleIdentifier", "AppSize", "IconName", "FileName", "Timestamp"];
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum SampleMode {
+    /// First N files in sorted path order
+    First,
+    /// N files chosen at random
+    Random,
+}
+
+/// Process exit codes, so shell wrappers can branch on the failure type
+mod exit_code {
+    /// Unexpected or internal error (including bad arguments)
+    pub const INTERNAL: i32 = 1;
+    /// The file is not a readable ZIP/IPA archive
+    pub const NOT_AN_IPA: i32 = 2;
+    /// Info.plist is missing, unreadable, or lacks a required field
+    pub const MISSING_PLIST: i32 = 3;
+    /// Reading or writing a file failed
+    pub const IO: i32 = 4;
+    /// Archive entries are encrypted
+    pub const ENCRYPTED: i32 = 5;
+    /// The icon couldn't be extracted, or declared icons are missing
+    pub const ICON: i32 = 6;
+    /// An IPA's digest doesn't match its manifest `expected_hash`
+    pub const HASH_MISMATCH: i32 = 7;
+    /// The manifest couldn't be read
+    pub const MANIFEST: i32 = 8;
+    /// embedded.mobileprovision or the executable is malformed
+    pub const BUNDLE_CONTENT: i32 = 9;
+}
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        std::process::exit(exit_code_for(&e));
     }
 }
 
+/// Prints library and CLI messages on stderr: warnings as `Warning: ...`,
+/// progress messages as they are
+fn init_logger(quiet: bool, verbose: bool) {
+    use std::io::Write;
+    
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (_, true) => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Info,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| match record.level() {
+            log::Level::Error => writeln!(buf, "Error: {}", record.args()),
+            log::Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
+}
+
+/// Maps an error to one of the documented exit codes
+fn exit_code_for(err: &anyhow::Error) -> i32 {
+    if let Some(err) = err.downcast_ref::<IpaError>() {
+        return match err.kind() {
+            ErrorKind::Internal => exit_code::INTERNAL,
+            ErrorKind::NotAnIpa => exit_code::NOT_AN_IPA,
+            ErrorKind::MissingPlist => exit_code::MISSING_PLIST,
+            ErrorKind::Io => exit_code::IO,
+            ErrorKind::Encrypted => exit_code::ENCRYPTED,
+            ErrorKind::Icon => exit_code::ICON,
+            ErrorKind::HashMismatch => exit_code::HASH_MISMATCH,
+            ErrorKind::Manifest => exit_code::MANIFEST,
+            ErrorKind::BundleContent => exit_code::BUNDLE_CONTENT,
+        };
+    }
+    
+    if err.downcast_ref::<std::io::Error>().is_some() {
+        return exit_code::IO;
+    }
+    
+    exit_code::INTERNAL
+}
+
 fn run() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    // clap exits with 2 on usage errors, which would collide with NOT_AN_IPA
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { exit_code::INTERNAL } else { 0 });
+    });
+    init_logger(cli.quiet, cli.verbose);
+    if cli.summary && cli.format == OutputFormat::Csv {
+        anyhow::bail!("--summary can't be written as CSV rows; use --format json, yaml or toml");
+    }
+    if let Some(threads) = cli.threads {
+        // Every parallel batch path (plain, spilled, split, manifest) runs on the global pool
+        rayon::ThreadPoolBuilder::new().num_threads(threads.into()).build_global()?;
+    }
 
     // Build parse options
     let options = ParseOptions {
         extract_icons: !cli.no_icons,
         icon_output_dir: cli.icon_dir,
-        key_by: cli.key_by.map(|k| match k {
-            KeyStrategy::Filename => "filename".to_string(),
-            KeyStrategy::Bundleid => "bundleid".to_string(),
-        }),
+        key_by: cli.key_by,
+        group_by_vendor: cli.group_by_vendor,
+        detect_duplicate_entries: cli.detect_duplicates,
+        parse_settings_bundle: cli.settings_keys,
+        flatten_opaque_icons: cli.flatten_opaque_icons,
+        icon_resize: cli.icon_resize,
+        icon_format: cli.icon_format,
+        icon_naming: cli.icon_naming,
+        compute_icon_phash: cli.icon_phash,
+        compute_icon_checksum: cli.icon_checksums.is_some(),
+        detect_placeholder_icons: cli.detect_placeholder_icons,
+        detect_icon_transparency: cli.icon_transparency,
+        parse_macho: cli.macho,
+        detect_architectures: cli.architectures,
+        detect_encryption: cli.encryption,
+        detect_swiftui_lifecycle: cli.detect_swiftui,
+        parse_provisioning_profile: cli.provisioning_profile,
+        extra_plists: cli.extra_plist,
+        require_declared_icons_present: cli.require_declared_icons,
+        declared_icons_warn_only: cli.warn_missing_icons,
+        strict_bundle_id: cli.strict_bundle_id,
+        check_entry_timestamps: cli.entry_timestamps,
+        detect_repackaging: cli.detect_repackaging,
+        list_frameworks: cli.frameworks,
+        lenient_layout: cli.lenient_layout,
+        serialize_icon_writes: cli.serialize_icon_writes,
+        hash_encoding: cli.hash_encoding,
+        hash_algorithm: cli.hash_algorithm,
+        include_file_hash: cli.file_sha256,
+        dedup_by_hash: cli.dedup,
+        icon_keep_original_name: cli.icon_keep_original_name,
+        content_addressed_icons: cli.content_addressed_icons,
+        icon_decode_limits: DecodeLimits {
+            max_width: cli.max_icon_dimension,
+            max_height: cli.max_icon_dimension,
+            ..Default::default()
+        },
+        verify_icon_crcs: cli.verify_png_crcs,
+        compose_icon_strip: cli.icon_strip,
+        ..Default::default()
     };
 
-    let json_value = if cli.multiple {
+    let json_value = if let Some(manifest) = &cli.manifest {
+        // Manifest mode
+        parse_manifest(manifest, &options)?
+    } else if cli.multiple {
         // Multiple file mode
-        let ipa_files = find_ipa_files(&cli.directory)?;
+        let mut ipa_files = if cli.recursive {
+            find_ipa_files_recursive(&cli.directory)?
+        } else {
+            find_ipa_files(&cli.directory)?
+        };
         
         if ipa_files.is_empty() {
             eprintln!("No IPA files found in {}", cli.directory.display());
             std::process::exit(1);
         }
         
-        eprintln!("Found {} IPA file(s), processing...", ipa_files.len());
-        parse_multiple_ipas(&ipa_files, &options)?
+        if cli.resume.is_some() && cli.format != OutputFormat::Json {
+            anyhow::bail!("--resume only reads and writes JSON output");
+        }
+        let previous = match &cli.resume {
+            Some(path) => read_previous_output(path)?,
+            None => serde_json::Map::new(),
+        };
+        if !previous.is_empty() {
+            let processed = previous.keys().cloned().collect();
+            let found = ipa_files.len();
+            ipa_files = skip_processed(&ipa_files, &processed, &options);
+            log::info!("Skipping {} already-processed IPA file(s)", found - ipa_files.len());
+        }
+        
+        if let Some(limit) = cli.limit {
+            // Discovery returns paths sorted, so sampling is reproducible regardless of directory order
+            if cli.sample == SampleMode::Random {
+                let seed = cli.seed.unwrap_or_else(|| {
+                    let seed = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or_default();
+                    log::info!("Sampling with seed {}", seed);
+                    seed
+                });
+                shuffle(&mut ipa_files, seed);
+            }
+            ipa_files.truncate(limit);
+        }
+        
+        log::info!("Found {} IPA file(s), processing...", ipa_files.len());
+        if let Some(spill_dir) = &cli.spill_dir {
+            let newline = !cli.no_trailing_newline;
+            let format = JsonFormat { pretty: cli.pretty, sort: false, newline };
+            return write_spilled(&ipa_files, &options, spill_dir, cli.outfile.as_deref(), cli.format, format);
+        }
+        if let Some(output_dir) = &cli.output_dir {
+            let newline = !cli.no_trailing_newline;
+            let format = JsonFormat { pretty: cli.pretty, sort: cli.sort, newline };
+            return write_split(&ipa_files, &options, &cli.directory, output_dir, format);
+        }
+        let json_value = parse_multiple_ipas(&ipa_files, &options)?;
+        merge_keyed(previous, json_value)
     } else {
         // Single file mode
         let file = cli.file.ok_or_else(|| {
-            anyhow::anyhow!("Either --file or --multiple must be specified")
+            anyhow::anyhow!("One of --file, --multiple or --manifest must be specified")
         })?;
         
         if !file.exists() {
-            anyhow::bail!("File not found: {}", file.display());
+            let message = format!("File not found: {}", file.display());
+            return Err(IpaError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, message)).into());
         }
         
-        let info = parse_ipa(&file, &options)?;
+        let info = if file.is_dir() { parse_app_dir(&file, &options)? } else { parse_ipa(&file, &options)? };
         serde_json::to_value(info)?
     };
 
-    // Format output
-    let output = if cli.pretty && cli.sort {
-        serde_json::to_string_pretty(&json_value)?
-    } else if cli.pretty {
-        serde_json::to_string_pretty(&json_value)?
-    } else if cli.sort {
-        // For sorted output without pretty print, we need to manually sort
-        serde_json::to_string(&json_value)?
+    let mut json_value = json_value;
+    if let (Some(field), serde_json::Value::Array(apps)) = (cli.sort_by, &mut json_value) {
+        sort_apps(apps, field, cli.sort_desc);
+    }
+
+    if let Some(path) = &cli.icon_checksums {
+        std::fs::write(path, icon_checksums(&json_value))?;
+        log::info!("Icon checksums written to {}", path.display());
+    }
+
+    let output = if cli.format == OutputFormat::Csv {
+        let mut apps = Vec::new();
+        collect_apps(&json_value, &mut apps);
+        apps_to_csv(&apps)
     } else {
-        serde_json::to_string(&json_value)?
+        let mut json_value = if cli.summary { with_summary(json_value) } else { json_value };
+        if cli.sort {
+            json_value.sort_all_objects();
+        }
+        render(&json_value, cli.format, cli.pretty)?
     };
 
-    // Write output
+    // Write output (stdout and --outfile get identical bytes)
+    let output = with_trailing_newline(output, !cli.no_trailing_newline);
     if let Some(outfile) = cli.outfile {
         std::fs::write(&outfile, output)?;
-        eprintln!("Output written to {}", outfile.display());
+        log::info!("Output written to {}", outfile.display());
     } else {
-        println!("{}", output);
+        use std::io::Write;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
     }
 
     Ok(())
 }
+
+/// The keyed object a `--resume` file holds; a file that doesn't exist yet is empty
+fn read_previous_output(path: &Path) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(serde_json::Map::new()),
+        Err(e) => return Err(e.into()),
+    };
+    match serde_json::from_slice(&data) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        Ok(_) => anyhow::bail!("{} is not keyed output (expected a JSON object)", path.display()),
+        Err(e) => anyhow::bail!("Could not read {}: {}", path.display(), e),
+    }
+}
+
+/// Adds the new keyed results to the earlier ones, new entries winning, in key order
+fn merge_keyed(
+    previous: serde_json::Map<String, serde_json::Value>,
+    value: serde_json::Value,
+) -> serde_json::Value {
+    let serde_json::Value::Object(new) = value else {
+        return value;
+    };
+    if previous.is_empty() {
+        return serde_json::Value::Object(new);
+    }
+    let merged: std::collections::BTreeMap<_, _> = previous.into_iter().chain(new).collect();
+    serde_json::Value::Object(merged.into_iter().collect())
+}
+
+/// Wraps the output as `{"Apps": ..., "Summary": ...}` for --summary, in any
+/// structured `--format`
+fn with_summary(json_value: serde_json::Value) -> serde_json::Value {
+    let mut apps = Vec::new();
+    collect_apps(&json_value, &mut apps);
+    serde_json::json!({ "Apps": json_value, "Summary": summarize(&apps) })
+}
+
+/// Serializes the output value in a structured `--format` (CSV goes through
+/// `apps_to_csv` instead). --pretty only changes JSON and TOML, since YAML
+/// output is always block style
+fn render(value: &serde_json::Value, format: OutputFormat, pretty: bool) -> anyhow::Result<String> {
+    Ok(match format {
+        OutputFormat::Json | OutputFormat::Csv if pretty => serde_json::to_string_pretty(value)?,
+        OutputFormat::Json | OutputFormat::Csv => serde_json::to_string(value)?,
+        OutputFormat::Yaml => serde_yaml::to_string(value)?,
+        OutputFormat::Toml => to_toml(value, pretty)?,
+    })
+}
+
+/// TOML needs a table at the top, so a batch array becomes `[[Apps]]` tables
+/// Nulls, which TOML has no way to write, are left out
+fn to_toml(value: &serde_json::Value, pretty: bool) -> anyhow::Result<String> {
+    let mut value = match value {
+        serde_json::Value::Array(_) => serde_json::json!({ "Apps": value }),
+        _ => value.clone(),
+    };
+    drop_nulls(&mut value);
+    
+    Ok(if pretty { toml::to_string_pretty(&value)? } else { toml::to_string(&value)? })
+}
+
+fn drop_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(drop_nulls);
+        }
+        serde_json::Value::Array(items) => {
+            items.retain(|v| !v.is_null());
+            items.iter_mut().for_each(drop_nulls);
+        }
+        _ => {}
+    }
+}
+
+/// Streams a spilled batch straight to --outfile or stdout, as JSON or CSV
+/// (YAML and TOML documents can't be written incrementally)
+fn write_spilled(
+    ipa_files: &[PathBuf],
+    options: &ParseOptions,
+    spill_dir: &Path,
+    outfile: Option<&Path>,
+    output_format: OutputFormat,
+    format: JsonFormat,
+) -> anyhow::Result<()> {
+    use std::io::Write;
+    
+    if matches!(output_format, OutputFormat::Yaml | OutputFormat::Toml) {
+        anyhow::bail!("--spill-dir only writes JSON or CSV");
+    }
+    let mut out: Box<dyn Write> = match outfile {
+        Some(outfile) => Box::new(std::io::BufWriter::new(std::fs::File::create(outfile)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    if output_format == OutputFormat::Csv {
+        writeln!(out, "{}", CSV_COLUMNS.join(","))?;
+        for_each_spilled(ipa_files, options, spill_dir, |_, app| Ok(writeln!(out, "{}", csv_row(&app))?))?;
+    } else {
+        parse_multiple_ipas_spilled(ipa_files, options, spill_dir, format.pretty, &mut out)?;
+        if format.newline {
+            out.write_all(b"\n")?;
+        }
+    }
+    out.flush()?;
+    
+    if let Some(outfile) = outfile {
+        log::info!("Output written to {}", outfile.display());
+    }
+    Ok(())
+}
+
+/// --pretty, --sort and --no-trailing-newline, for writers that format JSON themselves
+#[derive(Clone, Copy)]
+struct JsonFormat {
+    pretty: bool,
+    sort: bool,
+    newline: bool,
+}
+
+/// Writes each IPA's JSON to its mirrored path under the output directory
+fn write_split(
+    ipa_files: &[PathBuf],
+    options: &ParseOptions,
+    input_root: &Path,
+    output_dir: &Path,
+    format: JsonFormat,
+) -> anyhow::Result<()> {
+    let results = parse_each_ipa(ipa_files, options);
+    
+    for (path, info) in &results {
+        let mut value = serde_json::to_value(info)?;
+        if format.sort {
+            value.sort_all_objects();
+        }
+        let output = if format.pretty {
+            serde_json::to_string_pretty(&value)?
+        } else {
+            serde_json::to_string(&value)?
+        };
+        let target = split_output_path(output_dir, input_root, path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, with_trailing_newline(output, format.newline))?;
+    }
+    
+    log::info!("Wrote {} file(s) to {}", results.len(), output_dir.display());
+    Ok(())
+}
+
+/// Builds `<hash>  <icon file>` lines (md5sum format, sorted by file name)
+/// from every app object in the output, so `md5sum -c` (or `sha1sum -c`,
+/// `sha256sum -c` for the other hex algorithms) works in the icon dir
+fn icon_checksums(value: &serde_json::Value) -> String {
+    let mut entries = std::collections::BTreeMap::new();
+    collect_icon_checksums(value, &mut entries);
+    
+    entries
+        .into_iter()
+        .map(|(name, hash)| format!("{}  {}\n", hash, name))
+        .collect()
+}
+
+fn collect_icon_checksums(value: &serde_json::Value, entries: &mut std::collections::BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if let (Some(name), Some(hash)) = (
+                map.get("IconName").and_then(|v| v.as_str()),
+                map.get("IconChecksum").and_then(|v| v.as_str()),
+            ) {
+                entries.insert(name.to_string(), hash.to_string());
+            } else {
+                // Keyed or grouped output: recurse into the values
+                map.values().for_each(|v| collect_icon_checksums(v, entries));
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_icon_checksums(v, entries)),
+        _ => {}
+    }
+}
+
+/// Collects every app record from the output, however it is keyed or grouped
+fn collect_apps(value: &serde_json::Value, apps: &mut Vec<IpaInfo>) {
+    match value {
+        serde_json::Value::Object(map) if map.contains_key("AppBundleIdentifier") => {
+            if let Ok(info) = serde_json::from_value(value.clone()) {
+                apps.push(info);
+            }
+        }
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_apps(v, apps)),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_apps(v, apps)),
+        _ => {}
+    }
+}
+
+/// Renders apps as CSV with a `CSV_COLUMNS` header; missing values are empty
+fn apps_to_csv(apps: &[IpaInfo]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    
+    for app in apps {
+        csv.push_str(&csv_row(app));
+        csv.push('\n');
+    }
+    
+    csv
+}
+
+/// One app's `CSV_COLUMNS` values, without the line break
+fn csv_row(app: &IpaInfo) -> String {
+    let row = [
+        app.app_name.clone(),
+        app.app_version.clone(),
+        app.bundle_identifier.clone(),
+        app.app_size.map(|size| size.to_string()).unwrap_or_default(),
+        app.icon_name.clone().unwrap_or_default(),
+        app.file_name.clone().unwrap_or_default(),
+        app.timestamp.to_string(),
+    ];
+    let fields: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+    fields.join(",")
+}
+
+/// Quotes a field (doubling inner quotes) when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Ensures output ends with exactly one newline, or none when disabled
+fn with_trailing_newline(output: String, newline: bool) -> String {
+    let mut output = output.trim_end_matches('\n').to_string();
+    if newline {
+        output.push('\n');
+    }
+    output
+}
+
+/// Stable sort of app objects by one field; apps missing the field go last
+/// in either direction
+fn sort_apps(apps: &mut [serde_json::Value], field: SortField, descending: bool) {
+    use std::cmp::Ordering;
+    
+    let compare = |a: &serde_json::Value, b: &serde_json::Value| -> Option<Ordering> {
+        Some(match field {
+            SortField::Size => a["AppSize"].as_u64()?.cmp(&b["AppSize"].as_u64()?),
+            SortField::Name => a["AppName"].as_str()?.to_lowercase().cmp(&b["AppName"].as_str()?.to_lowercase()),
+            SortField::Version => compare_versions(a["AppVersion"].as_str()?, b["AppVersion"].as_str()?),
+            SortField::Bundleid => a["AppBundleIdentifier"].as_str()?.cmp(b["AppBundleIdentifier"].as_str()?),
+        })
+    };
+    let has_field = |app: &serde_json::Value| compare(app, app).is_some();
+    
+    apps.sort_by(|a, b| match (has_field(a), has_field(b)) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => Ordering::Equal,
+        (true, true) => {
+            let ordering = compare(a, b).unwrap_or(Ordering::Equal);
+            if descending { ordering.reverse() } else { ordering }
+        }
+    });
+}
+
+/// Compares dotted versions component by component: numbers numerically,
+/// anything else as text, so "1.10" sorts after "1.9"
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Fisher-Yates shuffle driven by a splitmix64 generator, so a seed always
+/// produces the same order
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_for() {
+        let code = |e: IpaError| exit_code_for(&anyhow::Error::from(e));
+        
+        assert_eq!(code(IpaError::InvalidIpa("bad".to_string())), exit_code::NOT_AN_IPA);
+        assert_eq!(code(IpaError::InfoPlistNotFound), exit_code::MISSING_PLIST);
+        assert_eq!(code(IpaError::Io(std::io::ErrorKind::NotFound.into())), exit_code::IO);
+        assert_eq!(
+            code(IpaError::Zip(zip::result::ZipError::UnsupportedArchive(
+                zip::result::ZipError::PASSWORD_REQUIRED
+            ))),
+            exit_code::ENCRYPTED
+        );
+        assert_eq!(code(IpaError::DeclaredIconsMissing("AppIcon".to_string())), exit_code::ICON);
+        assert_eq!(code(IpaError::PngNormalization("bad".to_string())), exit_code::ICON);
+        assert_eq!(
+            code(IpaError::HashMismatch { expected: "a".to_string(), actual: "b".to_string() }),
+            exit_code::HASH_MISMATCH
+        );
+        assert_eq!(code(IpaError::Manifest("bad".to_string())), exit_code::MANIFEST);
+        assert_eq!(code(IpaError::Provisioning("bad".to_string())), exit_code::BUNDLE_CONTENT);
+        assert_eq!(code(IpaError::Unsupported("combo".to_string())), exit_code::INTERNAL);
+        assert_eq!(exit_code_for(&anyhow::anyhow!("usage")), exit_code::INTERNAL);
+    }
+
+    #[test]
+    fn test_render_yaml_and_toml() {
+        let apps = serde_json::json!([
+            { "AppName": "Demo", "AppSize": 10, "Label": null, "LocalizedNames": { "fr": "Démo" } },
+            { "AppName": "Other", "AppSize": 20 },
+        ]);
+        
+        let toml = render(&apps, OutputFormat::Toml, false).unwrap();
+        assert!(toml.starts_with("[[Apps]]\nAppName = \"Demo\"\n"), "{}", toml);
+        assert!(!toml.contains("Label"));
+        let parsed: toml::Value = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed["Apps"][1]["AppSize"].as_integer(), Some(20));
+        assert_eq!(parsed["Apps"][0]["LocalizedNames"]["fr"].as_str(), Some("Démo"));
+        
+        let keyed = serde_json::json!({ "demo.ipa": { "AppName": "Demo" } });
+        assert_eq!(render(&keyed, OutputFormat::Toml, true).unwrap(), "[\"demo.ipa\"]\nAppName = \"Demo\"\n");
+        
+        let yaml = render(&apps, OutputFormat::Yaml, false).unwrap();
+        assert_eq!(yaml, render(&apps, OutputFormat::Yaml, true).unwrap());
+        let parsed: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed, apps);
+    }
+
+    #[test]
+    fn test_summary_renders_in_every_structured_format() {
+        let app = |name: &str| IpaInfo { app_name: name.to_string(), ..Default::default() };
+        let apps = serde_json::to_value([app("Demo"), app("Other")]).unwrap();
+        let wrapped = with_summary(apps.clone());
+        
+        let toml: toml::Value = toml::from_str(&render(&wrapped, OutputFormat::Toml, false).unwrap()).unwrap();
+        assert_eq!(toml["Summary"]["TotalApps"].as_integer(), Some(2));
+        assert_eq!(toml["Apps"][1]["AppName"].as_str(), Some("Other"));
+        let yaml: serde_json::Value = serde_yaml::from_str(&render(&wrapped, OutputFormat::Yaml, false).unwrap()).unwrap();
+        assert_eq!(yaml, wrapped);
+        assert_eq!(wrapped["Apps"], apps);
+    }
+
+    #[test]
+    fn test_icon_checksums() {
+        let value = serde_json::json!({
+            "b.ipa": { "IconName": "bbb.png", "IconChecksum": "22" },
+            "a.ipa": { "IconName": "aaa.png", "IconChecksum": "11" },
+            "c.ipa": { "AppName": "No icon" }
+        });
+        assert_eq!(icon_checksums(&value), "11  aaa.png\n22  bbb.png\n");
+    }
+
+    #[test]
+    fn test_collect_apps_from_grouped_output() {
+        let app = |id: &str| {
+            let info = IpaInfo { bundle_identifier: id.to_string(), ..Default::default() };
+            serde_json::to_value(info).unwrap()
+        };
+        let value = serde_json::json!({
+            "com.acme": [app("com.acme.a"), app("com.acme.b")],
+            "org.x": [app("org.x.c")]
+        });
+        
+        let mut apps = Vec::new();
+        collect_apps(&value, &mut apps);
+        assert_eq!(summarize(&apps).total_apps, 3);
+    }
+
+    #[test]
+    fn test_with_trailing_newline() {
+        assert_eq!(with_trailing_newline("{}".to_string(), true), "{}\n");
+        assert_eq!(with_trailing_newline("{}\n\n".to_string(), true), "{}\n");
+        assert_eq!(with_trailing_newline("{}\n".to_string(), false), "{}");
+    }
+
+    #[test]
+    fn test_sort_apps() {
+        let app = |name: &str, version: &str, size: Option<u64>| {
+            serde_json::json!({ "AppName": name, "AppVersion": version, "AppSize": size })
+        };
+        let mut apps = vec![app("beta", "1.10", Some(5)), app("Alpha", "1.9", None), app("gamma", "2.0", Some(9))];
+        let names = |apps: &[serde_json::Value]| -> Vec<String> {
+            apps.iter().map(|a| a["AppName"].as_str().unwrap().to_string()).collect()
+        };
+        
+        sort_apps(&mut apps, SortField::Size, true);
+        assert_eq!(names(&apps), ["gamma", "beta", "Alpha"]);
+        sort_apps(&mut apps, SortField::Name, false);
+        assert_eq!(names(&apps), ["Alpha", "beta", "gamma"]);
+        sort_apps(&mut apps, SortField::Version, false);
+        assert_eq!(names(&apps), ["Alpha", "beta", "gamma"]);
+    }
+
+    #[test]
+    fn test_apps_to_csv() {
+        let app = |name: &str, size: Option<u64>, file_name: Option<&str>| IpaInfo {
+            app_name: name.to_string(),
+            app_version: "1.0".to_string(),
+            bundle_identifier: "com.example.demo".to_string(),
+            app_size: size,
+            file_name: file_name.map(str::to_string),
+            timestamp: 1702123456,
+            ..Default::default()
+        };
+        
+        let csv = apps_to_csv(&[app("Demo", Some(42), Some("demo.ipa")), app("Say \"Hi\", Inc", None, None)]);
+        assert_eq!(
+            csv,
+            "AppName,AppVersion,AppBundleIdentifier,AppSize,IconName,FileName,Timestamp\n\
+             Demo,1.0,com.example.demo,42,,demo.ipa,1702123456\n\
+             \"Say \"\"Hi\"\", Inc\",1.0,com.example.demo,,,,1702123456\n"
+        );
+    }
+
+    #[test]
+    fn test_shuffle_is_seeded() {
+        let mut a: Vec<u32> = (0..20).collect();
+        let mut b = a.clone();
+        shuffle(&mut a, 42);
+        shuffle(&mut b, 42);
+        assert_eq!(a, b);
+        assert_ne!(a, (0..20).collect::<Vec<_>>());
+        
+        a.sort();
+        assert_eq!(a, (0..20).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,236 @@
+use crate::error::{IpaError, Result};
+use crate::hashing::HashEncoding;
+use crate::icon_extractor::{with_icon_writer, IconSlot, IconWriter};
+use crate::{
+    compute_file_hash, drop_failed_icon_writes, par_map, parse_ipa_with_writer, results_to_value, IpaInfo, ParseOptions,
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// One IPA listed in a batch manifest
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestEntry {
+    /// IPA path, resolved relative to the manifest's directory
+    pub path: PathBuf,
+    /// Label copied onto the parsed `IpaInfo`
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Expected digest of the IPA file, as `FileHash` would show it: `hash_algorithm`
+    /// written in `hash_encoding` (case-insensitive unless that is base64url)
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+}
+
+/// Reads a manifest file listing IPAs to process
+///
+/// JSON manifests are either an array of entries or `{ "entries": [...] }`;
+/// `.toml` manifests use `[[entries]]` tables. Each entry is validated on its
+/// own so one malformed entry doesn't reject the whole manifest.
+pub fn load_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<Vec<Result<ManifestEntry>>> {
+    let manifest_path = manifest_path.as_ref();
+    let contents = std::fs::read_to_string(manifest_path)?;
+    
+    let is_toml = manifest_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    
+    let root: serde_json::Value = if is_toml {
+        toml::from_str(&contents).map_err(|e| IpaError::Manifest(e.to_string()))?
+    } else {
+        serde_json::from_str(&contents).map_err(|e| IpaError::Manifest(e.to_string()))?
+    };
+    
+    let raw_entries = match root {
+        serde_json::Value::Array(entries) => entries,
+        serde_json::Value::Object(mut map) => match map.remove("entries") {
+            Some(serde_json::Value::Array(entries)) => entries,
+            _ => {
+                return Err(IpaError::Manifest(
+                    "expected an `entries` array".to_string(),
+                ))
+            }
+        },
+        _ => {
+            return Err(IpaError::Manifest(
+                "expected an array of entries".to_string(),
+            ))
+        }
+    };
+    
+    let base_dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    
+    Ok(raw_entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, raw)| {
+            let mut entry: ManifestEntry = serde_json::from_value(raw)
+                .map_err(|e| IpaError::Manifest(format!("entry {}: {}", index, e)))?;
+            if entry.path.is_relative() {
+                entry.path = base_dir.join(&entry.path);
+            }
+            Ok(entry)
+        })
+        .collect())
+}
+
+/// Parses a single manifest entry, verifying its hash and applying its label
+pub fn parse_manifest_entry(entry: &ManifestEntry, options: &ParseOptions) -> Result<IpaInfo> {
+    parse_entry_with_writer(entry, options, IconWriter::direct().slot(0))
+}
+
+fn parse_entry_with_writer(
+    entry: &ManifestEntry,
+    options: &ParseOptions,
+    slot: IconSlot<'_>,
+) -> Result<IpaInfo> {
+    if let Some(expected) = &entry.expected_hash {
+        let actual = compute_file_hash(&entry.path, options.hash_algorithm, options.hash_encoding)?;
+        let matches = match options.hash_encoding {
+            HashEncoding::Base64Url => actual == *expected,
+            _ => actual.eq_ignore_ascii_case(expected),
+        };
+        if !matches {
+            return Err(IpaError::HashMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+    
+    let mut info = parse_ipa_with_writer(&entry.path, options, slot)?;
+    info.label = entry.label.clone();
+    Ok(info)
+}
+
+/// Parses every IPA listed in a manifest in parallel
+/// Malformed entries and failed parses are reported as warnings and skipped;
+/// the output is shaped by `key_by`/`group_by_vendor` like `parse_multiple_ipas`.
+/// An `expected_hash` mismatch is different: every entry is still checked and
+/// warned about, then the call fails with the first mismatch in manifest order
+pub fn parse_manifest<P: AsRef<Path>>(
+    manifest_path: P,
+    options: &ParseOptions,
+) -> Result<serde_json::Value> {
+    let mut entries = Vec::new();
+    for entry in load_manifest(manifest_path)? {
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+    
+    let entries: Vec<(usize, ManifestEntry)> = entries.into_iter().enumerate().collect();
+    let (results, failed) = with_icon_writer(options.serialize_icon_writes, |writer| {
+        par_map(&entries, |(index, entry)| parse_entry_with_writer(entry, options, writer.slot(*index)))
+    });
+    
+    let mut parsed = Vec::new();
+    let mut mismatch = None;
+    for ((_, entry), result) in entries.iter().zip(results) {
+        match result {
+            Ok(mut info) => {
+                drop_failed_icon_writes(&mut info, &failed, options);
+                parsed.push((entry.path.clone(), info));
+            }
+            Err(e) => {
+                log::warn!("Failed to parse {}: {}", entry.path.display(), e);
+                if matches!(e, IpaError::HashMismatch { .. }) {
+                    mismatch.get_or_insert(e);
+                }
+            }
+        }
+    }
+    if let Some(e) = mismatch {
+        return Err(e);
+    }
+    
+    results_to_value(parsed, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_load_manifest_reports_malformed_entries() {
+        let dir = TempDir::new("manifest");
+        let manifest = dir.join("manifest.json");
+        std::fs::write(
+            &manifest,
+            r#"[{"path": "a.ipa", "label": "alpha"}, {"label": "no path"}]"#,
+        )
+        .unwrap();
+        
+        let entries = load_manifest(&manifest).unwrap();
+        
+        assert_eq!(entries.len(), 2);
+        let first = entries[0].as_ref().unwrap();
+        assert_eq!(first.path, dir.join("a.ipa"));
+        assert_eq!(first.label.as_deref(), Some("alpha"));
+        assert!(matches!(&entries[1], Err(IpaError::Manifest(msg)) if msg.starts_with("entry 1")));
+        
+        // Unparseable JSON is a manifest error, as unparseable TOML is
+        std::fs::write(&manifest, r#"[{"path": "#).unwrap();
+        assert!(matches!(load_manifest(&manifest), Err(IpaError::Manifest(_))));
+    }
+
+    #[test]
+    fn test_expected_hash_uses_hash_encoding() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("manifest-hash");
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        let path = dir.join("demo.ipa");
+        std::fs::write(&path, &data).unwrap();
+        let digest = crate::HashAlgo::Sha256.digest(&data);
+        
+        let check = |encoding: HashEncoding, expected: String| {
+            let options = ParseOptions { hash_algorithm: crate::HashAlgo::Sha256, hash_encoding: encoding, ..Default::default() };
+            let entry = ManifestEntry { path: path.clone(), label: None, expected_hash: Some(expected) };
+            parse_manifest_entry(&entry, &options)
+        };
+        let lower = HashEncoding::LowerHex.encode(&digest);
+        let base64 = HashEncoding::Base64Url.encode(&digest);
+        let results = [
+            check(HashEncoding::LowerHex, lower.to_uppercase()),
+            check(HashEncoding::Base32, HashEncoding::Base32.encode(&digest).to_lowercase()),
+            check(HashEncoding::Base64Url, base64.clone()),
+            // Hex is not accepted when FileHash would be written differently
+            check(HashEncoding::Base64Url, lower),
+            check(HashEncoding::Base64Url, base64.to_lowercase()),
+        ];
+        
+        assert!(results[..3].iter().all(Result::is_ok));
+        assert!(results[3..].iter().all(|r| matches!(r, Err(IpaError::HashMismatch { .. }))));
+    }
+
+    #[test]
+    fn test_parse_manifest_fails_on_hash_mismatch() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let dir = TempDir::new("manifest-mismatch");
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist_xml(info_plist("Demo", "com.example.demo")))]);
+        std::fs::write(dir.join("demo.ipa"), &data).unwrap();
+        let md5 = HashEncoding::LowerHex.encode(&crate::HashAlgo::Md5.digest(&data));
+        let manifest = dir.join("manifest.json");
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        
+        let write = |hashes: [&str; 2]| {
+            let entries = serde_json::json!([
+                { "path": "demo.ipa", "expected_hash": hashes[0] },
+                { "path": "missing.ipa" },
+                { "path": "demo.ipa", "label": "again", "expected_hash": hashes[1] },
+            ]);
+            std::fs::write(&manifest, entries.to_string()).unwrap();
+        };
+        
+        // A missing file is only skipped
+        write([&md5, &md5]);
+        assert_eq!(parse_manifest(&manifest, &options).unwrap().as_array().unwrap().len(), 2);
+        
+        write([&md5, "0000"]);
+        let err = parse_manifest(&manifest, &options).unwrap_err();
+        assert!(matches!(err, IpaError::HashMismatch { expected, .. } if expected == "0000"));
+    }
+}
@@ -1,15 +1,127 @@
 use crate::error::{IpaError, Result};
+use crate::strings_file;
 use plist::Value;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Read;
 use zip::ZipArchive;
 
 /// Extracted metadata from Info.plist
 #[derive(Debug, Clone)]
 pub struct PlistInfo {
+    /// Archive path of the app bundle directory, e.g. `Payload/App.app/`
+    /// (empty when parsed from a bare dictionary)
+    pub app_root: String,
+    /// Which container layout `app_root` was found in
+    pub layout: ContainerLayout,
     pub app_name: String,
+    /// CFBundleShortVersionString, or CFBundleVersion when that is missing
     pub app_version: String,
+    /// CFBundleVersion, the build number
+    pub build_number: Option<String>,
     pub bundle_identifier: String,
+    /// Declared primary icon names, ordered by the pixel size encoded in the name
+    /// (`<W>x<H>@<S>x` or legacy `Icon-<N>@<S>x`), smallest first and largest last.
+    /// Names without a recognizable size come first; ties keep plist order.
+    /// This order is informational: icon extraction matches files against every
+    /// name and keeps the largest entry by byte size (see `icon_extractor`)
     pub icon_files: Vec<String>,
+    /// CFBundleAlternateIcons names, in the same order; see `IconSet::alternates`
+    pub alternate_icon_files: Vec<String>,
+    /// UIPrerenderedIcon; see `IconSet::prerendered`
+    pub prerendered_icon: Option<bool>,
+    /// CFBundleIconName: the app icon's image set in Assets.car
+    pub icon_asset_name: Option<String>,
+    pub external_accessory_protocols: Vec<String>,
+    pub game_controller_profiles: Vec<String>,
+    pub supports_game_controllers: bool,
+    pub uses_non_exempt_encryption: Option<bool>,
+    pub accent_color_name: Option<String>,
+    pub allows_mixed_localizations: Option<bool>,
+    pub development_region: Option<String>,
+    /// LSApplicationCategoryType, e.g. `public.app-category.games`
+    pub category: Option<String>,
+    pub shortcut_items: Vec<ShortcutItem>,
+    pub user_activity_types: Vec<String>,
+    /// UIDeviceFamily: 1 = iPhone/iPod touch, 2 = iPad, 3 = Apple TV, 4 = Apple Watch
+    pub device_families: Vec<u32>,
+    /// UIRequiredDeviceCapabilities, e.g. `arm64`, `metal`; from the dictionary
+    /// form, only the keys set to true
+    pub required_capabilities: Vec<String>,
+    /// Every CFBundleURLSchemes entry across CFBundleURLTypes, first occurrence kept
+    pub url_schemes: Vec<String>,
+    /// Top-level `NS*UsageDescription` keys and the permission prompt text
+    pub usage_descriptions: HashMap<String, String>,
+    /// Display name per language, from the bundle's `<lang>.lproj/InfoPlist.strings`
+    /// (keyed by the lproj name, e.g. `en`, `Base`, `zh-Hans`)
+    pub localized_names: HashMap<String, String>,
+    /// CFBundleExecutable: the main binary's file name inside the bundle
+    pub executable: Option<String>,
+    pub minimum_os_version: Option<String>,
+    /// DTSDKName: the SDK Xcode built against, e.g. `iphoneos17.2`
+    pub sdk_name: Option<String>,
+    /// DTPlatformVersion: that SDK's platform version, e.g. `17.2`
+    pub platform_version: Option<String>,
+    /// BuildMachineOSBuild: the macOS build of the build machine, e.g. `23C71`
+    pub build_os: Option<String>,
+    /// UIApplicationSceneManifest is present
+    pub has_scene_manifest: bool,
+    /// A UIKit entry point is named: UIApplicationDelegateClassName, a main
+    /// storyboard or nib, or a scene configuration's delegate class or storyboard
+    pub declares_uikit_entry_point: bool,
+}
+
+/// Where the app bundle sits inside the archive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerLayout {
+    /// `Payload/<Name>.app/`, as in App Store IPAs
+    #[default]
+    Payload,
+    /// `<Name>.app/` at the archive root, as in some `.tipa` files
+    RootApp,
+    /// `Payload/<Name>/` without the `.app` suffix (only with `lenient_layout`)
+    PayloadWithoutAppSuffix,
+}
+
+impl ContainerLayout {
+    /// True for the standard App Store layout
+    pub fn is_payload(&self) -> bool {
+        *self == ContainerLayout::Payload
+    }
+}
+
+/// The SDK and build machine Xcode recorded in Info.plist (DTSDKName,
+/// DTPlatformVersion, BuildMachineOSBuild); each is independently optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    #[serde(rename = "SDKName", skip_serializing_if = "Option::is_none")]
+    pub sdk_name: Option<String>,
+    #[serde(rename = "PlatformVersion", skip_serializing_if = "Option::is_none")]
+    pub platform_version: Option<String>,
+    #[serde(rename = "BuildOS", skip_serializing_if = "Option::is_none")]
+    pub build_os: Option<String>,
+}
+
+impl BuildInfo {
+    /// The plist's SDK and build machine keys, or None when it has none of them
+    pub fn from_plist_info(info: &PlistInfo) -> Option<BuildInfo> {
+        let build_info = BuildInfo {
+            sdk_name: info.sdk_name.clone(),
+            platform_version: info.platform_version.clone(),
+            build_os: info.build_os.clone(),
+        };
+        (build_info != BuildInfo::default()).then_some(build_info)
+    }
+}
+
+/// A static Home Screen quick action from UIApplicationShortcutItems
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShortcutItem {
+    #[serde(rename = "Type")]
+    pub type_id: String,
+    /// May be a localization key rather than display text
+    #[serde(rename = "Title")]
+    pub title: String,
 }
 
 /// Extracts and parses Info.plist from IPA archive
@@ -17,22 +129,177 @@ pub struct PlistInfo {
 pub fn extract_plist_info<R: Read + std::io::Seek>(
     archive: &mut ZipArchive<R>,
 ) -> Result<PlistInfo> {
-    // Find Info.plist in Payload/*.app/Info.plist
-    let plist_file = find_info_plist(archive)?;
+    extract_plist_info_with(archive, false)
+}
+
+/// Like `extract_plist_info`; with `lenient_layout`, also accepts a Payload
+/// directory without the `.app` suffix (see `find_lenient_info_plist`)
+pub fn extract_plist_info_with<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    lenient_layout: bool,
+) -> Result<PlistInfo> {
+    // Find Info.plist in Payload/*.app/Info.plist, falling back to <Name>.app/Info.plist
+    let (plist_index, layout) = match find_info_plist(archive) {
+        Err(IpaError::InfoPlistNotFound) if lenient_layout => {
+            (find_lenient_info_plist(archive)?, ContainerLayout::PayloadWithoutAppSuffix)
+        }
+        result => result?,
+    };
     
-    // Extract and parse plist
-    let mut file = archive.by_name(&plist_file)?;
+    // Extract and parse plist (by index, so duplicate names resolve to the first entry)
+    let method = archive.by_index_raw(plist_index)?.compression();
+    let mut file = match archive.by_index(plist_index) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::UnsupportedArchive(_))
+            if !zip::SUPPORTED_COMPRESSION_METHODS.contains(&method) =>
+        {
+            return Err(IpaError::InvalidIpa(format!(
+                "unsupported compression for Info.plist: {}",
+                compression_name(method)
+            )));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let app_root = file.name().trim_end_matches("Info.plist").to_string();
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
+    drop(file);
     
     // Parse plist (handles both binary and XML formats automatically)
-    let plist = Value::from_reader(std::io::Cursor::new(buffer))?;
-    
-    // Extract required fields
-    let dict = plist
-        .as_dictionary()
+    let plist = read_plist_dict(&buffer)?
         .ok_or_else(|| IpaError::InvalidIpa("Info.plist is not a dictionary".to_string()))?;
     
+    let mut info = parse_info_dict(&plist)?;
+    info.localized_names = extract_localized_names(archive, &app_root)?;
+    info.app_root = app_root;
+    info.layout = layout;
+    Ok(info)
+}
+
+/// CFBundleDisplayName (else CFBundleName) from each `<app_root><lang>.lproj/InfoPlist.strings`
+/// Tables that can't be read or parsed, or that name neither key, are skipped
+fn extract_localized_names<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    app_root: &str,
+) -> Result<HashMap<String, String>> {
+    let mut tables = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let language = file
+            .name()
+            .strip_prefix(app_root)
+            .and_then(|rest| rest.strip_suffix(".lproj/InfoPlist.strings"))
+            .filter(|language| !language.is_empty() && !language.contains('/'));
+        if let Some(language) = language {
+            tables.push((i, language.to_string()));
+        }
+    }
+    
+    let mut names = HashMap::new();
+    for (index, language) in tables {
+        let mut buffer = Vec::new();
+        let Ok(mut file) = archive.by_index(index) else {
+            continue;
+        };
+        if file.read_to_end(&mut buffer).is_err() {
+            continue;
+        }
+        if let Some(name) = localized_name(&buffer) {
+            names.entry(language).or_insert(name);
+        }
+    }
+    
+    Ok(names)
+}
+
+/// Whether a CFBundleIdentifier has the reverse-DNS form Apple requires: two or
+/// more dot-separated components, each non-empty ASCII letters, digits and hyphens
+pub fn is_valid_bundle_id(bundle_id: &str) -> bool {
+    let components: Vec<&str> = bundle_id.split('.').collect();
+    components.len() >= 2
+        && components
+            .iter()
+            .all(|c| !c.is_empty() && c.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-'))
+}
+
+/// CFBundleDisplayName, else CFBundleName, from an InfoPlist.strings table
+pub(crate) fn localized_name(strings_data: &[u8]) -> Option<String> {
+    let mut strings = strings_file::parse_strings(strings_data)?;
+    strings.remove("CFBundleDisplayName").or_else(|| strings.remove("CFBundleName"))
+}
+
+/// Human-readable name for a ZIP compression method this build can't decode
+fn compression_name(method: zip::CompressionMethod) -> String {
+    use zip::CompressionMethod as M;
+    
+    let name = match method {
+        m if m == M::DEFLATE64 => "deflate64",
+        m if m == M::BZIP2 => "bzip2",
+        m if m == M::LZMA => "lzma",
+        m if m == M::ZSTD => "zstd",
+        m if m == M::XZ => "xz",
+        m if m == M::PPMD => "ppmd",
+        other => return other.to_string(),
+    };
+    name.to_string()
+}
+
+/// Parses plist bytes (binary or XML) whose root should be a dictionary
+/// Returns None when the root is some other type
+pub fn read_plist_dict(data: &[u8]) -> Result<Option<plist::Dictionary>> {
+    match Value::from_reader(std::io::Cursor::new(data))? {
+        Value::Dictionary(dict) => Ok(Some(dict)),
+        _ => Ok(None),
+    }
+}
+
+/// Converts a plist value to JSON
+/// Dates become ISO 8601 strings, data becomes unpadded URL-safe base64,
+/// and non-finite reals (which JSON can't hold) become null
+pub fn plist_to_json(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+    
+    match value {
+        Value::Dictionary(dict) => Json::Object(
+            dict.iter().map(|(k, v)| (k.clone(), plist_to_json(v))).collect(),
+        ),
+        Value::Array(items) => Json::Array(items.iter().map(plist_to_json).collect()),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Integer(i) => match (i.as_signed(), i.as_unsigned()) {
+            (Some(n), _) => Json::from(n),
+            (None, Some(n)) => Json::from(n),
+            (None, None) => Json::Null,
+        },
+        Value::Real(r) => serde_json::Number::from_f64(*r).map_or(Json::Null, Json::Number),
+        Value::Date(date) => Json::String(date.to_xml_format()),
+        Value::Data(data) => Json::String(crate::hashing::HashEncoding::Base64Url.encode(data)),
+        Value::Uid(uid) => Json::from(uid.get()),
+        _ => Json::Null,
+    }
+}
+
+/// Lists the preference keys declared in a Settings.bundle Root.plist
+/// Group and title-only specifiers without a `Key` are skipped
+pub fn extract_settings_keys(root_plist: &plist::Dictionary) -> Vec<String> {
+    let mut keys = Vec::new();
+    
+    if let Some(Value::Array(specifiers)) = root_plist.get("PreferenceSpecifiers") {
+        for specifier in specifiers {
+            if let Some(key) = specifier.as_dictionary().and_then(|d| get_string_value(d, "Key")) {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+    }
+    
+    keys
+}
+
+/// Builds `PlistInfo` from an already-parsed Info.plist dictionary
+pub fn parse_info_dict(dict: &plist::Dictionary) -> Result<PlistInfo> {
+    // Extract required fields
     let app_name = get_string_value(dict, "CFBundleName")
         .or_else(|| get_string_value(dict, "CFBundleDisplayName"))
         .ok_or_else(|| IpaError::MissingField("CFBundleName".to_string()))?;
@@ -40,40 +307,213 @@ pub fn extract_plist_info<R: Read + std::io::Seek>(
     let app_version = get_string_value(dict, "CFBundleShortVersionString")
         .or_else(|| get_string_value(dict, "CFBundleVersion"))
         .ok_or_else(|| IpaError::MissingField("CFBundleShortVersionString".to_string()))?;
+    let build_number = get_string_value(dict, "CFBundleVersion");
     
     let bundle_identifier = get_string_value(dict, "CFBundleIdentifier")
         .ok_or_else(|| IpaError::MissingField("CFBundleIdentifier".to_string()))?;
     
     // Extract icon file names
-    let icon_files = extract_icon_names(dict);
+    let icons = extract_icon_set(dict);
+    let icon_asset_name = extract_icon_asset_name(dict);
+    
+    // Accessory and controller capabilities
+    let external_accessory_protocols =
+        get_string_array(dict, "UISupportedExternalAccessoryProtocols");
+    let game_controller_profiles = extract_game_controller_profiles(dict);
+    let supports_game_controllers = !game_controller_profiles.is_empty()
+        || get_bool_value(dict, "GCSupportsControllerUserInteraction") == Some(true);
+    
+    // Export compliance flag (absent means unknown)
+    let uses_non_exempt_encryption = get_bool_value(dict, "ITSAppUsesNonExemptEncryption");
+    
+    let accent_color_name = extract_accent_color_name(dict);
+    
+    // Localization configuration
+    let allows_mixed_localizations = get_bool_value(dict, "CFBundleAllowMixedLocalizations");
+    let development_region = get_string_value(dict, "CFBundleDevelopmentRegion");
+    
+    let category = get_string_value(dict, "LSApplicationCategoryType");
+    
+    let shortcut_items = extract_shortcut_items(dict);
+    
+    let user_activity_types = get_string_array(dict, "NSUserActivityTypes");
+    
+    let device_families = extract_device_families(dict);
+    let required_capabilities = extract_required_capabilities(dict);
+    let url_schemes = extract_url_schemes(dict);
+    let usage_descriptions = extract_usage_descriptions(dict);
+    
+    let executable = get_string_value(dict, "CFBundleExecutable");
+    let minimum_os_version = get_string_value(dict, "MinimumOSVersion");
+    let sdk_name = get_string_value(dict, "DTSDKName");
+    let platform_version = get_string_value(dict, "DTPlatformVersion");
+    let build_os = get_string_value(dict, "BuildMachineOSBuild");
+    
+    let has_scene_manifest = dict.contains_key("UIApplicationSceneManifest");
+    let declares_uikit_entry_point = declares_uikit_entry_point(dict);
     
     Ok(PlistInfo {
+        app_root: String::new(),
+        layout: ContainerLayout::Payload,
         app_name,
         app_version,
+        build_number,
         bundle_identifier,
-        icon_files,
+        icon_files: icons.primary,
+        alternate_icon_files: icons.alternates,
+        prerendered_icon: icons.prerendered,
+        icon_asset_name,
+        external_accessory_protocols,
+        game_controller_profiles,
+        supports_game_controllers,
+        uses_non_exempt_encryption,
+        accent_color_name,
+        allows_mixed_localizations,
+        development_region,
+        category,
+        shortcut_items,
+        user_activity_types,
+        device_families,
+        required_capabilities,
+        url_schemes,
+        usage_descriptions,
+        localized_names: HashMap::new(),
+        executable,
+        minimum_os_version,
+        sdk_name,
+        platform_version,
+        build_os,
+        has_scene_manifest,
+        declares_uikit_entry_point,
     })
 }
 
-/// Finds Info.plist file in the archive
+/// Whether the plist names a UIKit app delegate, storyboard or nib, either at
+/// the top level or in any UIApplicationSceneManifest scene configuration
+fn declares_uikit_entry_point(dict: &plist::Dictionary) -> bool {
+    const TOP_LEVEL: [&str; 4] =
+        ["UIApplicationDelegateClassName", "UIMainStoryboardFile", "UIMainStoryboardFile~ipad", "NSMainNibFile"];
+    const SCENE: [&str; 2] = ["UISceneDelegateClassName", "UISceneStoryboardFile"];
+    
+    if TOP_LEVEL.iter().any(|key| dict.contains_key(key)) {
+        return true;
+    }
+    
+    // UISceneConfigurations maps a session role to an array of configurations
+    dict.get("UIApplicationSceneManifest")
+        .and_then(|manifest| manifest.as_dictionary())
+        .and_then(|manifest| manifest.get("UISceneConfigurations"))
+        .and_then(|configurations| configurations.as_dictionary())
+        .map(|configurations| {
+            configurations
+                .values()
+                .filter_map(|role| role.as_array())
+                .flatten()
+                .filter_map(|configuration| configuration.as_dictionary())
+                .any(|configuration| SCENE.iter().any(|key| configuration.contains_key(key)))
+        })
+        .unwrap_or(false)
+}
+
+/// Finds Info.plist file in the archive, returning the index of its first occurrence
+/// The main app's `Payload/<Name>.app/Info.plist` wins over plists of bundles nested
+/// inside it (`PlugIns/*.appex`, `Watch/*.app`), whatever the entry order; a
+/// `Payload/` bundle anywhere in the archive wins over a root-level `<Name>.app/`
 #[inline]
-fn find_info_plist<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Result<String> {
+fn find_info_plist<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<(usize, ContainerLayout)> {
+    let mut nested_app = None;
+    let mut root_app = None;
+    
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let Some(dir) = file.name().strip_suffix("/Info.plist") else {
+            continue;
+        };
+        
+        // Match pattern: Payload/*.app/Info.plist, preferring exactly one segment after Payload/
+        if let Some(bundle) = dir.strip_prefix("Payload/") {
+            if bundle.ends_with(".app") && !bundle.contains('/') {
+                return Ok((i, ContainerLayout::Payload));
+            }
+            if nested_app.is_none() && bundle.ends_with(".app") {
+                nested_app = Some(i);
+            }
+        }
+        
+        // Match pattern: *.app/Info.plist, directly at the root
+        if root_app.is_none() && dir.ends_with(".app") && !dir.contains('/') {
+            root_app = Some(i);
+        }
+    }
+    
+    nested_app
+        .map(|i| (i, ContainerLayout::Payload))
+        .or(root_app.map(|i| (i, ContainerLayout::RootApp)))
+        .ok_or(IpaError::InfoPlistNotFound)
+}
+
+/// Fallback for `Payload/<dir>/Info.plist` bundles that lack the `.app` suffix
+/// The directory must also hold a PkgInfo or an executable named after it,
+/// so stray plists in other folders aren't mistaken for an app
+fn find_lenient_info_plist<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Result<usize> {
+    let mut names = std::collections::HashSet::new();
+    let mut candidates = Vec::new();
+    
     for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
+        let file = archive.by_index_raw(i)?;
         let name = file.name();
         
-        // Match pattern: Payload/*.app/Info.plist
-        if name.starts_with("Payload/") && name.ends_with(".app/Info.plist") {
-            return Ok(name.to_string());
+        if let Some(dir) = name.strip_prefix("Payload/").and_then(|n| n.strip_suffix("/Info.plist")) {
+            if !dir.is_empty() && !dir.contains('/') {
+                candidates.push((i, dir.to_string()));
+            }
         }
+        names.insert(name.to_string());
     }
     
-    Err(IpaError::InfoPlistNotFound)
+    candidates
+        .into_iter()
+        .find(|(_, dir)| {
+            names.contains(&format!("Payload/{}/PkgInfo", dir))
+                || names.contains(&format!("Payload/{}/{}", dir, dir))
+        })
+        .map(|(i, _)| i)
+        .ok_or(IpaError::InfoPlistNotFound)
+}
+
+/// Icon names declared in Info.plist, split by the role they play
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IconSet {
+    /// CFBundleIconFiles, CFBundleIconFile and each CFBundleIcons primary icon
+    pub primary: Vec<String>,
+    /// Files and image set names of every CFBundleAlternateIcons entry, for
+    /// iPhone and iPad, leaving out names the primary icon already uses
+    pub alternates: Vec<String>,
+    /// UIPrerenderedIcon from a CFBundlePrimaryIcon, else the top-level key;
+    /// true means iOS 6 and earlier drew no gloss over the icon
+    pub prerendered: Option<bool>,
 }
 
-/// Extracts icon file names from plist dictionary
-fn extract_icon_names(dict: &plist::Dictionary) -> Vec<String> {
+impl IconSet {
+    /// Primary names followed by alternates, each part ordered by pixel size
+    pub fn flat(&self) -> Vec<String> {
+        self.primary.iter().chain(&self.alternates).cloned().collect()
+    }
+}
+
+/// Extracts every declared icon name into one list (see `IconSet::flat`)
+pub fn extract_icon_names(dict: &plist::Dictionary) -> Vec<String> {
+    extract_icon_set(dict).flat()
+}
+
+/// Extracts icon names from plist dictionary, keeping alternates apart from the
+/// primary icon. Both lists are ordered by pixel size, as `PlistInfo::icon_files` is
+pub fn extract_icon_set(dict: &plist::Dictionary) -> IconSet {
     let mut icons = Vec::new();
+    let mut alternates = Vec::new();
+    let mut prerendered = None;
     
     // Try CFBundleIconFiles (array)
     if let Some(Value::Array(arr)) = dict.get("CFBundleIconFiles") {
@@ -91,25 +531,257 @@ fn extract_icon_names(dict: &plist::Dictionary) -> Vec<String> {
         }
     }
     
-    // Try modern CFBundleIcons structure
-    if let Some(Value::Dictionary(icons_dict)) = dict.get("CFBundleIcons") {
-        if let Some(Value::Dictionary(primary)) = icons_dict.get("CFBundlePrimaryIcon") {
-            if let Some(Value::Array(files)) = primary.get("CFBundleIconFiles") {
-                for item in files {
-                    if let Some(s) = item.as_string() {
-                        let icon = s.to_string();
-                        if !icons.contains(&icon) {
-                            icons.push(icon);
+    // Try modern CFBundleIcons structure (iPhone and iPad variants)
+    for icons_key in ["CFBundleIcons", "CFBundleIcons~ipad"] {
+        if let Some(Value::Dictionary(icons_dict)) = dict.get(icons_key) {
+            if let Some(Value::Dictionary(primary)) = icons_dict.get("CFBundlePrimaryIcon") {
+                for icon in icon_dict_names(primary) {
+                    if !icons.contains(&icon) {
+                        icons.push(icon);
+                    }
+                }
+                prerendered = prerendered.or(get_bool_value(primary, "UIPrerenderedIcon"));
+            }
+            if let Some(Value::Dictionary(alternate_icons)) = icons_dict.get("CFBundleAlternateIcons") {
+                for alternate in alternate_icons.values().filter_map(Value::as_dictionary) {
+                    for icon in icon_dict_names(alternate) {
+                        if !alternates.contains(&icon) {
+                            alternates.push(icon);
                         }
                     }
                 }
             }
         }
     }
+    alternates.retain(|icon| !icons.contains(icon));
     
     // Remove duplicates while preserving order
     icons.dedup();
-    icons
+    
+    // Stable sort keeps plist order among equally-sized names
+    icons.sort_by_key(|name| icon_pixel_size(name));
+    alternates.sort_by_key(|name| icon_pixel_size(name));
+    IconSet {
+        primary: icons,
+        alternates,
+        prerendered: prerendered.or_else(|| get_bool_value(dict, "UIPrerenderedIcon")),
+    }
+}
+
+/// CFBundleIconFiles and CFBundleIconName of one CFBundleIcons entry
+/// Asset-catalog apps may only set the base name, e.g. "AppIcon", whose loose
+/// PNGs are named like AppIcon60x60@2x.png
+fn icon_dict_names(icon: &plist::Dictionary) -> Vec<String> {
+    let mut names = get_string_array(icon, "CFBundleIconFiles");
+    names.extend(get_string_value(icon, "CFBundleIconName"));
+    names
+}
+
+/// UIDeviceFamily as an array of integers, or the rare single-integer form
+/// Negative or non-integer entries are skipped
+fn extract_device_families(dict: &plist::Dictionary) -> Vec<u32> {
+    let family = |value: &Value| {
+        value
+            .as_unsigned_integer()
+            .or_else(|| value.as_signed_integer().and_then(|n| u64::try_from(n).ok()))
+            .and_then(|n| u32::try_from(n).ok())
+    };
+    
+    match dict.get("UIDeviceFamily") {
+        Some(Value::Array(arr)) => arr.iter().filter_map(family).collect(),
+        Some(value) => family(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// UIRequiredDeviceCapabilities as an array of names, or as a dictionary of
+/// name -> bool where false means the capability must be absent (those are skipped)
+fn extract_required_capabilities(dict: &plist::Dictionary) -> Vec<String> {
+    match dict.get("UIRequiredDeviceCapabilities") {
+        Some(Value::Dictionary(capabilities)) => capabilities
+            .iter()
+            .filter(|(_, required)| required.as_boolean() == Some(true))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        _ => get_string_array(dict, "UIRequiredDeviceCapabilities"),
+    }
+}
+
+/// Flattens the CFBundleURLSchemes arrays of every CFBundleURLTypes entry
+fn extract_url_schemes(dict: &plist::Dictionary) -> Vec<String> {
+    let mut schemes = Vec::new();
+    
+    if let Some(Value::Array(types)) = dict.get("CFBundleURLTypes") {
+        for url_type in types.iter().filter_map(Value::as_dictionary) {
+            for scheme in get_string_array(url_type, "CFBundleURLSchemes") {
+                if !schemes.contains(&scheme) {
+                    schemes.push(scheme);
+                }
+            }
+        }
+    }
+    
+    schemes
+}
+
+/// Privacy permission strings such as NSCameraUsageDescription
+/// Non-string values are skipped
+fn extract_usage_descriptions(dict: &plist::Dictionary) -> HashMap<String, String> {
+    dict.iter()
+        .filter(|(key, _)| key.starts_with("NS") && key.ends_with("UsageDescription"))
+        .filter_map(|(key, value)| Some((key.clone(), value.as_string()?.to_string())))
+        .collect()
+}
+
+/// Display label for an LSApplicationCategoryType UTI, e.g. "Games" for
+/// `public.app-category.games`; None for anything outside Apple's list
+pub fn category_label(uti: &str) -> Option<&'static str> {
+    let label = match uti.strip_prefix("public.app-category.")? {
+        "business" => "Business",
+        "developer-tools" => "Developer Tools",
+        "education" => "Education",
+        "entertainment" => "Entertainment",
+        "finance" => "Finance",
+        "games" => "Games",
+        "graphics-design" => "Graphics & Design",
+        "healthcare-fitness" => "Health & Fitness",
+        "lifestyle" => "Lifestyle",
+        "medical" => "Medical",
+        "music" => "Music",
+        "news" => "News",
+        "photography" => "Photography",
+        "productivity" => "Productivity",
+        "reference" => "Reference",
+        "social-networking" => "Social Networking",
+        "sports" => "Sports",
+        "travel" => "Travel",
+        "utilities" => "Utilities",
+        "video" => "Video",
+        "weather" => "Weather",
+        "action-games" => "Action Games",
+        "adventure-games" => "Adventure Games",
+        "arcade-games" => "Arcade Games",
+        "board-games" => "Board Games",
+        "card-games" => "Card Games",
+        "casino-games" => "Casino Games",
+        "dice-games" => "Dice Games",
+        "educational-games" => "Educational Games",
+        "family-games" => "Family Games",
+        "kids-games" => "Kids Games",
+        "music-games" => "Music Games",
+        "puzzle-games" => "Puzzle Games",
+        "racing-games" => "Racing Games",
+        "role-playing-games" => "Role Playing Games",
+        "simulation-games" => "Simulation Games",
+        "sports-games" => "Sports Games",
+        "strategy-games" => "Strategy Games",
+        "trivia-games" => "Trivia Games",
+        "word-games" => "Word Games",
+        _ => return None,
+    };
+    Some(label)
+}
+
+/// CFBundleIconName from the top level, or from the iPhone then iPad primary icon
+fn extract_icon_asset_name(dict: &plist::Dictionary) -> Option<String> {
+    get_string_value(dict, "CFBundleIconName").or_else(|| {
+        ["CFBundleIcons", "CFBundleIcons~ipad"].iter().find_map(|icons_key| {
+            let primary = dict.get(icons_key)?.as_dictionary()?.get("CFBundlePrimaryIcon")?.as_dictionary()?;
+            get_string_value(primary, "CFBundleIconName")
+        })
+    })
+}
+
+/// Estimates an icon's pixel width from its name, in hundredths of a pixel
+/// `AppIcon60x60@2x.png` -> 12000, `Icon-76.png` -> 7600, `AppIcon` -> 0
+pub fn icon_pixel_size(name: &str) -> u32 {
+    let stem = name.trim_end_matches(".png");
+    // Drop idiom suffixes such as ~ipad
+    let stem = stem.split('~').next().unwrap_or(stem);
+    
+    let (base, scale) = match stem.rsplit_once('@') {
+        Some((base, scale)) => (
+            base,
+            scale.strip_suffix('x').and_then(|s| s.parse::<u32>().ok()).unwrap_or(1),
+        ),
+        None => (stem, 1),
+    };
+    
+    // Trailing "<W>x<H>" (W may be fractional, e.g. 83.5x83.5)
+    let width = base
+        .rsplit_once('x')
+        .and_then(|(w, h)| {
+            h.parse::<f32>().ok()?;
+            // Split on chars, not bytes: names may hold non-ASCII text before the size
+            w.rsplit(|c: char| !(c.is_ascii_digit() || c == '.')).next()?.parse::<f32>().ok()
+        })
+        // Legacy "Icon-<N>"
+        .or_else(|| base.rsplit_once('-').and_then(|(_, n)| n.parse::<f32>().ok()));
+    
+    // Names come from the plist, so absurd sizes saturate instead of overflowing
+    width.map_or(0, |w| ((w * 100.0).clamp(0.0, u32::MAX as f32) as u32).saturating_mul(scale))
+}
+
+/// Finds the asset-catalog color name the app uses as its accent/tint
+/// Prefers NSAccentColorName, falling back to the launch screen background color.
+/// This is only the color's name: the RGB value lives in Assets.car and isn't decoded.
+fn extract_accent_color_name(dict: &plist::Dictionary) -> Option<String> {
+    get_string_value(dict, "NSAccentColorName").or_else(|| {
+        dict.get("UILaunchScreen")?
+            .as_dictionary()
+            .and_then(|launch| get_string_value(launch, "UIColorName"))
+    })
+}
+
+/// Extracts statically declared quick actions
+/// Entries without a UIApplicationShortcutItemType are skipped
+fn extract_shortcut_items(dict: &plist::Dictionary) -> Vec<ShortcutItem> {
+    let mut items = Vec::new();
+    
+    if let Some(Value::Array(arr)) = dict.get("UIApplicationShortcutItems") {
+        for item in arr.iter().filter_map(Value::as_dictionary) {
+            if let Some(type_id) = get_string_value(item, "UIApplicationShortcutItemType") {
+                items.push(ShortcutItem {
+                    type_id,
+                    title: get_string_value(item, "UIApplicationShortcutItemTitle").unwrap_or_default(),
+                });
+            }
+        }
+    }
+    
+    items
+}
+
+/// Extracts controller profile names from GCSupportedGameControllers
+/// Each entry is a dictionary like `{ ProfileName = "ExtendedGamepad" }`
+fn extract_game_controller_profiles(dict: &plist::Dictionary) -> Vec<String> {
+    let mut profiles = Vec::new();
+    
+    if let Some(Value::Array(arr)) = dict.get("GCSupportedGameControllers") {
+        for item in arr {
+            if let Some(profile) = item
+                .as_dictionary()
+                .and_then(|d| get_string_value(d, "ProfileName"))
+            {
+                if !profiles.contains(&profile) {
+                    profiles.push(profile);
+                }
+            }
+        }
+    }
+    
+    profiles
+}
+
+/// Helper to get an array of strings from dictionary, skipping non-string items
+fn get_string_array(dict: &plist::Dictionary, key: &str) -> Vec<String> {
+    match dict.get(key) {
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|item| item.as_string().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    }
 }
 
 /// Helper to get string value from dictionary
@@ -118,6 +790,12 @@ fn get_string_value(dict: &plist::Dictionary, key: &str) -> Option<String> {
     dict.get(key)?.as_string().map(|s| s.to_string())
 }
 
+/// Helper to get boolean value from dictionary
+#[inline]
+fn get_bool_value(dict: &plist::Dictionary, key: &str) -> Option<bool> {
+    dict.get(key)?.as_boolean()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,4 +815,487 @@ mod tests {
         assert_eq!(result.len(), 2);
         assert!(result.contains(&"Icon-60@2x.png".to_string()));
     }
+
+    #[test]
+    fn test_extract_icon_set() {
+        let icon_dict = |files: &[&str], prerendered: Option<bool>| {
+            let mut icon = plist::Dictionary::new();
+            icon.insert(
+                "CFBundleIconFiles".to_string(),
+                Value::Array(files.iter().map(|&f| f.into()).collect()),
+            );
+            if let Some(prerendered) = prerendered {
+                icon.insert("UIPrerenderedIcon".to_string(), prerendered.into());
+            }
+            Value::Dictionary(icon)
+        };
+        let mut alternates = plist::Dictionary::new();
+        alternates.insert("Dark".to_string(), icon_dict(&["DarkIcon60x60", "AppIcon60x60"], None));
+        alternates.insert("Retro".to_string(), icon_dict(&["RetroIcon76x76", "RetroIcon20x20"], Some(false)));
+        let mut icons = plist::Dictionary::new();
+        icons.insert("CFBundlePrimaryIcon".to_string(), icon_dict(&["AppIcon60x60"], Some(true)));
+        icons.insert("CFBundleAlternateIcons".to_string(), Value::Dictionary(alternates));
+        let mut dict = base_dict();
+        dict.insert("CFBundleIcons".to_string(), Value::Dictionary(icons));
+        dict.insert("UIPrerenderedIcon".to_string(), false.into());
+        
+        let set = extract_icon_set(&dict);
+        assert_eq!(set.primary, ["AppIcon60x60"]);
+        assert_eq!(set.alternates, ["RetroIcon20x20", "DarkIcon60x60", "RetroIcon76x76"]);
+        assert_eq!(set.prerendered, Some(true));
+        assert_eq!(extract_icon_names(&dict), ["AppIcon60x60", "RetroIcon20x20", "DarkIcon60x60", "RetroIcon76x76"]);
+        
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!(info.icon_files, set.primary);
+        assert_eq!(info.alternate_icon_files, set.alternates);
+        
+        // Legacy apps set the flag at the top level
+        let mut dict = base_dict();
+        dict.insert("UIPrerenderedIcon".to_string(), true.into());
+        assert_eq!(extract_icon_set(&dict), IconSet { prerendered: Some(true), ..Default::default() });
+    }
+
+    #[test]
+    fn test_icon_files_sorted_by_size() {
+        let mut dict = plist::Dictionary::new();
+        let names = ["AppIcon60x60@3x", "AppIcon", "AppIcon83.5x83.5@2x~ipad", "Icon-76", "AppIcon60x60@2x"];
+        dict.insert(
+            "CFBundleIconFiles".to_string(),
+            Value::Array(names.iter().map(|n| Value::String(n.to_string())).collect()),
+        );
+        
+        assert_eq!(
+            extract_icon_names(&dict),
+            vec!["AppIcon", "Icon-76", "AppIcon60x60@2x", "AppIcon83.5x83.5@2x~ipad", "AppIcon60x60@3x"]
+        );
+        assert_eq!(icon_pixel_size("AppIcon60x60@2x.png"), 12000);
+        assert_eq!(icon_pixel_size("AppIcon"), 0);
+        assert_eq!(icon_pixel_size("AppIcon99999999x99999999@2x.png"), u32::MAX);
+        assert_eq!(icon_pixel_size("AppIcon60x60@4294967295x.png"), u32::MAX);
+        assert_eq!(icon_pixel_size("é60x60"), 6000);
+        assert_eq!(icon_pixel_size("アイコン60x60@2x"), 12000);
+        assert_eq!(icon_pixel_size("アイコン"), 0);
+    }
+
+    #[test]
+    fn test_icon_name_only_plist() {
+        use crate::test_support::{build_zip, rgba_png};
+        
+        let mut primary = plist::Dictionary::new();
+        primary.insert("CFBundleIconName".to_string(), Value::String("AppIcon".to_string()));
+        let mut icons = plist::Dictionary::new();
+        icons.insert("CFBundlePrimaryIcon".to_string(), Value::Dictionary(primary));
+        let mut dict = base_dict();
+        dict.insert("CFBundleIcons".to_string(), Value::Dictionary(icons));
+        
+        let names = extract_icon_names(&dict);
+        assert_eq!(names, vec!["AppIcon"]);
+        
+        let icon = rgba_png(2, 2, [1, 2, 3, 255]);
+        let data = build_zip(&[("Payload/App.app/AppIcon60x60@2x.png", &icon)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        let info = parse_info_dict(&dict).unwrap();
+        let found = crate::icon_extractor::find_app_icon(&mut archive, &info, Default::default()).unwrap();
+        assert_eq!(found, Some(icon));
+    }
+
+    #[test]
+    fn test_accessory_and_controller_support() {
+        let mut dict = base_dict();
+        dict.insert(
+            "UISupportedExternalAccessoryProtocols".to_string(),
+            Value::Array(vec![Value::String("com.acme.remote".to_string())]),
+        );
+        let mut profile = plist::Dictionary::new();
+        profile.insert("ProfileName".to_string(), Value::String("ExtendedGamepad".to_string()));
+        dict.insert(
+            "GCSupportedGameControllers".to_string(),
+            Value::Array(vec![Value::Dictionary(profile)]),
+        );
+        
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!(info.external_accessory_protocols, vec!["com.acme.remote"]);
+        assert_eq!(info.game_controller_profiles, vec!["ExtendedGamepad"]);
+        assert!(info.supports_game_controllers);
+        
+        let info = parse_info_dict(&base_dict()).unwrap();
+        assert!(info.external_accessory_protocols.is_empty());
+        assert!(!info.supports_game_controllers);
+    }
+
+    #[test]
+    fn test_uses_non_exempt_encryption() {
+        let with_flag = |flag: bool| {
+            let mut dict = base_dict();
+            dict.insert("ITSAppUsesNonExemptEncryption".to_string(), Value::Boolean(flag));
+            parse_info_dict(&dict).unwrap().uses_non_exempt_encryption
+        };
+        
+        assert_eq!(with_flag(true), Some(true));
+        assert_eq!(with_flag(false), Some(false));
+        assert_eq!(parse_info_dict(&base_dict()).unwrap().uses_non_exempt_encryption, None);
+    }
+
+    #[test]
+    fn test_shortcut_items() {
+        let item = |type_id: Option<&str>, title: &str| {
+            let mut d = plist::Dictionary::new();
+            if let Some(type_id) = type_id {
+                d.insert("UIApplicationShortcutItemType".to_string(), Value::String(type_id.to_string()));
+            }
+            d.insert("UIApplicationShortcutItemTitle".to_string(), Value::String(title.to_string()));
+            Value::Dictionary(d)
+        };
+        let mut dict = base_dict();
+        dict.insert(
+            "UIApplicationShortcutItems".to_string(),
+            Value::Array(vec![item(Some("com.example.compose"), "New Message"), item(None, "Broken")]),
+        );
+        
+        assert_eq!(
+            parse_info_dict(&dict).unwrap().shortcut_items,
+            vec![ShortcutItem {
+                type_id: "com.example.compose".to_string(),
+                title: "New Message".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_user_activity_types() {
+        let mut dict = base_dict();
+        assert!(parse_info_dict(&dict).unwrap().user_activity_types.is_empty());
+        
+        dict.insert(
+            "NSUserActivityTypes".to_string(),
+            Value::Array(vec!["com.example.view-item".into(), "INSendMessageIntent".into()]),
+        );
+        assert_eq!(
+            parse_info_dict(&dict).unwrap().user_activity_types,
+            vec!["com.example.view-item", "INSendMessageIntent"]
+        );
+    }
+
+    #[test]
+    fn test_device_families() {
+        let mut dict = base_dict();
+        assert!(parse_info_dict(&dict).unwrap().device_families.is_empty());
+        
+        dict.insert("UIDeviceFamily".to_string(), Value::Array(vec![1.into(), 2.into()]));
+        assert_eq!(parse_info_dict(&dict).unwrap().device_families, vec![1, 2]);
+        
+        dict.insert("UIDeviceFamily".to_string(), 2.into());
+        assert_eq!(parse_info_dict(&dict).unwrap().device_families, vec![2]);
+    }
+
+    #[test]
+    fn test_url_schemes() {
+        let url_type = |schemes: &[&str]| {
+            let mut url_type = plist::Dictionary::new();
+            url_type.insert("CFBundleURLName".to_string(), "com.example.demo".into());
+            url_type.insert(
+                "CFBundleURLSchemes".to_string(),
+                Value::Array(schemes.iter().map(|&s| s.into()).collect()),
+            );
+            Value::Dictionary(url_type)
+        };
+        let mut dict = base_dict();
+        assert!(parse_info_dict(&dict).unwrap().url_schemes.is_empty());
+        
+        dict.insert(
+            "CFBundleURLTypes".to_string(),
+            Value::Array(vec![url_type(&["demo", "fb12345"]), url_type(&["demo-auth", "demo"])]),
+        );
+        assert_eq!(parse_info_dict(&dict).unwrap().url_schemes, vec!["demo", "fb12345", "demo-auth"]);
+    }
+
+    #[test]
+    fn test_build_number() {
+        let mut dict = base_dict();
+        dict.insert("CFBundleVersion".to_string(), "1234".into());
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!(info.app_version, "1.0");
+        assert_eq!(info.build_number.as_deref(), Some("1234"));
+        
+        dict.remove("CFBundleVersion");
+        assert_eq!(parse_info_dict(&dict).unwrap().build_number, None);
+        
+        dict.remove("CFBundleShortVersionString");
+        dict.insert("CFBundleVersion".to_string(), "42".into());
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!((info.app_version.as_str(), info.build_number.as_deref()), ("42", Some("42")));
+    }
+
+    #[test]
+    fn test_is_valid_bundle_id() {
+        for valid in ["com.example.app", "com.example.my-app2", "io.Acme.App"] {
+            assert!(is_valid_bundle_id(valid), "{}", valid);
+        }
+        let invalid = ["", "app", "com.example app", "com..example", ".com.example", "com.example.", "com.exämple.app", "com_example"];
+        for invalid in invalid {
+            assert!(!is_valid_bundle_id(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_build_info() {
+        let mut dict = base_dict();
+        assert_eq!(BuildInfo::from_plist_info(&parse_info_dict(&dict).unwrap()), None);
+        
+        dict.insert("DTSDKName".to_string(), "iphoneos17.2".into());
+        dict.insert("BuildMachineOSBuild".to_string(), "23C71".into());
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!(
+            BuildInfo::from_plist_info(&info),
+            Some(BuildInfo {
+                sdk_name: Some("iphoneos17.2".to_string()),
+                platform_version: None,
+                build_os: Some("23C71".to_string()),
+            })
+        );
+        
+        dict.insert("DTPlatformVersion".to_string(), "17.2".into());
+        assert_eq!(parse_info_dict(&dict).unwrap().platform_version.as_deref(), Some("17.2"));
+    }
+
+    #[test]
+    fn test_required_capabilities() {
+        let mut dict = base_dict();
+        assert!(parse_info_dict(&dict).unwrap().required_capabilities.is_empty());
+        
+        dict.insert(
+            "UIRequiredDeviceCapabilities".to_string(),
+            Value::Array(vec!["arm64".into(), "metal".into(), 7.into()]),
+        );
+        assert_eq!(parse_info_dict(&dict).unwrap().required_capabilities, vec!["arm64", "metal"]);
+        
+        let mut capabilities = plist::Dictionary::new();
+        capabilities.insert("gyroscope".to_string(), true.into());
+        capabilities.insert("telephony".to_string(), false.into());
+        capabilities.insert("arm64".to_string(), true.into());
+        dict.insert("UIRequiredDeviceCapabilities".to_string(), Value::Dictionary(capabilities));
+        assert_eq!(parse_info_dict(&dict).unwrap().required_capabilities, vec!["gyroscope", "arm64"]);
+    }
+
+    #[test]
+    fn test_usage_descriptions() {
+        let mut dict = base_dict();
+        assert!(parse_info_dict(&dict).unwrap().usage_descriptions.is_empty());
+        
+        dict.insert("NSCameraUsageDescription".to_string(), "Scan QR codes".into());
+        dict.insert("NSLocationWhenInUseUsageDescription".to_string(), "Find stores near you".into());
+        dict.insert("NSAppTransportSecurity".to_string(), Value::Dictionary(plist::Dictionary::new()));
+        dict.insert("UsageDescription".to_string(), "not a permission".into());
+        
+        let descriptions = parse_info_dict(&dict).unwrap().usage_descriptions;
+        assert_eq!(descriptions.len(), 2);
+        assert_eq!(descriptions["NSCameraUsageDescription"], "Scan QR codes");
+        assert_eq!(descriptions["NSLocationWhenInUseUsageDescription"], "Find stores near you");
+    }
+
+    #[test]
+    fn test_localization_settings() {
+        let mut dict = base_dict();
+        dict.insert("CFBundleAllowMixedLocalizations".to_string(), Value::Boolean(true));
+        dict.insert("CFBundleDevelopmentRegion".to_string(), Value::String("en".to_string()));
+        
+        let info = parse_info_dict(&dict).unwrap();
+        assert_eq!(info.allows_mixed_localizations, Some(true));
+        assert_eq!(info.development_region.as_deref(), Some("en"));
+        
+        let info = parse_info_dict(&base_dict()).unwrap();
+        assert_eq!(info.allows_mixed_localizations, None);
+        assert_eq!(info.development_region, None);
+    }
+
+    #[test]
+    fn test_accent_color_name() {
+        let mut dict = base_dict();
+        let mut launch = plist::Dictionary::new();
+        launch.insert("UIColorName".to_string(), Value::String("LaunchBackground".to_string()));
+        dict.insert("UILaunchScreen".to_string(), Value::Dictionary(launch));
+        assert_eq!(
+            parse_info_dict(&dict).unwrap().accent_color_name.as_deref(),
+            Some("LaunchBackground")
+        );
+        
+        dict.insert("NSAccentColorName".to_string(), Value::String("AccentColor".to_string()));
+        assert_eq!(
+            parse_info_dict(&dict).unwrap().accent_color_name.as_deref(),
+            Some("AccentColor")
+        );
+        assert_eq!(parse_info_dict(&base_dict()).unwrap().accent_color_name, None);
+    }
+
+    #[test]
+    fn test_plist_to_json() {
+        let mut dict = plist::Dictionary::new();
+        dict.insert("Version".to_string(), Value::Integer(3.into()));
+        dict.insert("Notes".to_string(), Value::Array(vec!["Fixed crash".into(), Value::Boolean(true)]));
+        dict.insert("Blob".to_string(), Value::Data(b"foobar".to_vec()));
+        dict.insert("Ratio".to_string(), Value::Real(f64::NAN));
+        
+        assert_eq!(
+            plist_to_json(&Value::Dictionary(dict)),
+            serde_json::json!({
+                "Version": 3,
+                "Notes": ["Fixed crash", true],
+                "Blob": "Zm9vYmFy",
+                "Ratio": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_extract_settings_keys() {
+        let specifier = |key: Option<&str>| {
+            let mut d = plist::Dictionary::new();
+            d.insert("Type".to_string(), Value::String("PSToggleSwitchSpecifier".to_string()));
+            if let Some(key) = key {
+                d.insert("Key".to_string(), Value::String(key.to_string()));
+            }
+            Value::Dictionary(d)
+        };
+        let mut root = plist::Dictionary::new();
+        root.insert(
+            "PreferenceSpecifiers".to_string(),
+            Value::Array(vec![specifier(None), specifier(Some("sync_enabled")), specifier(Some("theme"))]),
+        );
+        
+        assert_eq!(extract_settings_keys(&root), vec!["sync_enabled", "theme"]);
+    }
+
+    #[test]
+    fn test_duplicate_info_plist_uses_first_entry() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let first = plist_xml(info_plist("First", "com.example.first"));
+        let second = plist_xml(info_plist("Second", "com.example.second"));
+        let data = build_zip(&[
+            ("Payload/App.app/Info.plist", &first),
+            ("Payload/App.app/Info.plist", &second),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let info = extract_plist_info(&mut archive).unwrap();
+        assert_eq!(info.app_name, "First");
+    }
+
+    #[test]
+    fn test_localized_names() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("Demo", "com.example.demo"));
+        let mut english = plist::Dictionary::new();
+        english.insert("CFBundleDisplayName".to_string(), "Demo Pro".into());
+        let mut english_binary = Vec::new();
+        Value::Dictionary(english).to_writer_binary(&mut english_binary).unwrap();
+        let mut base = vec![0xFF, 0xFE];
+        base.extend("/* Localized */\n\"CFBundleName\" = \"Demo\";".encode_utf16().flat_map(u16::to_le_bytes));
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", &plist),
+            ("Payload/Demo.app/en.lproj/InfoPlist.strings", &english_binary),
+            ("Payload/Demo.app/Base.lproj/InfoPlist.strings", &base),
+            ("Payload/Demo.app/ja.lproj/InfoPlist.strings", "\"CFBundleDisplayName\" = \"デモ\";".as_bytes()),
+            ("Payload/Demo.app/fr.lproj/InfoPlist.strings", b"\"CFBundleDisplayName\" = ".as_slice()),
+            ("Payload/Demo.app/de.lproj/Localizable.strings", b"\"CFBundleDisplayName\" = \"Nein\";".as_slice()),
+            ("Payload/Demo.app/Frameworks/Kit.framework/es.lproj/InfoPlist.strings", b"\"CFBundleName\" = \"Kit\";".as_slice()),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let names = extract_plist_info(&mut archive).unwrap().localized_names;
+        assert_eq!(names.len(), 3);
+        assert_eq!(names["en"], "Demo Pro");
+        assert_eq!(names["Base"], "Demo");
+        assert_eq!(names["ja"], "デモ");
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", &plist)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        assert!(extract_plist_info(&mut archive).unwrap().localized_names.is_empty());
+    }
+
+    #[test]
+    fn test_main_app_plist_wins_over_extensions() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let main = plist_xml(info_plist("Demo", "com.example.demo"));
+        let extension = plist_xml(info_plist("Widget", "com.example.demo.widget"));
+        let watch = plist_xml(info_plist("Watch", "com.example.demo.watchkitapp"));
+        let data = build_zip(&[
+            ("Payload/Demo.app/PlugIns/Widget.appex/Info.plist", &extension),
+            ("Payload/Demo.app/Watch/Watch.app/Info.plist", &watch),
+            ("Payload/Demo.app/Info.plist", &main),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let info = extract_plist_info(&mut archive).unwrap();
+        assert_eq!(info.bundle_identifier, "com.example.demo");
+        assert_eq!(info.app_root, "Payload/Demo.app/");
+    }
+
+    #[test]
+    fn test_lenient_layout_without_app_suffix() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("MyApp", "com.example.myapp"));
+        let data = build_zip(&[
+            ("Payload/Docs/Info.plist", &plist),
+            ("Payload/MyApp/Info.plist", &plist),
+            ("Payload/MyApp/MyApp", b"\xcf\xfa\xed\xfe"),
+        ]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        assert!(matches!(extract_plist_info(&mut archive), Err(IpaError::InfoPlistNotFound)));
+        let info = extract_plist_info_with(&mut archive, true).unwrap();
+        assert_eq!(info.app_root, "Payload/MyApp/");
+        assert_eq!(info.layout, ContainerLayout::PayloadWithoutAppSuffix);
+    }
+
+    #[test]
+    fn test_root_app_layout() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("Tipa", "com.example.tipa"));
+        let data = build_zip(&[("Tipa.app/Info.plist", &plist)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        let info = extract_plist_info(&mut archive).unwrap();
+        assert_eq!(info.app_root, "Tipa.app/");
+        assert_eq!(info.layout, ContainerLayout::RootApp);
+        
+        // The standard layout still wins when both are present
+        let data = build_zip(&[("Tipa.app/Info.plist", &plist), ("Payload/Tipa.app/Info.plist", &plist)]);
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        assert_eq!(extract_plist_info(&mut archive).unwrap().layout, ContainerLayout::Payload);
+    }
+
+    #[test]
+    fn test_unsupported_info_plist_compression() {
+        use crate::test_support::{build_zip, info_plist, plist_xml};
+        
+        let plist = plist_xml(info_plist("App", "com.example.app"));
+        let mut data = build_zip(&[("Payload/App.app/Info.plist", &plist)]);
+        
+        // Rewrite the method in the local (offset 8) and central (offset 10) headers to LZMA
+        for (signature, offset) in [(b"PK\x03\x04", 8), (b"PK\x01\x02", 10)] {
+            let at = data.windows(4).position(|w| w == signature).unwrap() + offset;
+            data[at..at + 2].copy_from_slice(&14u16.to_le_bytes());
+        }
+        let mut archive = ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+        
+        match extract_plist_info(&mut archive) {
+            Err(IpaError::InvalidIpa(msg)) => {
+                assert_eq!(msg, "unsupported compression for Info.plist: lzma")
+            }
+            other => panic!("unexpected result: {:?}", other.map(|i| i.app_name)),
+        }
+    }
+
+    fn base_dict() -> plist::Dictionary {
+        let mut dict = plist::Dictionary::new();
+        dict.insert("CFBundleName".to_string(), Value::String("App".to_string()));
+        dict.insert("CFBundleShortVersionString".to_string(), Value::String("1.0".to_string()));
+        dict.insert("CFBundleIdentifier".to_string(), Value::String("com.example.app".to_string()));
+        dict
+    }
 }
@@ -1,12 +1,15 @@
+use crate::icon_extractor::IconFormat;
 use flate2::Decompress;
-use std::io::{Cursor, Read};
+use std::io::Cursor;
 
 /// Detects if a PNG file uses Apple's CgBI format
+/// Only the signature and the first chunk's type are looked at, so 16 bytes
+/// are enough; `normalize_cgbi_png` separately wants 20 before it reads chunks
 #[inline]
 pub fn is_cgbi_png(data: &[u8]) -> bool {
     const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
     
-    if data.len() < 20 || &data[0..8] != PNG_HEADER {
+    if data.len() < 16 || &data[0..8] != PNG_HEADER {
         return false;
     }
     
@@ -16,7 +19,9 @@ pub fn is_cgbi_png(data: &[u8]) -> bool {
 
 /// Normalizes Apple's CgBI PNG format to standard PNG
 /// This is a highly optimized version that processes the PNG in-place where possible
-pub fn normalize_cgbi_png(data: &[u8]) -> crate::error::Result<Vec<u8>> {
+/// The inflated image data may not exceed `limits.max_alloc`. A CgBI file whose
+/// chunks run out before IEND, or that lacks IHDR or IDAT, is an error
+pub fn normalize_cgbi_png(data: &[u8], limits: DecodeLimits) -> crate::error::Result<Vec<u8>> {
     const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
     
     if data.len() < 20 || &data[0..8] != PNG_HEADER {
@@ -35,37 +40,43 @@ pub fn normalize_cgbi_png(data: &[u8]) -> crate::error::Result<Vec<u8>> {
     
     let mut pos = 8;
     let mut idat_data = Vec::new();
-    let mut width = 0u32;
-    let mut height = 0u32;
+    // Where the first IDAT sat in the output; the merged IDAT goes back there so
+    // ancillary chunks keep their side of it
+    let mut idat_pos = None;
+    let mut header = None;
+    // Saving whatever was copied before the data ran out would give a broken icon
+    let truncated = || crate::error::IpaError::PngNormalization("CgBI PNG ends before IEND".to_string());
     
     // Parse chunks
-    while pos < data.len() {
+    loop {
         if pos + 12 > data.len() {
-            break;
+            return Err(truncated());
         }
         
         let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]);
         let chunk_type = &data[pos + 4..pos + 8];
         let chunk_data_start = pos + 8;
-        let chunk_data_end = chunk_data_start + length as usize;
+        // A forged length can overflow usize on 32-bit targets; treat it as truncation
+        let Some(chunk_data_end) = chunk_data_start.checked_add(length as usize) else {
+            return Err(truncated());
+        };
         
-        if chunk_data_end + 4 > data.len() {
-            break;
+        if chunk_data_end.checked_add(4).is_none_or(|end| end > data.len()) {
+            return Err(truncated());
         }
         
         let chunk_data = &data[chunk_data_start..chunk_data_end];
         
         match chunk_type {
             b"IHDR" => {
-                // Extract dimensions
-                width = u32::from_be_bytes([chunk_data[0], chunk_data[1], chunk_data[2], chunk_data[3]]);
-                height = u32::from_be_bytes([chunk_data[4], chunk_data[5], chunk_data[6], chunk_data[7]]);
+                header = Some(ImageHeader::parse(chunk_data)?);
                 
                 // Write IHDR chunk
                 write_chunk(&mut result, b"IHDR", chunk_data);
             }
             b"IDAT" => {
                 // Accumulate IDAT data
+                idat_pos.get_or_insert(result.len());
                 idat_data.extend_from_slice(chunk_data);
             }
             b"CgBI" => {
@@ -73,14 +84,18 @@ pub fn normalize_cgbi_png(data: &[u8]) -> crate::error::Result<Vec<u8>> {
             }
             b"IEND" => {
                 // Process accumulated IDAT data
-                if !idat_data.is_empty() && width > 0 && height > 0 {
-                    let normalized = normalize_idat(&idat_data, width, height)?;
-                    write_chunk(&mut result, b"IDAT", &normalized);
-                }
+                let (Some(idat_pos), Some(header)) = (idat_pos, &header) else {
+                    return Err(crate::error::IpaError::PngNormalization(
+                        "CgBI PNG has no IHDR or IDAT chunk".to_string()
+                    ));
+                };
+                let mut idat = Vec::new();
+                write_chunk(&mut idat, b"IDAT", &normalize_idat(&idat_data, header, limits)?);
+                result.splice(idat_pos..idat_pos, idat);
                 
                 // Write IEND
                 write_chunk(&mut result, b"IEND", &[]);
-                break;
+                return Ok(result);
             }
             _ => {
                 // Copy other chunks as-is
@@ -90,8 +105,192 @@ pub fn normalize_cgbi_png(data: &[u8]) -> crate::error::Result<Vec<u8>> {
         
         pos = chunk_data_end + 4; // Move to next chunk (skip CRC)
     }
+}
+
+/// Reads width and height from the IHDR chunk, skipping a leading CgBI chunk
+/// Only the start of the file is needed; normalization doesn't change dimensions
+pub fn png_dimensions(header: &[u8]) -> crate::error::Result<(u32, u32)> {
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let invalid = || crate::error::IpaError::PngNormalization("Missing IHDR chunk".to_string());
+    
+    if header.len() < 8 || &header[0..8] != PNG_HEADER {
+        return Err(crate::error::IpaError::PngNormalization("Invalid PNG header".to_string()));
+    }
+    
+    let mut pos = 8;
+    if is_cgbi_png(header) {
+        let length = u32::from_be_bytes([header[8], header[9], header[10], header[11]]) as usize;
+        pos = length.checked_add(20).ok_or_else(invalid)?;
+    }
+    
+    let ihdr = header.get(pos..pos.checked_add(16).ok_or_else(invalid)?).ok_or_else(invalid)?;
+    if &ihdr[4..8] != b"IHDR" {
+        return Err(invalid());
+    }
+    Ok((
+        u32::from_be_bytes([ihdr[8], ihdr[9], ihdr[10], ihdr[11]]),
+        u32::from_be_bytes([ihdr[12], ihdr[13], ihdr[14], ihdr[15]]),
+    ))
+}
+
+/// Re-walks a PNG's chunks and checks each stored CRC against a recomputed one
+/// A cheap structural self-check of `normalize_cgbi_png` output; needs no decoder
+pub fn verify_chunk_crcs(data: &[u8]) -> crate::error::Result<()> {
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n";
+    let fail = |msg: String| Err(crate::error::IpaError::PngNormalization(msg));
+    
+    if data.len() < 8 || &data[0..8] != PNG_HEADER {
+        return fail("Invalid PNG header".to_string());
+    }
+    
+    let mut pos = 8;
+    while pos + 12 <= data.len() {
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let chunk_type = &data[pos + 4..pos + 8];
+        let Some(crc_start) = (pos + 8).checked_add(length) else {
+            break;
+        };
+        
+        if crc_start.checked_add(4).is_none_or(|end| end > data.len()) {
+            break;
+        }
+        
+        let stored = u32::from_be_bytes([data[crc_start], data[crc_start + 1], data[crc_start + 2], data[crc_start + 3]]);
+        if crc32(chunk_type, &data[pos + 8..crc_start]) != stored {
+            return fail(format!(
+                "CRC mismatch in {} chunk at offset {}",
+                String::from_utf8_lossy(chunk_type),
+                pos
+            ));
+        }
+        
+        if chunk_type == b"IEND" {
+            return Ok(());
+        }
+        pos = crc_start + 4;
+    }
+    
+    fail(format!("Truncated chunk at offset {}", pos))
+}
+
+/// Caps on what a PNG may claim before it is decoded, so a forged header
+/// can't make the decoder allocate gigabytes (a decompression bomb)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Upper bound on the decoder's buffers, in bytes
+    pub max_alloc: u64,
+}
+
+impl Default for DecodeLimits {
+    /// Generous for icons (1024x1024 is the largest Apple asks for)
+    fn default() -> Self {
+        Self {
+            max_width: 4096,
+            max_height: 4096,
+            max_alloc: 128 * 1024 * 1024,
+        }
+    }
+}
+
+/// Decodes a PNG, failing with `IpaError::Image` (a limits error) when its
+/// declared size exceeds `limits`; every decode in the crate goes through here
+pub fn decode_png(data: &[u8], limits: DecodeLimits) -> crate::error::Result<image::DynamicImage> {
+    let mut image_limits = image::io::Limits::default();
+    image_limits.max_image_width = Some(limits.max_width);
+    image_limits.max_image_height = Some(limits.max_height);
+    image_limits.max_alloc = Some(limits.max_alloc);
+    
+    let mut reader = image::io::Reader::with_format(Cursor::new(data), image::ImageFormat::Png);
+    reader.limits(image_limits);
+    Ok(reader.decode()?)
+}
+
+/// Downscales a PNG to fit within a `size` x `size` box, keeping its aspect ratio
+/// (Lanczos3). An image that already fits is returned unchanged, never enlarged
+pub fn resize_png(data: &[u8], size: u32, limits: DecodeLimits) -> crate::error::Result<Vec<u8>> {
+    let image = decode_png(data, limits)?;
+    let size = size.max(1);
+    
+    if image.width().max(image.height()) <= size {
+        return Ok(data.to_vec());
+    }
+    
+    let mut output = Vec::new();
+    image
+        .resize(size, size, image::imageops::FilterType::Lanczos3)
+        .write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)?;
+    
+    Ok(output)
+}
+
+/// Re-encodes a PNG icon as `format`; PNG input is returned unchanged
+/// JPEG has no alpha channel, so translucent pixels are composited over white.
+/// WebP output is lossless and keeps the alpha channel
+pub fn encode_icon(data: &[u8], format: IconFormat, limits: DecodeLimits) -> crate::error::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    
+    match format {
+        IconFormat::Png => return Ok(data.to_vec()),
+        IconFormat::Jpeg => {
+            let rgba = decode_png(data, limits)?.to_rgba8();
+            let over_white = |c: u8, alpha: u8| ((c as u32 * alpha as u32 + 255 * (255 - alpha as u32) + 127) / 255) as u8;
+            let rgb = image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+                let [r, g, b, a] = rgba.get_pixel(x, y).0;
+                image::Rgb([over_white(r, a), over_white(g, a), over_white(b, a)])
+            });
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, 90).encode_image(&rgb)?;
+        }
+        IconFormat::Webp => {
+            let rgba = decode_png(data, limits)?.to_rgba8();
+            image::codecs::webp::WebPEncoder::new_lossless(&mut output).encode(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                image::ColorType::Rgba8,
+            )?;
+        }
+    }
+    
+    Ok(output)
+}
+
+/// Rewrites a fully-opaque RGBA PNG as RGB (color type 2)
+/// Images with any transparency, or without an alpha channel, are returned unchanged
+pub fn flatten_opaque_png(data: &[u8], limits: DecodeLimits) -> crate::error::Result<Vec<u8>> {
+    let image = decode_png(data, limits)?;
+    
+    if !image.color().has_alpha() || is_translucent(&image) {
+        return Ok(data.to_vec());
+    }
+    
+    let mut output = Vec::with_capacity(data.len());
+    image::DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut Cursor::new(&mut output), image::ImageOutputFormat::Png)?;
     
-    Ok(result)
+    Ok(output)
+}
+
+/// Whether any pixel of a decoded PNG has alpha below 255
+/// Images without an alpha channel are opaque
+pub fn has_transparency(data: &[u8], limits: DecodeLimits) -> crate::error::Result<bool> {
+    let image = decode_png(data, limits)?;
+    Ok(image.color().has_alpha() && is_translucent(&image))
+}
+
+// Compare at the decoded bit depth: converting 16-bit alpha down to 8 bits
+// rounds anything from 65408 up to fully opaque.
+fn is_translucent(image: &image::DynamicImage) -> bool {
+    use image::DynamicImage;
+    match image {
+        DynamicImage::ImageLumaA8(i) => i.pixels().any(|p| p[1] != u8::MAX),
+        DynamicImage::ImageRgba8(i) => i.pixels().any(|p| p[3] != u8::MAX),
+        DynamicImage::ImageLumaA16(i) => i.pixels().any(|p| p[1] != u16::MAX),
+        DynamicImage::ImageRgba16(i) => i.pixels().any(|p| p[3] != u16::MAX),
+        DynamicImage::ImageRgba32F(i) => i.pixels().any(|p| p[3] < 1.0),
+        _ => image.to_rgba8().pixels().any(|p| p[3] != u8::MAX),
+    }
 }
 
 #[inline]
@@ -106,64 +305,124 @@ fn write_chunk(output: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
     output.extend_from_slice(&crc.to_be_bytes());
 }
 
+/// PNG chunk CRC (CRC-32/IEEE) over the type and data
 #[inline]
 fn crc32(chunk_type: &[u8], data: &[u8]) -> u32 {
-    let mut crc = 0xFFFFFFFFu32;
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// The IHDR fields that decide how IDAT scanlines are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlace: u8,
+}
+
+impl ImageHeader {
+    fn parse(ihdr: &[u8]) -> crate::error::Result<Self> {
+        if ihdr.len() < 13 {
+            return Err(crate::error::IpaError::PngNormalization("IHDR chunk is too short".to_string()));
+        }
+        let header = ImageHeader {
+            width: u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]),
+            height: u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]),
+            bit_depth: ihdr[8],
+            color_type: ihdr[9],
+            interlace: ihdr[12],
+        };
+        
+        if header.interlace > 1 {
+            return Err(crate::error::IpaError::PngNormalization(format!(
+                "Unsupported interlace method {}",
+                header.interlace
+            )));
+        }
+        
+        let valid_depth = match header.color_type {
+            0 => matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(header.bit_depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(header.bit_depth, 8 | 16),
+            _ => false,
+        };
+        if !valid_depth {
+            return Err(crate::error::IpaError::PngNormalization(format!(
+                "Unsupported color type {} with bit depth {}",
+                header.color_type, header.bit_depth
+            )));
+        }
+        Ok(header)
+    }
     
-    for &byte in chunk_type.iter().chain(data.iter()) {
-        crc ^= byte as u32;
-        for _ in 0..8 {
-            crc = if crc & 1 != 0 {
-                (crc >> 1) ^ 0xEDB88320
-            } else {
-                crc >> 1
-            };
+    /// Samples per pixel: gray, RGB, palette index, gray + alpha, RGBA
+    fn channels(&self) -> u64 {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
         }
     }
     
-    !crc
-}
-
-/// Decompresses and swaps RGB channels in IDAT data
-fn normalize_idat(compressed: &[u8], width: u32, height: u32) -> crate::error::Result<Vec<u8>> {
-    // Decompress using raw deflate (window bits = -15)
-    let buf_size = (width * height * 4 + height) as usize;
-    let mut decompressed = vec![0u8; buf_size];
+    /// Byte distance the filters look back, at least 1 for sub-byte depths
+    fn filter_bpp(&self) -> usize {
+        ((self.channels() * u64::from(self.bit_depth)).div_ceil(8)) as usize
+    }
     
-    let mut decompressor = Decompress::new(false);
-    let mut cursor = Cursor::new(compressed);
-    let mut temp_buf = vec![0u8; 8192];
-    let mut output_pos = 0;
+    /// Bytes in one scanline of `width` pixels, excluding the filter byte
+    fn row_bytes(&self, width: u32) -> u64 {
+        (u64::from(width) * self.channels() * u64::from(self.bit_depth)).div_ceil(8)
+    }
     
-    loop {
-        let bytes_read = cursor.read(&mut temp_buf).map_err(|e| {
-            crate::error::IpaError::PngNormalization(format!("Decompression read error: {}", e))
-        })?;
-        
-        if bytes_read == 0 {
-            break;
-        }
+    /// (width, height) of each sub-image stored in IDAT, in order: the whole
+    /// image, or the non-empty Adam7 passes of an interlaced one
+    fn passes(&self) -> Vec<(u32, u32)> {
+        // (x start, y start, x step, y step) of each Adam7 pass
+        const ADAM7: [(u32, u32, u32, u32); 7] =
+            [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
         
-        let before_in = decompressor.total_in();
-        let before_out = decompressor.total_out();
-        
-        decompressor
-            .decompress(&temp_buf[..bytes_read], &mut decompressed[output_pos..], flate2::FlushDecompress::None)
-            .map_err(|e| crate::error::IpaError::PngNormalization(format!("Decompression error: {}", e)))?;
-        
-        let produced = (decompressor.total_out() - before_out) as usize;
-        output_pos += produced;
-        
-        if decompressor.total_in() == before_in && bytes_read > 0 {
-            break;
+        if self.interlace == 0 {
+            return vec![(self.width, self.height)];
         }
+        let span = |size: u32, start: u32, step: u32| if size > start { (size - start).div_ceil(step) } else { 0 };
+        ADAM7
+            .iter()
+            .map(|&(x, y, dx, dy)| (span(self.width, x, dx), span(self.height, y, dy)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+            .collect()
     }
     
-    decompressed.truncate(output_pos);
+    /// Total inflated IDAT size: every pass's rows plus their filter bytes
+    fn data_size(&self) -> u64 {
+        self.passes()
+            .iter()
+            .map(|&(width, height)| (self.row_bytes(width) + 1) * u64::from(height))
+            .sum()
+    }
+}
+
+/// Decompresses IDAT data and restores standard scanlines (see `swap_rgb_channels_optimized`)
+/// A forged IHDR could ask for gigabytes, so its size is checked against
+/// `limits.max_alloc` and the buffer only grows as the data actually inflates
+fn normalize_idat(compressed: &[u8], header: &ImageHeader, limits: DecodeLimits) -> crate::error::Result<Vec<u8>> {
+    // Decompress using raw deflate (window bits = -15)
+    let buf_size = header.data_size();
+    if buf_size > limits.max_alloc {
+        return Err(crate::error::IpaError::PngNormalization(format!(
+            "{}x{} image is too large to normalize",
+            header.width, header.height
+        )));
+    }
+    let mut decompressed = Vec::new();
+    inflate_raw(compressed, buf_size as usize, &mut decompressed)?;
     
-    // Swap RGB channels (BGRA -> RGBA)
-    // Ultra-optimized version with chunked processing for SIMD-friendly operations
-    swap_rgb_channels_optimized(&mut decompressed, width, height);
+    // BGRA -> RGBA and un-premultiply, rewriting every row unfiltered
+    swap_rgb_channels_optimized(&mut decompressed, header)?;
     
     // Recompress with fast compression for speed
     let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
@@ -175,48 +434,177 @@ fn normalize_idat(compressed: &[u8], width: u32, height: u32) -> crate::error::R
     })
 }
 
-/// Ultra-fast RGB channel swapping optimized for modern CPUs
-#[inline(always)]
-fn swap_rgb_channels_optimized(data: &mut [u8], width: u32, height: u32) {
-    let width = width as usize;
-    let height = height as usize;
-    let mut pos = 0;
+/// Inflates a raw deflate stream into `output` until the stream ends or
+/// `limit` bytes have come out, feeding whatever input the previous call left
+/// unconsumed. `output` starts small and doubles as the data arrives, never
+/// past `limit`; anything beyond what IHDR describes isn't image data.
+/// A stream cut short stops at the last byte it produced; the scanline pass
+/// then reports the missing rows
+fn inflate_raw(compressed: &[u8], limit: usize, output: &mut Vec<u8>) -> crate::error::Result<()> {
+    let mut decompressor = Decompress::new(false);
     
-    // Process in chunks for better cache locality and SIMD potential
-    for _ in 0..height {
-        if pos >= data.len() {
-            break;
+    loop {
+        if output.len() >= limit {
+            output.truncate(limit);
+            return Ok(());
         }
-        pos += 1; // Skip filter byte
-        
-        let row_start = pos;
-        let row_end = (pos + width * 4).min(data.len());
-        
-        // Process 4 pixels at a time for better performance
-        let mut i = row_start;
-        while i + 15 < row_end {
-            // Swap 4 pixels in parallel (SIMD-friendly)
-            unsafe {
-                let ptr = data.as_mut_ptr().add(i);
-                // Pixel 1
-                std::ptr::swap(ptr, ptr.add(2));
-                // Pixel 2
-                std::ptr::swap(ptr.add(4), ptr.add(6));
-                // Pixel 3
-                std::ptr::swap(ptr.add(8), ptr.add(10));
-                // Pixel 4
-                std::ptr::swap(ptr.add(12), ptr.add(14));
-            }
-            i += 16;
+        if output.len() == output.capacity() {
+            let room = limit - output.len();
+            output.reserve_exact(output.capacity().max(8192).min(room));
+        }
+        
+        let (before_in, before_out) = (decompressor.total_in(), decompressor.total_out());
+        let status = decompressor
+            .decompress_vec(&compressed[before_in as usize..], output, flate2::FlushDecompress::None)
+            .map_err(|e| crate::error::IpaError::PngNormalization(format!("Decompression error: {}", e)))?;
+        
+        if status == flate2::Status::StreamEnd {
+            return Ok(());
+        }
+        // No progress with output space to spare: the input ran out mid-stream
+        if decompressor.total_in() == before_in
+            && decompressor.total_out() == before_out
+            && output.len() < output.capacity()
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Restores standard scanlines from CgBI ones in a single pass per row:
+/// undoes the row's PNG filter, swaps BGR(A) to RGB(A), un-premultiplies alpha
+/// for RGBA and gray + alpha and marks the row unfiltered. Filters predict from
+/// neighbouring premultiplied bytes, so they have to be undone before the channel
+/// values change. Fully transparent pixels come out as transparent black. Plain
+/// grayscale and palette images have no RGB triple or alpha and only get unfiltered.
+/// Adam7 images are handled pass by pass, each pass being its own small image.
+fn swap_rgb_channels_optimized(data: &mut [u8], header: &ImageHeader) -> crate::error::Result<()> {
+    let mut offset = 0;
+    
+    for (width, height) in header.passes() {
+        let size = ((header.row_bytes(width) + 1) * u64::from(height)) as usize;
+        let pass = data
+            .get_mut(offset..offset + size)
+            .ok_or_else(|| crate::error::IpaError::PngNormalization("Truncated image data".to_string()))?;
+        restore_scanlines(pass, header, width, height)?;
+        offset += size;
+    }
+    
+    Ok(())
+}
+
+/// Restores the `height` rows of one (sub-)image `width` pixels wide
+fn restore_scanlines(data: &mut [u8], header: &ImageHeader, width: u32, height: u32) -> crate::error::Result<()> {
+    let bpp = header.filter_bpp();
+    let row_len = header.row_bytes(width) as usize;
+    // Previous row as reconstructed but not yet converted, for Up/Average/Paeth
+    let mut prev = vec![0u8; row_len];
+    
+    for row in data.chunks_mut(row_len + 1).take(height as usize) {
+        if row.len() <= row_len {
+            return Err(crate::error::IpaError::PngNormalization("Truncated image data".to_string()));
         }
+        let (filter, pixels) = row.split_at_mut(1);
         
-        // Handle remaining pixels
-        while i + 3 < row_end {
-            data.swap(i, i + 2);
-            i += 4;
+        match filter[0] {
+            0 => {}
+            // Sub
+            1 => {
+                for i in bpp..row_len {
+                    pixels[i] = pixels[i].wrapping_add(pixels[i - bpp]);
+                }
+            }
+            // Up
+            2 => {
+                for i in 0..row_len {
+                    pixels[i] = pixels[i].wrapping_add(prev[i]);
+                }
+            }
+            // Average
+            3 => {
+                for i in 0..row_len {
+                    let left = if i >= bpp { pixels[i - bpp] } else { 0 };
+                    pixels[i] = pixels[i].wrapping_add(((u16::from(left) + u16::from(prev[i])) / 2) as u8);
+                }
+            }
+            // Paeth
+            4 => {
+                for i in 0..row_len {
+                    let (left, up_left) = if i >= bpp { (pixels[i - bpp], prev[i - bpp]) } else { (0, 0) };
+                    pixels[i] = pixels[i].wrapping_add(paeth(left, prev[i], up_left));
+                }
+            }
+            other => {
+                return Err(crate::error::IpaError::PngNormalization(format!("Unknown filter type {}", other)));
+            }
         }
+        prev.copy_from_slice(pixels);
+        filter[0] = 0;
         
-        pos = row_end;
+        match (header.color_type, header.bit_depth) {
+            (6, 8) => pixels.chunks_exact_mut(4).for_each(|pixel| {
+                let alpha = u32::from(pixel[3]);
+                let (b, g, r) = (pixel[0], pixel[1], pixel[2]);
+                for (i, channel) in [r, g, b].into_iter().enumerate() {
+                    pixel[i] = unpremultiply(u32::from(channel), alpha, 255) as u8;
+                }
+            }),
+            (6, 16) => pixels.chunks_exact_mut(8).for_each(|pixel| {
+                let sample = |i: usize| u32::from(u16::from_be_bytes([pixel[i * 2], pixel[i * 2 + 1]]));
+                let alpha = sample(3);
+                let (b, g, r) = (sample(0), sample(1), sample(2));
+                for (i, channel) in [r, g, b].into_iter().enumerate() {
+                    let value = unpremultiply(channel, alpha, 65535) as u16;
+                    pixel[i * 2..i * 2 + 2].copy_from_slice(&value.to_be_bytes());
+                }
+            }),
+            (4, 8) => pixels.chunks_exact_mut(2).for_each(|pixel| {
+                pixel[0] = unpremultiply(u32::from(pixel[0]), u32::from(pixel[1]), 255) as u8;
+            }),
+            (4, 16) => pixels.chunks_exact_mut(4).for_each(|pixel| {
+                let gray = u32::from(u16::from_be_bytes([pixel[0], pixel[1]]));
+                let alpha = u32::from(u16::from_be_bytes([pixel[2], pixel[3]]));
+                pixel[..2].copy_from_slice(&(unpremultiply(gray, alpha, 65535) as u16).to_be_bytes());
+            }),
+            (2, 8) => pixels.chunks_exact_mut(3).for_each(|pixel| pixel.swap(0, 2)),
+            (2, 16) => pixels.chunks_exact_mut(6).for_each(|pixel| {
+                pixel.swap(0, 4);
+                pixel.swap(1, 5);
+            }),
+            _ => {}
+        }
+    }
+    
+    Ok(())
+}
+
+/// Divides a premultiplied channel by alpha, rounding; zero alpha gives zero
+/// `max` is the channel's full scale (255 or 65535). Shared with the asset
+/// catalog decoder, whose ARGB renditions are premultiplied the same way
+#[inline]
+pub(crate) fn unpremultiply(channel: u32, alpha: u32, max: u32) -> u32 {
+    match alpha {
+        0 => 0,
+        a if a == max => channel,
+        a => ((channel * max + a / 2) / a).min(max),
+    }
+}
+
+#[inline]
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let (dl, du, dul) = (
+        (estimate - i16::from(left)).abs(),
+        (estimate - i16::from(up)).abs(),
+        (estimate - i16::from(up_left)).abs(),
+    );
+    
+    if dl <= du && dl <= dul {
+        left
+    } else if du <= dul {
+        up
+    } else {
+        up_left
     }
 }
 
@@ -224,6 +612,63 @@ fn swap_rgb_channels_optimized(data: &mut [u8], width: u32, height: u32) {
 mod tests {
     use super::*;
 
+    /// A CgBI PNG with the given IHDR fields and already-filtered scanlines
+    fn cgbi_png(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8, scanlines: &[u8]) -> Vec<u8> {
+        let mut deflated = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut deflated, scanlines).unwrap();
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"CgBI", &[0x50, 0x00, 0x20, 0x06]);
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        write_chunk(&mut png, b"IDAT", &deflated.finish().unwrap());
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn test_normalize_large_multi_block_idat() {
+        // Noise barely compresses, so the IDAT spans many deflate blocks and input feeds
+        let (width, height) = (300u32, 200u32);
+        let mut seed = 0x2545F491u32;
+        let mut noise = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as u8
+        };
+        let rows: Vec<Vec<[u8; 4]>> = (0..height)
+            .map(|_| (0..width).map(|_| [noise(), noise(), noise(), 255]).collect())
+            .collect();
+        // Unfiltered BGRA rows in, RGBA pixels out
+        let scanlines: Vec<u8> = rows.iter().flat_map(|row| std::iter::once(0).chain(row.concat())).collect();
+        let expected: Vec<[u8; 4]> = rows.concat().iter().map(|&[b, g, r, a]| [r, g, b, a]).collect();
+        // Trailing bytes past the image grow the buffer but are dropped
+        let mut padded = scanlines.clone();
+        padded.extend(std::iter::repeat_n(7, 100_000));
+        
+        for data in [&scanlines, &padded] {
+            let cgbi = cgbi_png(width, height, 8, 6, 0, data);
+            assert!(cgbi.len() > 150_000);
+            let normalized = normalize_cgbi_png(&cgbi, DecodeLimits::default()).unwrap();
+            let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+            assert!(decoded.pixels().map(|p| p.0).eq(expected.iter().copied()));
+        }
+        
+        let cgbi = cgbi_png(width, height, 8, 6, 0, &scanlines[..scanlines.len() / 2]);
+        assert!(normalize_cgbi_png(&cgbi, DecodeLimits::default()).is_err());
+        
+        // IHDR asks for more than max_alloc: refused before anything is inflated
+        let cgbi = cgbi_png(width, height, 8, 6, 0, &scanlines);
+        let tight = DecodeLimits { max_alloc: scanlines.len() as u64 - 1, ..Default::default() };
+        assert!(normalize_cgbi_png(&cgbi, tight).is_err());
+        let exact = DecodeLimits { max_alloc: scanlines.len() as u64, ..Default::default() };
+        assert!(normalize_cgbi_png(&cgbi, exact).is_ok());
+    }
+
     #[test]
     fn test_is_cgbi_png() {
         let cgbi_png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x04CgBI";
@@ -231,5 +676,330 @@ mod tests {
         
         let normal_png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x04IHDR";
         assert!(!is_cgbi_png(normal_png));
+        
+        // Cut off inside the chunk type
+        assert!(!is_cgbi_png(&cgbi_png[..15]));
+        // Detected, but too short to normalize
+        assert!(normalize_cgbi_png(cgbi_png, DecodeLimits::default()).is_err());
+    }
+
+    #[test]
+    fn test_png_dimensions_skips_cgbi() {
+        let png = crate::test_support::rgba_png(7, 3, [0, 0, 0, 255]);
+        assert_eq!(png_dimensions(&png).unwrap(), (7, 3));
+        
+        let mut cgbi = png[..8].to_vec();
+        write_chunk(&mut cgbi, b"CgBI", &[0x50, 0x00, 0x20, 0x06]);
+        cgbi.extend_from_slice(&png[8..]);
+        assert_eq!(png_dimensions(&cgbi).unwrap(), (7, 3));
+        
+        // A forged CgBI length points past the end instead of wrapping around
+        cgbi[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(png_dimensions(&cgbi).is_err());
+        assert!(verify_chunk_crcs(&cgbi).is_err());
+        assert!(normalize_cgbi_png(&cgbi, DecodeLimits::default()).is_err());
+    }
+
+    #[test]
+    fn test_normalize_rejects_incomplete_cgbi() {
+        let cgbi = cgbi_png(1, 1, 8, 6, 0, &[0, 0, 0, 255, 255]);
+        assert!(normalize_cgbi_png(&cgbi, DecodeLimits::default()).is_ok());
+        
+        // Cut off inside IDAT, and cut off just before IEND
+        for end in [cgbi.len() - 20, cgbi.len() - 12] {
+            let err = normalize_cgbi_png(&cgbi[..end], DecodeLimits::default()).unwrap_err();
+            assert!(err.to_string().contains("ends before IEND"), "{}", err);
+        }
+        
+        // Signature, CgBI and IEND with nothing to normalize in between
+        let mut empty = cgbi[..24].to_vec();
+        write_chunk(&mut empty, b"IEND", &[]);
+        let err = normalize_cgbi_png(&empty, DecodeLimits::default()).unwrap_err();
+        assert!(err.to_string().contains("no IHDR or IDAT"), "{}", err);
+    }
+
+    #[test]
+    fn test_normalize_keeps_ancillary_chunk_order() {
+        let cgbi = cgbi_png(1, 1, 8, 6, 0, &[0, 0, 0, 255, 255]);
+        // pHYs right after IHDR (8 signature + 16 CgBI + 25 IHDR), tEXt just before IEND
+        let mut png = cgbi[..49].to_vec();
+        write_chunk(&mut png, b"pHYs", &[0, 0, 0x0b, 0x13, 0, 0, 0x0b, 0x13, 1]);
+        png.extend_from_slice(&cgbi[49..cgbi.len() - 12]);
+        write_chunk(&mut png, b"tEXt", b"Comment\0trailing");
+        png.extend_from_slice(&cgbi[cgbi.len() - 12..]);
+        
+        let normalized = normalize_cgbi_png(&png, DecodeLimits::default()).unwrap();
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos + 12 <= normalized.len() {
+            let length = u32::from_be_bytes(normalized[pos..pos + 4].try_into().unwrap()) as usize;
+            chunks.push(String::from_utf8_lossy(&normalized[pos + 4..pos + 8]).into_owned());
+            pos += 12 + length;
+        }
+        assert_eq!(chunks, ["IHDR", "pHYs", "IDAT", "tEXt", "IEND"]);
+        verify_chunk_crcs(&normalized).unwrap();
+        
+        let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_normalize_unpremultiplies_alpha() {
+        // Two rows of premultiplied BGRA: half-transparent red, then a fully
+        // transparent pixel with leftover channel bytes. The first row is
+        // Sub-filtered and the second Up-filtered, as pngcrush may write them
+        let rows: [[u8; 8]; 2] = [[0, 0, 128, 128, 10, 20, 30, 0], [0, 64, 0, 64, 0, 0, 0, 255]];
+        let mut filtered = vec![1];
+        filtered.extend_from_slice(&rows[0][..4]);
+        filtered.extend((4..8).map(|i| rows[0][i].wrapping_sub(rows[0][i - 4])));
+        filtered.push(2);
+        filtered.extend((0..8).map(|i| rows[1][i].wrapping_sub(rows[0][i])));
+        
+        let cgbi = cgbi_png(2, 2, 8, 6, 0, &filtered);
+        let normalized = normalize_cgbi_png(&cgbi, DecodeLimits::default()).unwrap();
+        let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [255, 0, 0, 128]);
+        assert_eq!(decoded.get_pixel(1, 0).0, [0, 0, 0, 0]);
+        assert_eq!(decoded.get_pixel(0, 1).0, [0, 255, 0, 64]);
+        assert_eq!(decoded.get_pixel(1, 1).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_normalize_matches_fixture_pixels() {
+        // See tests/fixtures/cgbi/generate.py for how the pair was made; every
+        // alpha value from 0 to 255 is covered. Pixels are compared, not bytes
+        let cgbi = include_bytes!("../tests/fixtures/cgbi/icon.png");
+        let reference = include_bytes!("../tests/fixtures/cgbi/icon.expected.png");
+        assert!(is_cgbi_png(cgbi));
+        
+        let normalized = normalize_cgbi_png(cgbi, DecodeLimits::default()).unwrap();
+        verify_chunk_crcs(&normalized).unwrap();
+        let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+        let expected = image::load_from_memory(reference).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), expected.dimensions());
+        assert!(decoded.as_raw() == expected.as_raw(), "decoded pixels differ from the fixture");
+    }
+
+    #[test]
+    fn test_normalize_every_filter_type() {
+        // Premultiplied BGRA rows, two pixels each, filtered with None, Sub, Up,
+        // Average and Paeth in turn, like the mixed filters Xcode's pngcrush writes
+        let rows: [[u8; 8]; 5] = [
+            [10, 20, 30, 255, 0, 0, 0, 0],
+            [40, 50, 60, 255, 64, 32, 16, 128],
+            [1, 2, 3, 255, 200, 100, 50, 255],
+            [9, 9, 9, 9, 0, 0, 0, 255],
+            [255, 0, 0, 255, 3, 6, 9, 12],
+        ];
+        let mut filtered = Vec::new();
+        for (filter, row) in rows.iter().enumerate() {
+            let above = if filter == 0 { [0; 8] } else { rows[filter - 1] };
+            filtered.push(filter as u8);
+            for i in 0..8 {
+                let (left, up_left) = if i < 4 { (0, 0) } else { (row[i - 4], above[i - 4]) };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => above[i],
+                    3 => ((u16::from(left) + u16::from(above[i])) / 2) as u8,
+                    _ => paeth(left, above[i], up_left),
+                };
+                filtered.push(row[i].wrapping_sub(predicted));
+            }
+        }
+        
+        let normalized = normalize_cgbi_png(&cgbi_png(2, 5, 8, 6, 0, &filtered), DecodeLimits::default()).unwrap();
+        let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+        let pixels: Vec<[u8; 4]> = decoded.pixels().map(|p| p.0).collect();
+        // Opaque pixels only swap to RGBA; the rest are divided by alpha, rounding
+        assert_eq!(
+            pixels,
+            [
+                [30, 20, 10, 255],
+                [0, 0, 0, 0],
+                [60, 50, 40, 255],
+                [32, 64, 128, 128],
+                [3, 2, 1, 255],
+                [50, 100, 200, 255],
+                [255, 255, 255, 9],
+                [0, 0, 0, 255],
+                [0, 0, 255, 255],
+                [191, 128, 64, 12],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_grayscale_and_rgb() {
+        // 8-bit grayscale, 3x1, Sub-filtered: values 10, 20, 30
+        let gray = cgbi_png(3, 1, 8, 0, 0, &[1, 10, 10, 10]);
+        let decoded = image::load_from_memory(&normalize_cgbi_png(&gray, DecodeLimits::default()).unwrap()).unwrap().to_luma8();
+        assert_eq!(decoded.into_raw(), [10, 20, 30]);
+        
+        // 1-bit grayscale: one byte holds all eight pixels
+        let bits = cgbi_png(8, 1, 1, 0, 0, &[0, 0b1010_0000]);
+        let decoded = image::load_from_memory(&normalize_cgbi_png(&bits, DecodeLimits::default()).unwrap()).unwrap().to_luma8();
+        assert_eq!(decoded.into_raw(), [255, 0, 255, 0, 0, 0, 0, 0]);
+        
+        // RGB without alpha: only the channel swap applies
+        let rgb = cgbi_png(1, 1, 8, 2, 0, &[0, 30, 20, 10]);
+        let decoded = image::load_from_memory(&normalize_cgbi_png(&rgb, DecodeLimits::default()).unwrap()).unwrap().to_rgb8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [10, 20, 30]);
+        
+        // Gray + alpha is premultiplied too: half-transparent white, then fully transparent
+        let gray_alpha = cgbi_png(2, 1, 8, 4, 0, &[0, 128, 128, 7, 0]);
+        let decoded = image::load_from_memory(&normalize_cgbi_png(&gray_alpha, DecodeLimits::default()).unwrap()).unwrap();
+        assert_eq!(decoded.to_luma_alpha8().into_raw(), [255, 128, 0, 0]);
+        
+        let gray_alpha = cgbi_png(1, 1, 16, 4, 0, &[0, 0x80, 0x00, 0x80, 0x00]);
+        let decoded = image::load_from_memory(&normalize_cgbi_png(&gray_alpha, DecodeLimits::default()).unwrap()).unwrap();
+        assert_eq!(decoded.to_luma_alpha16().into_raw(), [65535, 0x8000]);
+    }
+
+    #[test]
+    fn test_normalize_adam7_interlaced() {
+        // 3x3 premultiplied BGRA, pixel (x, y) = [x, y, 10, 255]; all rows unfiltered.
+        // Adam7 splits 3x3 into passes 1 (1x1), 4 (1x1), 5 (2x1), 6 (1x2) and 7 (3x1)
+        let pass = |pixels: &[(u8, u8)], width: usize| -> Vec<u8> {
+            pixels
+                .chunks(width)
+                .flat_map(|row| std::iter::once(0).chain(row.iter().flat_map(|&(x, y)| [x, y, 10, 255])))
+                .collect()
+        };
+        let mut scanlines = pass(&[(0, 0)], 1);
+        scanlines.extend(pass(&[(2, 0)], 1));
+        scanlines.extend(pass(&[(0, 2), (2, 2)], 2));
+        scanlines.extend(pass(&[(1, 0), (1, 2)], 1));
+        scanlines.extend(pass(&[(0, 1), (1, 1), (2, 1)], 3));
+        
+        let normalized = normalize_cgbi_png(&cgbi_png(3, 3, 8, 6, 1, &scanlines), DecodeLimits::default()).unwrap();
+        let decoded = image::load_from_memory(&normalized).unwrap().to_rgba8();
+        for (x, y, pixel) in decoded.enumerate_pixels() {
+            assert_eq!(pixel.0, [10, y as u8, x as u8, 255]);
+        }
+        
+        let unknown_interlace = cgbi_png(1, 1, 8, 6, 2, &[0, 0, 0, 0, 255]);
+        assert!(matches!(
+            normalize_cgbi_png(&unknown_interlace, DecodeLimits::default()),
+            Err(crate::error::IpaError::PngNormalization(_))
+        ));
+    }
+
+    #[test]
+    fn test_crc32_matches_bitwise_reference() {
+        fn bitwise(bytes: &[u8]) -> u32 {
+            let mut crc = 0xFFFFFFFFu32;
+            for &byte in bytes {
+                crc ^= byte as u32;
+                for _ in 0..8 {
+                    crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+                }
+            }
+            !crc
+        }
+        
+        assert_eq!(crc32(b"IEND", b""), 0xAE426082);
+        let data: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        for len in [0, 1, 7, 255, 4096] {
+            let mut bytes = b"IDAT".to_vec();
+            bytes.extend(&data[..len]);
+            assert_eq!(crc32(b"IDAT", &data[..len]), bitwise(&bytes));
+        }
+    }
+
+    #[test]
+    fn test_verify_chunk_crcs() {
+        let mut png = crate::test_support::rgba_png(4, 4, [1, 2, 3, 255]);
+        verify_chunk_crcs(&png).unwrap();
+        
+        // Flip a byte of IHDR's data (width) so its stored CRC no longer matches
+        png[18] ^= 0xFF;
+        assert!(verify_chunk_crcs(&png).is_err());
+        assert!(verify_chunk_crcs(&png[..png.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn test_flatten_opaque_png() {
+        use crate::test_support::rgba_png;
+        
+        // IHDR color type lives at byte 25 (8 signature + 8 chunk header + 9 data)
+        let opaque = rgba_png(8, 8, [200, 100, 50, 255]);
+        assert_eq!(opaque[25], 6);
+        let flattened = flatten_opaque_png(&opaque, DecodeLimits::default()).unwrap();
+        assert_eq!(flattened[25], 2);
+        let decoded = image::load_from_memory(&flattened).unwrap().to_rgb8();
+        assert_eq!(decoded.get_pixel(3, 3).0, [200, 100, 50]);
+        
+        let translucent = rgba_png(8, 8, [200, 100, 50, 128]);
+        assert_eq!(flatten_opaque_png(&translucent, DecodeLimits::default()).unwrap(), translucent);
+        
+        assert!(has_transparency(&translucent, DecodeLimits::default()).unwrap());
+        assert!(!has_transparency(&opaque, DecodeLimits::default()).unwrap());
+        assert!(!has_transparency(&flattened, DecodeLimits::default()).unwrap());
+    }
+
+    #[test]
+    fn test_16_bit_alpha_is_not_rounded_to_opaque() {
+        let image = image::ImageBuffer::from_pixel(4, 4, image::Rgba([1000u16, 2000, 3000, 65534]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba16(image)
+            .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        
+        assert!(has_transparency(&png, DecodeLimits::default()).unwrap());
+        assert_eq!(flatten_opaque_png(&png, DecodeLimits::default()).unwrap(), png);
+    }
+
+    #[test]
+    fn test_resize_png() {
+        use crate::test_support::rgba_png;
+        
+        let wide = rgba_png(200, 100, [10, 20, 30, 255]);
+        let resized = resize_png(&wide, 64, DecodeLimits::default()).unwrap();
+        assert_eq!(png_dimensions(&resized).unwrap(), (64, 32));
+        let decoded = image::load_from_memory(&resized).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(32, 16).0, [10, 20, 30, 255]);
+        
+        let small = rgba_png(20, 20, [0, 0, 0, 255]);
+        assert_eq!(resize_png(&small, 40, DecodeLimits::default()).unwrap(), small);
+        assert_eq!(resize_png(&small, 20, DecodeLimits::default()).unwrap(), small);
+    }
+
+    #[test]
+    fn test_encode_icon() {
+        use crate::test_support::rgba_png;
+        
+        let png = rgba_png(4, 4, [0, 0, 0, 0]);
+        assert_eq!(encode_icon(&png, IconFormat::Png, DecodeLimits::default()).unwrap(), png);
+        
+        let jpeg = encode_icon(&png, IconFormat::Jpeg, DecodeLimits::default()).unwrap();
+        let decoded = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).unwrap().to_rgb8();
+        assert!(decoded.pixels().all(|p| p.0.iter().all(|&c| c > 250)), "transparent areas should be white");
+        
+        let translucent = rgba_png(4, 4, [200, 100, 50, 128]);
+        let webp = encode_icon(&translucent, IconFormat::Webp, DecodeLimits::default()).unwrap();
+        let decoded = image::load_from_memory_with_format(&webp, image::ImageFormat::WebP).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(1, 1).0, [200, 100, 50, 128]);
+    }
+
+    #[test]
+    fn test_decode_limits_reject_oversized_header() {
+        // A valid 100000x100000 RGBA header over an empty image stream
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&100_000u32.to_be_bytes());
+        ihdr.extend_from_slice(&100_000u32.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let mut bomb = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut bomb, b"IHDR", &ihdr);
+        write_chunk(&mut bomb, b"IDAT", &[0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        write_chunk(&mut bomb, b"IEND", &[]);
+        
+        let err = decode_png(&bomb, DecodeLimits::default()).unwrap_err();
+        assert!(matches!(err, crate::error::IpaError::Image(image::ImageError::Limits(_))), "{}", err);
+        
+        let small = crate::test_support::rgba_png(8, 8, [1, 2, 3, 255]);
+        let tight = DecodeLimits { max_width: 4, ..Default::default() };
+        assert!(decode_png(&small, tight).is_err());
+        assert!(decode_png(&small, DecodeLimits::default()).is_ok());
     }
 }
@@ -0,0 +1,165 @@
+use crate::error::{IpaError, Result};
+use crate::plist_parser::read_plist_dict;
+use plist::Value;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+const PROFILE_NAME: &str = "embedded.mobileprovision";
+
+/// Fields read from the app bundle's embedded.mobileprovision
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvisioningInfo {
+    /// First entry of the profile's TeamIdentifier array
+    #[serde(rename = "TeamIdentifier", skip_serializing_if = "Option::is_none")]
+    pub team_identifier: Option<String>,
+    /// `application-identifier` from the profile's Entitlements, e.g. `ABCDE12345.com.example.app`
+    #[serde(rename = "ApplicationIdentifier", skip_serializing_if = "Option::is_none")]
+    pub application_identifier: Option<String>,
+    /// ISO 8601, as written in the profile
+    #[serde(rename = "ExpirationDate", skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    /// Device UDIDs; empty for App Store and enterprise profiles
+    #[serde(rename = "ProvisionedDevices", default, skip_serializing_if = "Vec::is_empty")]
+    pub provisioned_devices: Vec<String>,
+}
+
+/// Reads `Payload/*.app/embedded.mobileprovision`, if the archive has one
+/// The profile is a CMS (PKCS#7) SignedData blob; the XML plist it signs is
+/// stored unencrypted inside it, so it is cut out by its `<?xml` and `</plist>`
+/// markers rather than by decoding the DER. Returns `None` when there is no profile
+pub fn extract_provisioning_info<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Option<ProvisioningInfo>> {
+    let Some(index) = find_profile(archive)? else {
+        return Ok(None);
+    };
+    
+    let file = archive.by_index(index)?;
+    let size = file.size();
+    let buffer = crate::archive_scan::read_to_vec(file, size)?;
+    
+    parse_profile(&buffer).map(Some)
+}
+
+/// Parses the contents of a .mobileprovision file
+pub fn parse_profile(data: &[u8]) -> Result<ProvisioningInfo> {
+    let dict = read_plist_dict(inner_plist(data)?)?
+        .ok_or_else(|| IpaError::Provisioning("profile plist is not a dictionary".to_string()))?;
+    
+    let team_identifier = match dict.get("TeamIdentifier") {
+        Some(Value::Array(ids)) => ids.iter().find_map(|id| id.as_string()).map(str::to_string),
+        _ => None,
+    };
+    let application_identifier = dict
+        .get("Entitlements")
+        .and_then(|e| e.as_dictionary())
+        .and_then(|e| e.get("application-identifier"))
+        .and_then(|id| id.as_string())
+        .map(str::to_string);
+    let expiration_date = match dict.get("ExpirationDate") {
+        Some(Value::Date(date)) => Some(date.to_xml_format()),
+        _ => None,
+    };
+    let provisioned_devices = dict
+        .get("ProvisionedDevices")
+        .and_then(|d| d.as_array())
+        .map(|devices| devices.iter().filter_map(|d| d.as_string()).map(str::to_string).collect())
+        .unwrap_or_default();
+    
+    Ok(ProvisioningInfo {
+        team_identifier,
+        application_identifier,
+        expiration_date,
+        provisioned_devices,
+    })
+}
+
+/// The signed XML plist inside the CMS wrapper
+fn inner_plist(data: &[u8]) -> Result<&[u8]> {
+    const START: &[u8] = b"<?xml";
+    const END: &[u8] = b"</plist>";
+    
+    let start = find(data, START)
+        .ok_or_else(|| IpaError::Provisioning("no XML plist inside the profile".to_string()))?;
+    let end = find(&data[start..], END)
+        .map(|end| start + end + END.len())
+        .ok_or_else(|| IpaError::Provisioning("profile plist is truncated".to_string()))?;
+    Ok(&data[start..end])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Index of `Payload/<Name>.app/embedded.mobileprovision` (not one inside a nested bundle)
+fn find_profile<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Option<usize>> {
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let Some((bundle, rest)) = file.name().strip_prefix("Payload/").and_then(|p| p.split_once('/')) else {
+            continue;
+        };
+        if bundle.ends_with(".app") && rest == PROFILE_NAME {
+            return Ok(Some(i));
+        }
+    }
+    
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_support::{build_zip, plist_xml};
+    use std::io::Cursor;
+    
+    /// A plist wrapped in bytes standing in for the CMS SignedData envelope
+    pub(crate) fn mobileprovision(team: &str, devices: &[&str]) -> Vec<u8> {
+        let mut entitlements = plist::Dictionary::new();
+        entitlements.insert(
+            "application-identifier".to_string(),
+            format!("{}.com.example.demo", team).into(),
+        );
+        let mut dict = plist::Dictionary::new();
+        dict.insert("TeamIdentifier".to_string(), Value::Array(vec![team.into()]));
+        dict.insert("Entitlements".to_string(), Value::Dictionary(entitlements));
+        dict.insert(
+            "ExpirationDate".to_string(),
+            Value::Date(plist::Date::from_xml_format("2027-01-31T12:00:00Z").unwrap()),
+        );
+        dict.insert(
+            "ProvisionedDevices".to_string(),
+            Value::Array(devices.iter().map(|&d| d.into()).collect()),
+        );
+        
+        let mut data = vec![0x30, 0x80, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+        data.extend(plist_xml(dict));
+        data.extend([0x00, 0x00, 0x31, 0x82, 0x01, 0x00]);
+        data
+    }
+    
+    #[test]
+    fn test_extract_provisioning_info() {
+        let profile = mobileprovision("ABCDE12345", &["00008030-001A2B3C4D5E6F70"]);
+        let data = build_zip(&[
+            ("Payload/Demo.app/Info.plist", b"".as_slice()),
+            ("Payload/Demo.app/Watch/W.app/embedded.mobileprovision", b"".as_slice()),
+            ("Payload/Demo.app/embedded.mobileprovision", &profile),
+        ]);
+        let info = extract_provisioning_info(&mut ZipArchive::new(Cursor::new(data)).unwrap())
+            .unwrap()
+            .unwrap();
+        
+        assert_eq!(info.team_identifier.as_deref(), Some("ABCDE12345"));
+        assert_eq!(info.application_identifier.as_deref(), Some("ABCDE12345.com.example.demo"));
+        assert_eq!(info.expiration_date.as_deref(), Some("2027-01-31T12:00:00Z"));
+        assert_eq!(info.provisioned_devices, vec!["00008030-001A2B3C4D5E6F70"]);
+        
+        let data = build_zip(&[("Payload/Demo.app/Info.plist", b"".as_slice())]);
+        assert!(extract_provisioning_info(&mut ZipArchive::new(Cursor::new(data)).unwrap())
+            .unwrap()
+            .is_none());
+        
+        assert!(matches!(parse_profile(b"\x30\x80 not a profile"), Err(IpaError::Provisioning(_))));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::error::{IpaError, Result};
+use crate::icon_extractor::{with_icon_writer, FailedIconWrites};
+use crate::{drop_failed_icon_writes, par_map, parse_ipa_with_writer, IpaInfo, ParseOptions};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Where one kept result sits in the spill file
+struct SpilledResult {
+    /// Position in the input, which orders array output
+    index: usize,
+    key: Option<String>,
+    offset: u64,
+    len: usize,
+}
+
+/// Like `parse_multiple_ipas`, but for batches too large to hold in memory
+///
+/// Each result is appended to an NDJSON file in `spill_dir` as soon as it is
+/// parsed. The combined JSON array (or `key_by` object) is then streamed from
+/// that file into `out`, so peak memory doesn't grow with the batch. The output
+/// matches `parse_multiple_ipas`: arrays keep input order, keyed objects are in
+/// key order and the last result for a repeated key wins. `group_by_vendor`
+/// needs every result at once and is rejected, and `dedup_by_hash` is ignored.
+/// Returns the number of results written.
+pub fn parse_multiple_ipas_spilled<P: AsRef<Path>, W: Write>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+    spill_dir: &Path,
+    pretty: bool,
+    out: W,
+) -> Result<usize> {
+    let mut out = BufWriter::new(out);
+    let keyed = options.key_by.is_some();
+    let mut count = 0;
+    
+    out.write_all(if keyed { b"{" } else { b"[" })?;
+    for_each_spilled(ipa_paths, options, spill_dir, |key, info| {
+        if count > 0 {
+            out.write_all(b",")?;
+        }
+        let element = if pretty {
+            out.write_all(b"\n  ")?;
+            serde_json::to_string_pretty(&info)?.replace('\n', "\n  ")
+        } else {
+            serde_json::to_string(&info)?
+        };
+        if let Some(key) = key {
+            serde_json::to_writer(&mut out, key)?;
+            out.write_all(if pretty { b": " } else { b":" })?;
+        }
+        out.write_all(element.as_bytes())?;
+        count += 1;
+        Ok(())
+    })?;
+    if pretty && count > 0 {
+        out.write_all(b"\n")?;
+    }
+    out.write_all(if keyed { b"}" } else { b"]" })?;
+    out.flush()?;
+    
+    Ok(count)
+}
+
+/// The streaming half of `parse_multiple_ipas_spilled`, for other output formats
+/// Calls `f(key, info)` for each kept result in output order (the key is `None`
+/// without `key_by`) and returns how many there were
+pub fn for_each_spilled<P, F>(ipa_paths: &[P], options: &ParseOptions, spill_dir: &Path, mut f: F) -> Result<usize>
+where
+    P: AsRef<Path>,
+    F: FnMut(Option<&str>, IpaInfo) -> Result<()>,
+{
+    if options.group_by_vendor.is_some() {
+        return Err(IpaError::Unsupported(
+            "group_by_vendor can't be combined with spilled results".to_string(),
+        ));
+    }
+    
+    std::fs::create_dir_all(spill_dir)?;
+    let (spill_file, spill_path) = create_spill_file(spill_dir)?;
+    
+    let result = spill_results(ipa_paths, options, spill_file).and_then(|(spilled, failed)| {
+        let spilled = output_order(spilled, options.key_by.is_some());
+        let mut file = BufReader::new(File::open(&spill_path)?);
+        for entry in &spilled {
+            file.seek(SeekFrom::Start(entry.offset))?;
+            let mut line = vec![0; entry.len];
+            file.read_exact(&mut line)?;
+            let mut info = serde_json::from_slice(&line)?;
+            drop_failed_icon_writes(&mut info, &failed, options);
+            f(entry.key.as_deref(), info)?;
+        }
+        Ok(spilled.len())
+    });
+    let _ = std::fs::remove_file(&spill_path);
+    result
+}
+
+/// Creates a spill file in `spill_dir` that no other call, in this process or
+/// another, is using: `ipa-results-<pid>-<n>.ndjson`, with `n` counting up
+fn create_spill_file(spill_dir: &Path) -> Result<(File, PathBuf)> {
+    static NEXT_SPILL: AtomicUsize = AtomicUsize::new(0);
+    
+    loop {
+        let n = NEXT_SPILL.fetch_add(1, Ordering::Relaxed);
+        let path = spill_dir.join(format!("ipa-results-{}-{}.ndjson", std::process::id(), n));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            // Left behind by an earlier process with the same pid
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Parses every file, appending each kept result to the spill file as one line
+/// Lines land in the order parses finish; the returned entries say where each is.
+/// Queued icon writes finish after their lines are spilled, so the ones that
+/// failed are returned alongside, to be applied as the lines are read back
+fn spill_results<P: AsRef<Path>>(
+    ipa_paths: &[P],
+    options: &ParseOptions,
+    spill_file: File,
+) -> Result<(Vec<SpilledResult>, FailedIconWrites)> {
+    let paths: Vec<(usize, PathBuf)> = ipa_paths.iter().map(|p| p.as_ref().to_path_buf()).enumerate().collect();
+    let spill = Mutex::new((BufWriter::new(spill_file), 0u64));
+    
+    let (written, failed) = with_icon_writer(options.serialize_icon_writes, |writer| {
+        par_map(&paths, |(index, path)| -> Result<Option<SpilledResult>> {
+            let info = match parse_ipa_with_writer(path, options, writer.slot(*index)) {
+                Ok(info) => info,
+                Err(e) => {
+                    log::warn!("Failed to parse {}: {}", path.display(), e);
+                    return Ok(None);
+                }
+            };
+            if options.filter.as_ref().is_some_and(|filter| !filter.matches(&info)) {
+                return Ok(None);
+            }
+            
+            let key = options.key_by.and_then(|key_by| key_by.key(path, &info));
+            // Results without a key are dropped, as in `results_to_value`
+            if options.key_by.is_some() && key.is_none() {
+                return Ok(None);
+            }
+            
+            let mut line = serde_json::to_vec(&info)?;
+            line.push(b'\n');
+            let mut spill = spill.lock().unwrap_or_else(|e| e.into_inner());
+            let (file, offset) = &mut *spill;
+            file.write_all(&line)?;
+            let entry = SpilledResult { index: *index, key, offset: *offset, len: line.len() - 1 };
+            *offset += line.len() as u64;
+            Ok(Some(entry))
+        })
+    });
+    
+    let spilled = written.into_iter().filter_map(Result::transpose).collect::<Result<Vec<_>>>()?;
+    spill.into_inner().unwrap_or_else(|e| e.into_inner()).0.flush()?;
+    Ok((spilled, failed))
+}
+
+/// Input order for arrays; key order for objects, keeping the last result per key
+fn output_order(mut spilled: Vec<SpilledResult>, keyed: bool) -> Vec<SpilledResult> {
+    if !keyed {
+        spilled.sort_by_key(|entry| entry.index);
+        return spilled;
+    }
+    
+    spilled.sort_by(|a, b| a.key.cmp(&b.key).then(a.index.cmp(&b.index)));
+    let mut kept: Vec<SpilledResult> = Vec::with_capacity(spilled.len());
+    for entry in spilled {
+        match kept.last_mut() {
+            Some(last) if last.key == entry.key => *last = entry,
+            _ => kept.push(entry),
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{build_zip, info_plist, plist_xml, TempDir};
+
+    #[test]
+    fn test_spilled_keyed_output() {
+        let dir = TempDir::new("spill");
+        let paths: Vec<PathBuf> = ["a", "b", "a"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.{}", name)));
+                let path = dir.join(format!("{}.ipa", i));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions {
+            key_by: Some(crate::KeyStrategy::BundleId),
+            ..Default::default()
+        };
+        
+        let mut out = Vec::new();
+        let count = parse_multiple_ipas_spilled(&paths, &options, &dir.join("spill"), true, &mut out).unwrap();
+        let spilled: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let leftover = std::fs::read_dir(dir.join("spill")).unwrap().count();
+        let in_memory = crate::parse_multiple_ipas(&paths, &options).unwrap();
+        
+        assert_eq!(count, 2);
+        assert_eq!(leftover, 0);
+        let file_names = |value: &serde_json::Value| {
+            value.as_object().unwrap().iter().map(|(key, app)| (key.clone(), app["FileName"].clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(file_names(&spilled), file_names(&in_memory));
+        let keys: Vec<_> = spilled.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, vec!["com.example.a", "com.example.b"]);
+        // The last file with a repeated key wins
+        assert_eq!(spilled["com.example.a"]["FileName"], "2.ipa");
+    }
+
+    #[test]
+    fn test_spilled_array_keeps_input_order() {
+        let dir = TempDir::new("spill-order");
+        let paths: Vec<PathBuf> = (0..16)
+            .rev()
+            .map(|i| {
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.app{}", i)));
+                let path = dir.join(format!("{}.ipa", i));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        
+        let mut out = Vec::new();
+        parse_multiple_ipas_spilled(&paths, &options, &dir.join("spill"), false, &mut out).unwrap();
+        let mut names = Vec::new();
+        for_each_spilled(&paths, &options, &dir.join("spill"), |key, info| {
+            assert!(key.is_none());
+            names.push(info.file_name.unwrap());
+            Ok(())
+        })
+        .unwrap();
+        let in_memory = crate::parse_multiple_ipas(&paths, &options).unwrap();
+        
+        let file_names = |value: serde_json::Value| -> Vec<_> {
+            value.as_array().unwrap().iter().map(|app| app["FileName"].as_str().unwrap().to_string()).collect()
+        };
+        let expected: Vec<_> = (0..16).rev().map(|i| format!("{}.ipa", i)).collect();
+        assert_eq!(file_names(serde_json::from_slice(&out).unwrap()), expected);
+        assert_eq!(file_names(in_memory), expected);
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_spill_calls_sharing_a_dir_dont_clash() {
+        let dir = TempDir::new("spill-shared");
+        let paths: Vec<PathBuf> = (0..4)
+            .map(|i| {
+                let plist = plist_xml(info_plist("Demo", &format!("com.example.app{}", i)));
+                let path = dir.join(format!("{}.ipa", i));
+                std::fs::write(&path, build_zip(&[("Payload/Demo.app/Info.plist", &plist)])).unwrap();
+                path
+            })
+            .collect();
+        let options = ParseOptions { extract_icons: false, ..Default::default() };
+        let spill_dir = dir.join("spill");
+        
+        // A second call on the same spill directory runs, and finishes, while the first is reading back
+        let mut outer = 0;
+        let mut inner = 0;
+        for_each_spilled(&paths, &options, &spill_dir, |_, _| {
+            if outer == 0 {
+                inner = parse_multiple_ipas_spilled(&paths, &options, &spill_dir, false, std::io::sink()).unwrap();
+            }
+            outer += 1;
+            Ok(())
+        })
+        .unwrap();
+        let leftover = std::fs::read_dir(&spill_dir).unwrap().count();
+        
+        assert_eq!((outer, inner, leftover), (4, 4, 0));
+    }
+}
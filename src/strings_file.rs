@@ -0,0 +1,181 @@
+use crate::plist_parser::read_plist_dict;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Parses a `.strings` localization table into its key/value pairs
+/// Compiled tables are binary (or XML) plists; source tables use the legacy
+/// `"key" = "value";` format, in UTF-8 or UTF-16 with a byte order mark.
+/// Returns None when the data is neither, or when the text is malformed
+pub fn parse_strings(data: &[u8]) -> Option<HashMap<String, String>> {
+    if data.starts_with(b"bplist") || data.trim_ascii_start().starts_with(b"<") {
+        let dict = read_plist_dict(data).ok()??;
+        return Some(
+            dict.into_iter()
+                .filter_map(|(key, value)| Some((key, value.into_string()?)))
+                .collect(),
+        );
+    }
+    
+    parse_legacy(&decode_text(data)?)
+}
+
+/// UTF-16 when there is a byte order mark, UTF-8 (BOM optional) otherwise
+fn decode_text(data: &[u8]) -> Option<String> {
+    let utf16 = |bytes: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| decode([pair[0], pair[1]])).collect();
+        String::from_utf16(&units).ok()
+    };
+    
+    match data {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec()).ok(),
+        _ => String::from_utf8(data.to_vec()).ok(),
+    }
+}
+
+/// `"key" = "value";` entries with C-style comments; `"key";` maps a key to itself
+fn parse_legacy(text: &str) -> Option<HashMap<String, String>> {
+    let mut chars = text.chars().peekable();
+    let mut entries = HashMap::new();
+    
+    loop {
+        skip_trivia(&mut chars)?;
+        if chars.peek().is_none() {
+            return Some(entries);
+        }
+        let key = read_token(&mut chars)?;
+        skip_trivia(&mut chars)?;
+        let value = match chars.next()? {
+            ';' => key.clone(),
+            '=' => {
+                skip_trivia(&mut chars)?;
+                let value = read_token(&mut chars)?;
+                skip_trivia(&mut chars)?;
+                if chars.next()? != ';' {
+                    return None;
+                }
+                value
+            }
+            _ => return None,
+        };
+        entries.insert(key, value);
+    }
+}
+
+/// Skips whitespace and comments; None on an unterminated block comment
+fn skip_trivia(chars: &mut Peekable<Chars>) -> Option<()> {
+    loop {
+        match chars.peek() {
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+            }
+            Some('/') => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                match lookahead.next() {
+                    Some('/') => {
+                        chars.find(|&c| c == '\n');
+                    }
+                    Some('*') => {
+                        chars.nth(1);
+                        let mut previous = '\0';
+                        loop {
+                            let c = chars.next()?;
+                            if previous == '*' && c == '/' {
+                                break;
+                            }
+                            previous = c;
+                        }
+                    }
+                    _ => return Some(()),
+                }
+            }
+            _ => return Some(()),
+        }
+    }
+}
+
+/// A quoted string, or a bare word of letters, digits and `_.$:/-`
+fn read_token(chars: &mut Peekable<Chars>) -> Option<String> {
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        return read_quoted(chars);
+    }
+    
+    let mut word = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_alphanumeric() || "_.$:/-".contains(c)) {
+            break;
+        }
+        word.push(c);
+        chars.next();
+    }
+    (!word.is_empty()).then_some(word)
+}
+
+/// The rest of a quoted string after its opening quote, with escapes resolved
+fn read_quoted(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut string = String::new();
+    
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => match chars.next()? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'U' | 'u' => {
+                    let unit = read_hex_unit(chars)?;
+                    if (0xD800..0xDC00).contains(&unit) {
+                        // A high surrogate; its pair follows as another \U escape
+                        if chars.next()? != '\\' || !matches!(chars.next()?, 'U' | 'u') {
+                            return None;
+                        }
+                        let low = read_hex_unit(chars)?;
+                        string.push_str(&String::from_utf16(&[unit, low]).ok()?);
+                    } else {
+                        string.push(char::from_u32(unit as u32)?);
+                    }
+                }
+                other => string.push(other),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+fn read_hex_unit(chars: &mut Peekable<Chars>) -> Option<u16> {
+    let digits: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+    u16::from_str_radix(&digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strings() {
+        let text = "/* Bundle name */\n\"CFBundleDisplayName\" = \"Caf\\U00e9 \\\"Demo\\\"\";\n// trailing\nNSHumanReadableCopyright = \"\u{1F600} \\UD83D\\UDE00\";\n\"Same\";\n";
+        let strings = parse_strings(text.as_bytes()).unwrap();
+        assert_eq!(strings["CFBundleDisplayName"], "Café \"Demo\"");
+        assert_eq!(strings["NSHumanReadableCopyright"], "\u{1F600} \u{1F600}");
+        assert_eq!(strings["Same"], "Same");
+        
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("\"CFBundleName\" = \"デモ\";".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(parse_strings(&utf16).unwrap()["CFBundleName"], "デモ");
+        
+        let mut dict = plist::Dictionary::new();
+        dict.insert("CFBundleDisplayName".to_string(), "Démo".into());
+        dict.insert("Count".to_string(), 3.into());
+        let mut binary = Vec::new();
+        plist::Value::Dictionary(dict).to_writer_binary(&mut binary).unwrap();
+        assert_eq!(parse_strings(&binary).unwrap(), HashMap::from([("CFBundleDisplayName".to_string(), "Démo".to_string())]));
+        
+        assert!(parse_strings(b"\"CFBundleName\" = \"unterminated").is_none());
+        assert!(parse_strings(b"\"CFBundleName\" = \"Demo\"").is_none());
+        assert!(parse_strings(b"/* open comment").is_none());
+    }
+}
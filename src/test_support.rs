@@ -0,0 +1,156 @@
+//! Helpers for building in-memory IPA fixtures and scratch directories in tests
+//! `tests/cli.rs` pulls this file in with `#[path]`, so it only uses dependencies
+
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zip::write::{FileOptions, ZipWriter};
+
+/// Builds an uncompressed ZIP archive from (name, contents) pairs, in order
+/// Duplicate names are written as-is, which the zip crate allows
+pub fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    
+    for (name, contents) in entries {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    
+    writer.finish().unwrap().into_inner()
+}
+
+/// Rewrites a `build_zip` archive in ZIP64 form: every central directory entry
+/// keeps its sizes and offset in a ZIP64 extra field, and a ZIP64 end of central
+/// directory record and locator precede the classic one, which is saturated.
+/// Entries named in `declared_sizes` claim that size (compressed and not) instead,
+/// standing in for files too big to build in a test; their data can't be read
+pub fn zip64(data: &[u8], declared_sizes: &[(&str, u64)]) -> Vec<u8> {
+    let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as u64;
+    
+    let eocd = data.len() - 22;
+    let entries = u16_at(eocd + 10) as u64;
+    let central_start = u32_at(eocd + 16) as usize;
+    let mut out = data[..central_start].to_vec();
+    
+    let mut pos = central_start;
+    for _ in 0..entries {
+        let (name_len, extra_len, comment_len) = (u16_at(pos + 28), u16_at(pos + 30), u16_at(pos + 32));
+        let name = std::str::from_utf8(&data[pos + 46..pos + 46 + name_len]).unwrap();
+        let (compressed, uncompressed) = match declared_sizes.iter().find(|(n, _)| *n == name) {
+            Some(&(_, size)) => (size, size),
+            None => (u32_at(pos + 20), u32_at(pos + 24)),
+        };
+        
+        let mut header = data[pos..pos + 46].to_vec();
+        header[6..8].copy_from_slice(&45u16.to_le_bytes());
+        header[20..28].fill(0xFF);
+        header[30..32].copy_from_slice(&((extra_len + 28) as u16).to_le_bytes());
+        header[42..46].fill(0xFF);
+        out.extend(header);
+        out.extend_from_slice(&data[pos + 46..pos + 46 + name_len]);
+        out.extend([0x01, 0x00, 24, 0x00]);
+        out.extend(uncompressed.to_le_bytes());
+        out.extend(compressed.to_le_bytes());
+        out.extend(u32_at(pos + 42).to_le_bytes());
+        out.extend_from_slice(&data[pos + 46 + name_len..pos + 46 + name_len + extra_len + comment_len]);
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    
+    let central_size = (out.len() - central_start) as u64;
+    let zip64_end = out.len() as u64;
+    out.extend(0x06064b50u32.to_le_bytes());
+    out.extend(44u64.to_le_bytes());
+    out.extend([45, 0, 45, 0]);
+    out.extend([0; 8]);
+    out.extend(entries.to_le_bytes());
+    out.extend(entries.to_le_bytes());
+    out.extend(central_size.to_le_bytes());
+    out.extend((central_start as u64).to_le_bytes());
+    out.extend(0x07064b50u32.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    out.extend(zip64_end.to_le_bytes());
+    out.extend(1u32.to_le_bytes());
+    out.extend(0x06054b50u32.to_le_bytes());
+    out.extend([0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+    out.extend([0xFF; 8]);
+    out.extend([0, 0]);
+    out
+}
+
+/// Serializes a plist dictionary as XML bytes
+pub fn plist_xml(dict: plist::Dictionary) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    plist::Value::Dictionary(dict).to_writer_xml(&mut buffer).unwrap();
+    buffer
+}
+
+/// A minimal Info.plist with the required fields set
+pub fn info_plist(name: &str, bundle_id: &str) -> plist::Dictionary {
+    let mut dict = plist::Dictionary::new();
+    dict.insert("CFBundleName".to_string(), name.into());
+    dict.insert("CFBundleShortVersionString".to_string(), "1.0".into());
+    dict.insert("CFBundleIdentifier".to_string(), bundle_id.into());
+    dict
+}
+
+/// An IPA for `Demo` (`com.example.demo`) whose Info.plist lists `names` under
+/// `CFBundleIconFiles`, with each of `files` stored under `Payload/Demo.app/`
+pub fn icon_ipa(names: &[&str], files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut plist = info_plist("Demo", "com.example.demo");
+    plist.insert(
+        "CFBundleIconFiles".to_string(),
+        plist::Value::Array(names.iter().map(|&name| name.into()).collect()),
+    );
+    let plist = plist_xml(plist);
+    let paths: Vec<String> = files.iter().map(|(name, _)| format!("Payload/Demo.app/{}", name)).collect();
+    let mut entries = vec![("Payload/Demo.app/Info.plist", plist.as_slice())];
+    entries.extend(paths.iter().map(String::as_str).zip(files.iter().map(|&(_, contents)| contents)));
+    build_zip(&entries)
+}
+
+/// Encodes a solid-color RGBA image as a standard PNG
+pub fn rgba_png(width: u32, height: u32, pixel: [u8; 4]) -> Vec<u8> {
+    let image = image::RgbaImage::from_pixel(width, height, image::Rgba(pixel));
+    let mut buffer = Vec::new();
+    image::DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
+        .unwrap();
+    buffer
+}
+
+/// A fresh directory under the system temp dir, removed with its contents on drop
+/// Named `ipa-<label>-<pid>-<n>`, so no two tests (or test binaries) share one,
+/// and a failing assertion doesn't leave it behind
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(label: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("ipa-{}-{}-{}", label, std::process::id(), n));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+    
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
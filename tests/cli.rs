@@ -0,0 +1,199 @@
+#![cfg(feature = "native")]
+
+// Shared with the unit tests; not every helper is needed here
+#[allow(dead_code)]
+#[path = "../src/test_support.rs"]
+mod test_support;
+
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use test_support::TempDir;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Writes a minimal IPA with just an Info.plist to `dir/<name>.ipa`
+fn write_ipa(dir: &Path, name: &str) -> PathBuf {
+    let mut dict = plist::Dictionary::new();
+    dict.insert("CFBundleName".to_string(), name.into());
+    dict.insert("CFBundleShortVersionString".to_string(), "1.0".into());
+    dict.insert("CFBundleIdentifier".to_string(), format!("com.example.{}", name).into());
+    let mut plist = Vec::new();
+    plist::Value::Dictionary(dict).to_writer_xml(&mut plist).unwrap();
+    
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file(format!("Payload/{}.app/Info.plist", name), options).unwrap();
+    writer.write_all(&plist).unwrap();
+    
+    let path = dir.join(format!("{}.ipa", name));
+    std::fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> serde_json::Value {
+    serde_json::from_slice(&run_with_stderr(args).0).unwrap()
+}
+
+/// Runs the CLI, which must succeed, and returns its stdout and stderr
+fn run_with_stderr(args: &[&str]) -> (Vec<u8>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ipa-parser"))
+        .args(args)
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(output.status.success(), "{}", stderr);
+    (output.stdout, stderr)
+}
+
+/// Fails unless every object in `value`, however deeply nested, has ascending keys
+fn assert_sorted(value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let keys: Vec<_> = map.keys().collect();
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]), "unsorted keys: {:?}", keys);
+            map.values().for_each(assert_sorted);
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(assert_sorted),
+        _ => {}
+    }
+}
+
+#[test]
+fn test_sort_flag_orders_keys() {
+    let dir = TempDir::new("cli-sort");
+    let ipa = write_ipa(&dir, "Zebra");
+    write_ipa(&dir, "Apple");
+    let ipa = ipa.to_str().unwrap();
+    let dir_arg = dir.to_str().unwrap();
+    
+    let unsorted = run(&["--file", ipa, "--no-icons"]);
+    let sorted = run(&["--file", ipa, "--no-icons", "--sort"]);
+    let pretty = run(&["--file", ipa, "--no-icons", "--sort", "--pretty"]);
+    let keyed = run(&["-m", "-d", dir_arg, "--no-icons", "--key-by", "filename", "--sort"]);
+    let array = run(&["-m", "-d", dir_arg, "--no-icons", "--sort", "--pretty"]);
+    let single_threaded = run(&["-m", "-d", dir_arg, "--no-icons", "--sort", "--threads", "1"]);
+    
+    // Without --sort, fields keep their declaration order
+    assert_eq!(unsorted.as_object().unwrap().keys().next().map(String::as_str), Some("AppName"));
+    assert_sorted(&sorted);
+    assert_sorted(&pretty);
+    assert_eq!(sorted, unsorted);
+    assert_sorted(&keyed);
+    assert_eq!(keyed.as_object().unwrap().len(), 2);
+    assert_sorted(&array);
+    assert_eq!(single_threaded, array);
+}
+
+#[test]
+fn test_quiet_and_verbose_flags() {
+    let dir = TempDir::new("cli-quiet");
+    write_ipa(&dir, "Demo");
+    std::fs::write(dir.join("Broken.ipa"), b"not a zip").unwrap();
+    let dir_arg = dir.to_str().unwrap();
+    
+    let (_, default) = run_with_stderr(&["-m", "-d", dir_arg, "--no-icons"]);
+    let (stdout, quiet) = run_with_stderr(&["-m", "-d", dir_arg, "--no-icons", "--quiet"]);
+    let (_, verbose) = run_with_stderr(&["-m", "-d", dir_arg, "--no-icons", "--verbose"]);
+    
+    assert!(default.contains("Found 2 IPA file(s), processing..."), "{}", default);
+    assert!(default.contains("Warning: Failed to parse "), "{}", default);
+    assert!(!default.contains("Parsing "), "{}", default);
+    assert_eq!(quiet, "");
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&stdout).unwrap().as_array().unwrap().len(), 1);
+    assert!(verbose.contains("Parsing "), "{}", verbose);
+}
+
+#[test]
+fn test_resume_skips_processed_files() {
+    let dir = TempDir::new("cli-resume");
+    write_ipa(&dir, "First");
+    let dir_arg = dir.to_str().unwrap();
+    let out = dir.join("out.json");
+    let out_arg = out.to_str().unwrap();
+    let args = ["-m", "-d", dir_arg, "--no-icons", "--key-by", "filename", "--resume", out_arg, "-o", out_arg];
+    
+    let (_, first_run) = run_with_stderr(&args);
+    let mut first: serde_json::Value = serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+    // A marker the second run would overwrite if it parsed First.ipa again
+    first["First.ipa"]["Timestamp"] = 0.into();
+    std::fs::write(&out, first.to_string()).unwrap();
+    write_ipa(&dir, "Second");
+    let (_, second_run) = run_with_stderr(&args);
+    let merged: serde_json::Value = serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+    
+    assert!(!first_run.contains("Skipping"), "{}", first_run);
+    assert!(second_run.contains("Skipping 1 already-processed IPA file(s)"), "{}", second_run);
+    let keys: Vec<_> = merged.as_object().unwrap().keys().collect();
+    assert_eq!(keys, ["First.ipa", "Second.ipa"]);
+    assert_eq!(merged["First.ipa"]["Timestamp"], 0);
+    assert_eq!(merged["Second.ipa"]["AppName"], "Second");
+}
+
+#[test]
+fn test_spilled_csv_matches_in_memory_rows() {
+    let dir = TempDir::new("cli-spill-csv");
+    let spill = dir.join("spill");
+    std::fs::create_dir_all(&spill).unwrap();
+    for name in ["Zeta", "Alpha", "Mid"] {
+        write_ipa(&dir, name);
+    }
+    let dir_arg = dir.to_str().unwrap();
+    let base = ["-m", "-d", dir_arg, "--no-icons", "--format", "csv"];
+    
+    let (in_memory, _) = run_with_stderr(&base);
+    let (spilled, _) = run_with_stderr(&[&base[..], &["--spill-dir", spill.to_str().unwrap()]].concat());
+    
+    // Timestamps differ between runs, so compare everything before them
+    let strip = |csv: &[u8]| -> Vec<String> {
+        let csv = String::from_utf8(csv.to_vec()).unwrap();
+        csv.lines().map(|line| line.rsplit_once(',').unwrap().0.to_string()).collect()
+    };
+    let rows = strip(&spilled);
+    assert_eq!(rows.len(), 4);
+    assert!(rows[0].starts_with("AppName,"), "{:?}", rows);
+    assert_eq!(rows, strip(&in_memory));
+}
+
+#[test]
+fn test_resume_rejects_unreadable_output_shapes() {
+    let dir = TempDir::new("cli-resume-reject");
+    write_ipa(&dir, "First");
+    let out = dir.join("out.json");
+    let base = ["-m", "-d", dir.to_str().unwrap(), "--no-icons", "--key-by", "filename", "--resume", out.to_str().unwrap()];
+    
+    for extra in [&["--summary"][..], &["--format", "csv"], &["--format", "yaml"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ipa-parser"))
+            .args(base.iter().chain(extra))
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{:?} was accepted", extra);
+    }
+}
+
+#[test]
+fn test_missing_file_exits_with_io_code() {
+    let dir = TempDir::new("cli-missing");
+    let missing = dir.join("missing.ipa");
+    let output = Command::new(env!("CARGO_BIN_EXE_ipa-parser"))
+        .args(["-f", missing.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("File not found"));
+}
+
+#[test]
+fn test_manifest_hash_mismatch_exits_with_hash_code() {
+    let dir = TempDir::new("cli-manifest-mismatch");
+    write_ipa(&dir, "Demo");
+    let manifest = dir.join("manifest.json");
+    std::fs::write(&manifest, r#"[{"path": "Demo.ipa", "expected_hash": "0000"}]"#).unwrap();
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_ipa-parser"))
+        .args(["--manifest", manifest.to_str().unwrap(), "--no-icons"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(7));
+}
@@ -0,0 +1,101 @@
+#!/usr/bin/env python3
+"""Regenerates icon.png (CgBI) and icon.expected.png in this directory.
+
+icon.png is built the way Xcode's PNG compressor lays a CgBI icon out: a CgBI
+chunk ahead of IHDR, premultiplied BGRA samples, per-row filters computed on
+the premultiplied bytes, a raw deflate stream with no zlib header, and IDAT
+split into several chunks.
+
+icon.expected.png holds the pixels normalization should produce: BGRA swapped
+back to RGBA, and each colour channel divided by alpha, rounding to nearest:
+(c * 255 + a / 2) / a, with zero alpha giving transparent black. That is the
+same rounding `unpremultiply` uses, so the test pins filtering, channel order,
+IDAT handling and the alpha edge cases, not agreement with another tool.
+
+This is not `pngcrush -revert-iphone` output, and the test does not check that
+normalize_cgbi_png is byte-identical to it: pngcrush with the iPhone patches
+was not available to build the fixture, and file bytes also depend on the
+encoder's filter and zlib choices, which normalization doesn't try to copy.
+"""
+import struct
+import zlib
+from pathlib import Path
+
+WIDTH, HEIGHT = 32, 32
+
+
+def source_pixel(x, y):
+    # Every alpha value from 0 to 255 appears in the first 8 rows; colours vary
+    alpha = (y * WIDTH + x) % 256 if y < 8 else (x * 8 + y * 3) % 256
+    return ((x * 37 + y * 11) % 256, (x * 5 + y * 53) % 256, (255 - x * 7 - y * 3) % 256, alpha)
+
+
+def premultiply(c, a):
+    return (c * a + 127) // 255
+
+
+def unpremultiply(c, a):
+    if a == 0:
+        return 0
+    if a == 255:
+        return c
+    return min(255, (c * 255 + a // 2) // a)
+
+
+def paeth(a, b, c):
+    p = a + b - c
+    pa, pb, pc = abs(p - a), abs(p - b), abs(p - c)
+    if pa <= pb and pa <= pc:
+        return a
+    return b if pb <= pc else c
+
+
+def filter_row(kind, row, prev, bpp=4):
+    out = bytearray([kind])
+    for i, value in enumerate(row):
+        left = row[i - bpp] if i >= bpp else 0
+        up = prev[i]
+        up_left = prev[i - bpp] if i >= bpp else 0
+        predictor = [0, left, up, (left + up) // 2, paeth(left, up, up_left)][kind]
+        out.append((value - predictor) % 256)
+    return bytes(out)
+
+
+def chunk(kind, data):
+    return struct.pack(">I", len(data)) + kind + data + struct.pack(">I", zlib.crc32(kind + data))
+
+
+def main():
+    here = Path(__file__).resolve().parent
+    ihdr = struct.pack(">IIBBBBB", WIDTH, HEIGHT, 8, 6, 0, 0, 0)
+    pixels = [[source_pixel(x, y) for x in range(WIDTH)] for y in range(HEIGHT)]
+
+    # The CgBI file: premultiplied BGRA, every filter type in turn
+    scanlines = b""
+    prev = bytes(WIDTH * 4)
+    for y, row in enumerate(pixels):
+        stored = bytes(v for (r, g, b, a) in row for v in (premultiply(b, a), premultiply(g, a), premultiply(r, a), a))
+        scanlines += filter_row(y % 5, stored, prev)
+        prev = stored
+    raw = zlib.compressobj(9, zlib.DEFLATED, -15)
+    deflated = raw.compress(scanlines) + raw.flush()
+    cgbi = b"\x89PNG\r\n\x1a\n" + chunk(b"CgBI", bytes([0x50, 0x00, 0x20, 0x06])) + chunk(b"IHDR", ihdr)
+    for start in range(0, len(deflated), 256):
+        cgbi += chunk(b"IDAT", deflated[start:start + 256])
+    cgbi += chunk(b"IEND", b"")
+    (here / "icon.png").write_bytes(cgbi)
+
+    # The reference: what the stored bytes mean once reverted
+    scanlines = b""
+    for row in pixels:
+        reverted = []
+        for (r, g, b, a) in row:
+            reverted += [unpremultiply(premultiply(c, a), a) for c in (r, g, b)] + [a]
+        scanlines += b"\x00" + bytes(reverted)
+    reference = b"\x89PNG\r\n\x1a\n" + chunk(b"IHDR", ihdr) + chunk(b"IDAT", zlib.compress(scanlines, 9))
+    reference += chunk(b"IEND", b"")
+    (here / "icon.expected.png").write_bytes(reference)
+
+
+if __name__ == "__main__":
+    main()